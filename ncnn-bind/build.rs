@@ -1,10 +1,13 @@
+#[cfg(feature = "bindgen")]
 extern crate bindgen;
 use cmake::Config;
+use sha2::{Digest, Sha256};
 
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str;
 
@@ -12,73 +15,848 @@ fn output_dir() -> PathBuf {
     PathBuf::from(env::var("OUT_DIR").unwrap())
 }
 
-fn ncnn_src_dir() -> PathBuf {
-    // I'm using master
-    let master_dir = "ncnn-master";
-    // let tag_dir = format!("ncnn-src-{}", ncnn_tag());
-    output_dir().join(master_dir)
+fn ncnn_tag() -> String {
+    const DEFAULT_NCNN_TAG: &str = "20220729";
+    env::var("NCNN_TAG").unwrap_or(DEFAULT_NCNN_TAG.to_string())
 }
 
-fn _ncnn_tag() -> String {
-    const DEFAULT_NCNN_TAG: &'static str = "20220729";
-    env::var("NCNN_TAG").unwrap_or(DEFAULT_NCNN_TAG.to_string())
+/// Where fetched sources and finished builds are kept between builds.
+///
+/// `NCNN_CACHE_DIR` can be shared by several crates or checkouts; by default the cache
+/// lives in the target directory, so it survives `cargo clean -p` and feature changes.
+fn cache_dir() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=NCNN_CACHE_DIR");
+    match env::var_os("NCNN_CACHE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        // OUT_DIR is `<target>/<profile>/build/ncnn-bind-<hash>/out`
+        None => output_dir()
+            .ancestors()
+            .nth(3)
+            .map(|profile_dir| profile_dir.join("ncnn"))
+            .unwrap_or_else(output_dir),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Checks `archive` against `NCNN_SRC_SHA256`, if it is set.
+fn verify_checksum(archive: &Path, actual: &str) -> io::Result<()> {
+    let expected = match env::var("NCNN_SRC_SHA256") {
+        Ok(expected) => expected.trim().to_lowercase(),
+        Err(_) => return Ok(()),
+    };
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "checksum mismatch for {}: expected {}, got {}",
+                archive.display(),
+                expected,
+                actual
+            ),
+        ))
+    }
+}
+
+/// Finds the source root in an unpacked archive, which is either the directory itself
+/// or its only subdirectory.
+fn source_root(dir: &Path) -> io::Result<PathBuf> {
+    if dir.join("CMakeLists.txt").is_file() {
+        return Ok(dir.to_path_buf());
+    }
+    let entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    match entries.as_slice() {
+        [root] if root.join("CMakeLists.txt").is_file() => Ok(root.clone()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no CMakeLists.txt in {}", dir.display()),
+        )),
+    }
 }
 
-fn fetch() -> io::Result<()> {
-    let target_dir = ncnn_src_dir();
+fn run(command: &mut Command) -> io::Result<()> {
+    let status = command.status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{:?} failed with {}",
+            command, status
+        )))
+    }
+}
 
+/// Scratch directory next to `target_dir` that only this build writes to, as concurrent
+/// builds sharing `NCNN_CACHE_DIR` may fill the same cache entry at once.
+fn partial_dir(target_dir: &Path) -> io::Result<PathBuf> {
+    let partial_dir = target_dir.with_extension(format!("partial-{}", std::process::id()));
+    if partial_dir.exists() {
+        fs::remove_dir_all(&partial_dir)?;
+    }
+    Ok(partial_dir)
+}
+
+/// Moves a filled scratch directory into the cache. Losing the race to another build that
+/// filled the same entry is fine, its contents are the same.
+fn commit_partial_dir(partial_dir: &Path, target_dir: &Path) -> io::Result<()> {
+    match fs::rename(partial_dir, target_dir) {
+        Err(_) if target_dir.exists() => fs::remove_dir_all(partial_dir),
+        result => result,
+    }
+}
+
+/// Unpacks a `.tar`, `.tar.gz`, `.tar.xz` or `.zip` release archive into the cache,
+/// keyed by its checksum so a changed archive is never mistaken for an old one.
+fn unpack(archive: &Path, sha256: &str) -> io::Result<PathBuf> {
+    let target_dir = cache_dir().join("src").join(&sha256[..16]);
     if target_dir.exists() {
-        return Ok(());
+        return source_root(&target_dir);
+    }
+
+    // unpack next to the final location, so an interrupted build leaves nothing behind
+    let partial_dir = partial_dir(&target_dir)?;
+    fs::create_dir_all(&partial_dir)?;
+    let is_zip = archive
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    if is_zip && !cfg!(windows) {
+        run(Command::new("unzip")
+            .arg("-q")
+            .arg(archive)
+            .arg("-d")
+            .arg(&partial_dir))?;
+    } else {
+        // bsdtar, which Windows ships as `tar`, reads zip archives as well
+        run(Command::new("tar")
+            .arg("-xf")
+            .arg(archive)
+            .arg("-C")
+            .arg(&partial_dir))?;
     }
+    commit_partial_dir(&partial_dir, &target_dir)?;
+    source_root(&target_dir)
+}
 
-    let status = Command::new("git")
+/// Checks the commit checked out in `dir` against `NCNN_SRC_COMMIT`, if it is set.
+fn verify_commit(dir: &Path) -> io::Result<()> {
+    let expected = match env::var("NCNN_SRC_COMMIT") {
+        Ok(expected) => expected.trim().to_lowercase(),
+        Err(_) => return Ok(()),
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git rev-parse HEAD failed in {} with {}",
+            dir.display(),
+            output.status
+        )));
+    }
+    let actual = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "commit mismatch for {}: expected {}, got {}",
+                dir.display(),
+                expected,
+                actual
+            ),
+        ))
+    }
+}
+
+/// Clones `tag` into the cache, unless an earlier build already did.
+///
+/// A clone has no archive to check against `NCNN_SRC_SHA256`, so setting it is an error
+/// here; `NCNN_SRC_COMMIT` pins the commit the tag must point to instead.
+fn fetch(tag: &str) -> io::Result<PathBuf> {
+    if env::var_os("NCNN_SRC_SHA256").is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "NCNN_SRC_SHA256 only applies to an archive in NCNN_SRC_DIR; \
+             set NCNN_SRC_COMMIT to pin the commit of NCNN_TAG instead",
+        ));
+    }
+    let target_dir = cache_dir().join("src").join(format!("ncnn-{}", tag));
+    if target_dir.exists() {
+        verify_commit(&target_dir)?;
+        return Ok(target_dir);
+    }
+
+    let partial_dir = partial_dir(&target_dir)?;
+    if let Some(parent) = partial_dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    run(Command::new("git")
         .arg("clone")
         .arg("--recursive")
         .arg("--depth=1")
         .arg("-b")
-        // .arg(ncnn_tag())
-        .arg("master")
+        .arg(tag)
         .arg("https://github.com/Tencent/ncnn")
-        .arg(&target_dir)
-        .status()?;
+        .arg(&partial_dir))
+    .map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "{}\nfetching ncnn {} failed; for offline builds set NCNN_SRC_DIR \
+                 to an ncnn source directory or release archive",
+                e, tag
+            ),
+        )
+    })?;
+    if let Err(e) = verify_commit(&partial_dir) {
+        fs::remove_dir_all(&partial_dir)?;
+        return Err(e);
+    }
+    commit_partial_dir(&partial_dir, &target_dir)?;
+    Ok(target_dir)
+}
 
-    if status.success() {
-        Ok(())
+/// The ncnn source tree to build: `NCNN_SRC_DIR` if set, otherwise `NCNN_TAG` from GitHub.
+///
+/// Returns the tree and a string identifying it for the build cache, or `None` if the
+/// tree can change between builds.
+fn ncnn_src_dir() -> io::Result<(PathBuf, Option<String>)> {
+    println!("cargo:rerun-if-env-changed=NCNN_SRC_DIR");
+    let src = match env::var_os("NCNN_SRC_DIR") {
+        Some(src) => PathBuf::from(src),
+        None => {
+            let tag = ncnn_tag();
+            return Ok((fetch(&tag)?, Some(format!("tag:{}", tag))));
+        }
+    };
+
+    if src.is_dir() {
+        println!("cargo:rerun-if-changed={}", src.display());
+        Ok((source_root(&src)?, None))
+    } else if src.is_file() {
+        let sha256 = sha256_file(&src)?;
+        verify_checksum(&src, &sha256)?;
+        Ok((unpack(&src, &sha256)?, Some(format!("archive:{}", sha256))))
     } else {
-        Err(io::Error::new(io::ErrorKind::Other, "fetch failed"))
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "NCNN_SRC_DIR is set to {}, which does not exist",
+                src.display()
+            ),
+        ))
+    }
+}
+
+/// ncnn CMake options that can be overridden by an environment variable of the same name.
+///
+/// `NCNN_STDIO`, `NCNN_STRING` and `NCNN_PIXEL` are left out, the C API needs them.
+const CMAKE_OPTION_VARS: [&str; 20] = [
+    "NCNN_OPENMP",
+    "NCNN_SIMPLEOCV",
+    "NCNN_BENCHMARK",
+    "NCNN_INT8",
+    "NCNN_BF16",
+    "NCNN_PIXEL_ROTATE",
+    "NCNN_PIXEL_AFFINE",
+    "NCNN_PIXEL_DRAWING",
+    "NCNN_RUNTIME_CPU",
+    "NCNN_SSE2",
+    "NCNN_AVX",
+    "NCNN_FMA",
+    "NCNN_F16C",
+    "NCNN_AVX2",
+    "NCNN_AVXVNNI",
+    "NCNN_AVX512",
+    "NCNN_AVX512VNNI",
+    "NCNN_ARM82",
+    "NCNN_ARM82DOT",
+    "NCNN_VULKAN_ONLINE_SPIRV",
+];
+
+// `LAYER_TYPES`, every layer ncnn can be built with
+include!("src/layer_types.rs");
+
+/// Layers the net itself creates, needed by every model.
+const CORE_LAYERS: [&str; 4] = ["Input", "Split", "Packing", "Cast"];
+
+/// Layers a layer creates internally, for padding, fused activations, int8 and groups.
+fn layer_deps(layer: &str) -> &'static [&'static str] {
+    const ACTIVATIONS: [&str; 5] = ["ReLU", "Clip", "Sigmoid", "Mish", "HardSwish"];
+    const CONVOLUTION: [&str; 11] = [
+        "Padding",
+        "Crop",
+        "Flatten",
+        "Quantize",
+        "Dequantize",
+        "Requantize",
+        ACTIVATIONS[0],
+        ACTIVATIONS[1],
+        ACTIVATIONS[2],
+        ACTIVATIONS[3],
+        ACTIVATIONS[4],
+    ];
+    match layer {
+        "Convolution" | "Convolution1D" | "Convolution3D" | "Deconvolution" | "Deconvolution1D"
+        | "Deconvolution3D" | "InnerProduct" | "DeformableConv2D" => &CONVOLUTION,
+        "ConvolutionDepthWise" => &["Convolution"],
+        "ConvolutionDepthWise1D" => &["Convolution1D"],
+        "ConvolutionDepthWise3D" => &["Convolution3D"],
+        "DeconvolutionDepthWise" => &["Deconvolution"],
+        "DeconvolutionDepthWise1D" => &["Deconvolution1D"],
+        "DeconvolutionDepthWise3D" => &["Deconvolution3D"],
+        "Pooling" | "Pooling1D" | "Pooling3D" => &["Padding"],
+        "YoloDetectionOutput" | "Yolov3DetectionOutput" => &["Softmax", "Sigmoid"],
+        _ => &[],
+    }
+}
+
+/// Reads the layer types a text `.param` file uses.
+fn param_layer_types(path: &Path) -> io::Result<Vec<String>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "{}: {} (only text .param files are supported)",
+                path.display(),
+                e
+            ),
+        )
+    })?;
+    let mut lines = content.lines();
+    if lines.next().map(str::trim) != Some("7767517") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not an ncnn .param file", path.display()),
+        ));
+    }
+    // the second line holds the layer and blob counts
+    Ok(lines
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect())
+}
+
+/// `WITH_LAYER_*` options that leave out every layer the models in
+/// `NCNN_LAYERS_FROM_PARAMS` do not use, or nothing if it is not set.
+fn layer_options() -> io::Result<Vec<(String, String)>> {
+    println!("cargo:rerun-if-env-changed=NCNN_LAYERS_FROM_PARAMS");
+    println!("cargo:rerun-if-changed=src/layer_types.rs");
+    let params = match env::var_os("NCNN_LAYERS_FROM_PARAMS") {
+        Some(params) => params,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut used: Vec<String> = CORE_LAYERS.iter().map(|layer| layer.to_string()).collect();
+    for path in env::split_paths(&params) {
+        println!("cargo:rerun-if-changed={}", path.display());
+        used.extend(param_layer_types(&path)?);
+    }
+    let mut i = 0;
+    while i < used.len() {
+        for dep in layer_deps(&used[i]) {
+            if !used.iter().any(|layer| layer == dep) {
+                used.push(dep.to_string());
+            }
+        }
+        i += 1;
+    }
+
+    Ok(LAYER_TYPES
+        .iter()
+        .filter(|layer| !used.iter().any(|used| used == *layer))
+        .map(|layer| {
+            (
+                format!("WITH_LAYER_{}", layer.to_lowercase()),
+                "OFF".to_string(),
+            )
+        })
+        .collect())
+}
+
+/// Reads a CMake boolean from an environment variable, if it is set.
+fn env_cmake_bool(name: &str) -> Option<&'static str> {
+    println!("cargo:rerun-if-env-changed={}", name);
+    let value = env::var(name).ok()?;
+    match value.trim().to_uppercase().as_str() {
+        "ON" | "1" | "TRUE" | "YES" => Some("ON"),
+        "OFF" | "0" | "FALSE" | "NO" => Some("OFF"),
+        _ => panic!("{} must be ON or OFF, not {:?}", name, value),
     }
 }
 
-fn build() -> io::Result<()> {
-    let mut config = Config::new(ncnn_src_dir());
-    config.define("NCNN_BUILD_TOOLS", "OFF");
-    config.define("NCNN_BUILD_EXAMPLES", "OFF");
-    config.define("NCNN_BUILD_BENCHMARK", "OFF");
-    config.define("CMAKE_BUILD_TYPE", "Release");
+/// CMake options ncnn is built with; part of the build cache key.
+fn cmake_options() -> io::Result<Vec<(String, String)>> {
+    let mut options: Vec<(String, String)> = [
+        ("NCNN_BUILD_TOOLS", "OFF"),
+        ("NCNN_BUILD_EXAMPLES", "OFF"),
+        ("NCNN_BUILD_BENCHMARK", "OFF"),
+        ("CMAKE_BUILD_TYPE", "Release"),
+    ]
+    .iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+    let mut set = |name: &str, value: &str| {
+        options.retain(|(option, _)| option != name);
+        options.push((name.to_string(), value.to_string()));
+    };
 
     if cfg!(feature = "vulkan") {
-        config.define("NCNN_VULKAN", "ON");
+        set("NCNN_VULKAN", "ON");
     }
 
     if use_dynamic_linking() {
-        config.define("NCNN_SHARED_LIB", "ON");
+        set("NCNN_SHARED_LIB", "ON");
+    }
+
+    // OpenMP is only linked with the `openmp` feature, so ncnn must not use it otherwise
+    set(
+        "NCNN_OPENMP",
+        if cfg!(feature = "openmp") {
+            "ON"
+        } else {
+            "OFF"
+        },
+    );
+
+    if cfg!(feature = "simpleocv") {
+        set("NCNN_SIMPLEOCV", "ON");
+    }
+
+    if cfg!(feature = "benchmark") {
+        set("NCNN_BENCHMARK", "ON");
+    }
+
+    // environment variables win over features
+    for name in CMAKE_OPTION_VARS {
+        if let Some(value) = env_cmake_bool(name) {
+            if name == "NCNN_OPENMP" && value == "ON" && !cfg!(feature = "openmp") {
+                panic!("NCNN_OPENMP=ON needs the `openmp` feature to link OpenMP");
+            }
+            set(name, value);
+        }
+    }
+
+    options.extend(layer_options()?);
+    Ok(options)
+}
+
+/// Builds and installs ncnn, returning the install prefix.
+///
+/// Installs are cached by source, options and target, so other crates and later builds
+/// with the same configuration reuse them instead of building again. A source directory
+/// may have changed since the last build, so it is always handed to CMake, which rebuilds
+/// only what changed. Builds of the same entry hold a lock on it, so concurrent builds
+/// wait for each other instead of running CMake in the same directory.
+fn build() -> io::Result<PathBuf> {
+    let (src, src_id) = ncnn_src_dir()?;
+    let options = cmake_options()?;
+
+    let mut key = Sha256::new();
+    match &src_id {
+        Some(id) => key.update(id.as_bytes()),
+        None => key.update(format!("dir:{}", src.display()).as_bytes()),
+    }
+    key.update(env::var("TARGET").unwrap().as_bytes());
+    for (name, value) in &options {
+        key.update(format!("\n{}={}", name, value).as_bytes());
+    }
+    let prefix = cache_dir().join("build").join(&hex(&key.finalize())[..16]);
+    fs::create_dir_all(&prefix)?;
+    // held until this returns, and released by the OS if the build script dies first
+    let lock = fs::File::create(prefix.with_extension("lock"))?;
+    lock.lock()?;
+    let stamp = prefix.join(".complete");
+    if src_id.is_some() && stamp.is_file() {
+        return Ok(prefix);
+    }
+
+    let mut config = Config::new(&src);
+    config.out_dir(&prefix);
+    for (name, value) in &options {
+        config.define(name, value);
+    }
+    config.build();
+
+    if let Some(id) = src_id {
+        fs::write(&stamp, format!("{}\n", id))?;
+    }
+    Ok(prefix)
+}
+
+/// An ncnn installation: where its headers are and what it takes to link it.
+struct Ncnn {
+    /// Directory containing `c_api.h`.
+    include_dir: PathBuf,
+    lib_dirs: Vec<PathBuf>,
+    /// Libraries ncnn itself depends on, linked after it.
+    deps: Vec<String>,
+    vulkan: bool,
+    /// Whether ncnn was built with OpenMP, if that is recorded anywhere.
+    openmp: Option<bool>,
+}
+
+impl Ncnn {
+    /// An installation laid out as `<prefix>/include/ncnn` and `<prefix>/lib`.
+    fn from_prefix(prefix: &Path) -> Ncnn {
+        let lib_dir = prefix.join("lib");
+        let vulkan = cfg!(feature = "vulkan");
+        Ncnn {
+            include_dir: prefix.join("include").join("ncnn"),
+            deps: glslang_deps(&lib_dir, vulkan),
+            openmp: openmp_from_lib_dir(&lib_dir),
+            lib_dirs: vec![lib_dir],
+            vulkan,
+        }
+    }
+}
+
+/// Returns the directory holding `c_api.h`, accepting both `<prefix>/include` and
+/// `<prefix>/include/ncnn`.
+fn ncnn_include_dir(dir: &Path) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("ncnn")]
+        .into_iter()
+        .find(|dir| dir.join("c_api.h").is_file())
+}
+
+/// glslang libraries a static ncnn with Vulkan needs, if it installed them next to itself.
+fn glslang_deps(lib_dir: &Path, vulkan: bool) -> Vec<String> {
+    const GLSLANG_LIBS: [&str; 6] = [
+        "glslang",
+        "SPIRV",
+        "MachineIndependent",
+        "OGLCompiler",
+        "OSDependent",
+        "GenericCodeGen",
+    ];
+    if !vulkan || use_dynamic_linking() {
+        return Vec::new();
     }
+    GLSLANG_LIBS
+        .iter()
+        .filter(|lib| {
+            lib_dir.join(format!("lib{}.a", lib)).is_file()
+                || lib_dir.join(format!("{}.lib", lib)).is_file()
+        })
+        .map(|lib| lib.to_string())
+        .collect()
+}
+
+/// `NCNN_INCLUDE_DIR` and `NCNN_LIB_DIR` take precedence over any discovery.
+fn find_from_env() -> Result<Ncnn, String> {
+    println!("cargo:rerun-if-env-changed=NCNN_INCLUDE_DIR");
+    println!("cargo:rerun-if-env-changed=NCNN_LIB_DIR");
 
-    let dst = config.build();
+    let include_dir = env::var_os("NCNN_INCLUDE_DIR").ok_or("not set")?;
+    let include_dir = ncnn_include_dir(Path::new(&include_dir)).unwrap_or_else(|| {
+        panic!(
+            "NCNN_INCLUDE_DIR is set to {:?}, but c_api.h is not there",
+            include_dir
+        )
+    });
+    let lib_dirs: Vec<PathBuf> = env::var_os("NCNN_LIB_DIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    let vulkan = cfg!(feature = "vulkan");
+    Ok(Ncnn {
+        include_dir,
+        deps: lib_dirs
+            .first()
+            .map(|dir| glslang_deps(dir, vulkan))
+            .unwrap_or_default(),
+        openmp: lib_dirs.first().and_then(|dir| openmp_from_lib_dir(dir)),
+        lib_dirs,
+        vulkan,
+    })
+}
+
+fn find_from_pkg_config() -> Result<Ncnn, String> {
+    let lib = pkg_config::Config::new()
+        .cargo_metadata(false)
+        .env_metadata(true)
+        .statik(!use_dynamic_linking())
+        .probe("ncnn")
+        .map_err(|e| {
+            let message = e.to_string();
+            let line = message.lines().find(|l| !l.trim().is_empty());
+            line.unwrap_or_default().trim().to_string()
+        })?;
+    let include_dir = lib
+        .include_paths
+        .iter()
+        .find_map(|dir| ncnn_include_dir(dir))
+        .ok_or("ncnn.pc found, but c_api.h is not in its include paths")?;
+    let deps: Vec<String> = lib.libs.into_iter().filter(|lib| lib != "ncnn").collect();
+    let openmp = if deps
+        .iter()
+        .any(|lib| matches!(lib.as_str(), "gomp" | "omp" | "iomp5"))
+    {
+        Some(true)
+    } else {
+        lib.link_paths
+            .iter()
+            .find_map(|dir| openmp_from_lib_dir(dir))
+    };
+    Ok(Ncnn {
+        include_dir,
+        openmp,
+        lib_dirs: lib.link_paths,
+        vulkan: cfg!(feature = "vulkan")
+            || deps
+                .iter()
+                .any(|lib| lib.contains("vulkan") || lib == "glslang"),
+        deps,
+    })
+}
 
-    println!("cargo:rustc-link-search=native={}", dst.display());
+/// Reads the `set(NAME VALUE)` lines of `ncnnConfig.cmake`, which record how ncnn was built.
+fn cmake_config_vars(path: &Path) -> io::Result<HashMap<String, String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| {
+            let args = line.trim().strip_prefix("set(")?.strip_suffix(')')?;
+            let (name, value) = args.split_once(char::is_whitespace)?;
+            Some((name.to_string(), value.trim().to_string()))
+        })
+        .collect())
+}
+
+fn cmake_flag(vars: &HashMap<String, String>, name: &str) -> bool {
+    matches!(
+        vars.get(name).map(|v| v.to_uppercase()).as_deref(),
+        Some("ON" | "1" | "TRUE" | "YES")
+    )
+}
 
-    Ok(())
+/// Reads `NCNN_OPENMP` from the `ncnnConfig.cmake` installed in `lib_dir`, if there is one.
+fn openmp_from_lib_dir(lib_dir: &Path) -> Option<bool> {
+    let vars = cmake_config_vars(&lib_dir.join("cmake/ncnn/ncnnConfig.cmake")).ok()?;
+    vars.contains_key("NCNN_OPENMP")
+        .then(|| cmake_flag(&vars, "NCNN_OPENMP"))
 }
 
-fn search_include(include_paths: &[PathBuf], header: &str) -> String {
-    for dir in include_paths {
-        let include = dir.join(header);
-        if fs::metadata(&include).is_ok() {
-            return include.as_path().to_str().unwrap().to_string();
+fn find_from_cmake_config() -> Result<Ncnn, String> {
+    println!("cargo:rerun-if-env-changed=ncnn_DIR");
+    println!("cargo:rerun-if-env-changed=CMAKE_PREFIX_PATH");
+
+    let mut candidates: Vec<PathBuf> = env::var_os("ncnn_DIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    let mut prefixes: Vec<PathBuf> = env::var_os("CMAKE_PREFIX_PATH")
+        .map(|paths| env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    prefixes.extend([PathBuf::from("/usr/local"), PathBuf::from("/usr")]);
+    let multiarch = format!(
+        "lib/{}-linux-gnu",
+        env::var("CARGO_CFG_TARGET_ARCH").unwrap()
+    );
+    for prefix in &prefixes {
+        for lib in ["lib", "lib64", &multiarch] {
+            candidates.push(prefix.join(lib).join("cmake").join("ncnn"));
         }
     }
-    format!("/usr/include/{}", header)
+
+    let config = candidates
+        .iter()
+        .map(|dir| dir.join("ncnnConfig.cmake"))
+        .find(|config| config.is_file())
+        .ok_or("no ncnnConfig.cmake found, set ncnn_DIR to the directory containing it")?;
+    let vars = cmake_config_vars(&config).map_err(|e| format!("{}: {}", config.display(), e))?;
+
+    // the config lives in `<prefix>/<libdir>/cmake/ncnn`
+    let lib_dir = config.ancestors().nth(3).unwrap().to_path_buf();
+    let prefix = lib_dir.parent().unwrap_or(&lib_dir);
+    let include_dir = ncnn_include_dir(&prefix.join("include"))
+        .ok_or_else(|| format!("{} found, but c_api.h is not installed", config.display()))?;
+
+    let vulkan = cfg!(feature = "vulkan") || cmake_flag(&vars, "NCNN_VULKAN");
+    let mut deps = Vec::new();
+    if vulkan && !cmake_flag(&vars, "NCNN_SHARED_LIB") {
+        if cmake_flag(&vars, "NCNN_SYSTEM_GLSLANG") {
+            deps.extend(["glslang".to_string(), "SPIRV".to_string()]);
+        } else {
+            deps.extend(glslang_deps(&lib_dir, vulkan));
+        }
+    }
+    // OpenMP (`NCNN_OPENMP`) is linked through `openmp-sys`
+    let openmp = vars
+        .contains_key("NCNN_OPENMP")
+        .then(|| cmake_flag(&vars, "NCNN_OPENMP"));
+
+    Ok(Ncnn {
+        include_dir,
+        lib_dirs: vec![lib_dir],
+        deps,
+        vulkan,
+        openmp,
+    })
+}
+
+fn find_from_default_paths() -> Result<Ncnn, String> {
+    ["/usr/local", "/usr"]
+        .iter()
+        .map(Path::new)
+        .find(|prefix| ncnn_include_dir(&prefix.join("include")).is_some())
+        .map(Ncnn::from_prefix)
+        .ok_or_else(|| "no c_api.h in /usr/local/include/ncnn or /usr/include/ncnn".to_string())
+}
+
+/// Looks for ncnn in one place, explaining why it is not there on failure.
+type Probe = fn() -> Result<Ncnn, String>;
+
+fn find_ncnn() -> Ncnn {
+    let probes: [(&str, Probe); 4] = [
+        ("NCNN_INCLUDE_DIR", find_from_env),
+        ("pkg-config", find_from_pkg_config),
+        ("CMake config", find_from_cmake_config),
+        ("default paths", find_from_default_paths),
+    ];
+
+    let mut tried = Vec::new();
+    for (name, probe) in probes {
+        match probe() {
+            Ok(ncnn) => return ncnn,
+            Err(reason) => tried.push(format!("  - {}: {}", name, reason)),
+        }
+    }
+    panic!(
+        "\n\nCould not find ncnn. Tried:\n{}\n\n\
+         Install ncnn, or point the build at it with NCNN_INCLUDE_DIR and NCNN_LIB_DIR, \
+         PKG_CONFIG_PATH or ncnn_DIR, or enable the `build` feature to build it from source.\n\n",
+        tried.join("\n")
+    );
+}
+
+fn build_shim(c_api_header: &Path) {
+    println!("cargo:rerun-if-changed=src/shim.h");
+    println!("cargo:rerun-if-changed=src/shim.cpp");
+
+    // the shim has to be linked before ncnn itself
+    cc::Build::new()
+        .cpp(true)
+        .file("src/shim.cpp")
+        .include(c_api_header.parent().unwrap())
+        .compile("ncnn_rs_shim");
+}
+
+/// Reads `NCNN_VERSION_STRING`, e.g. `1.0.20220729`, from the `platform.h` next to `c_api.h`.
+fn ncnn_version_string(include_dir: &Path) -> Option<String> {
+    let platform = fs::read_to_string(include_dir.join("platform.h")).ok()?;
+    platform.lines().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix("#define")?
+            .trim()
+            .strip_prefix("NCNN_VERSION_STRING")?;
+        Some(value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// The release date an ncnn version ends with, e.g. `20220729` for `1.0.20220729`.
+fn ncnn_release(version: &str) -> Option<u32> {
+    version.rsplit('.').next()?.parse().ok()
+}
+
+fn bindings_dir() -> PathBuf {
+    PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("src/bindings")
+}
+
+/// The checked-in `src/bindings/ncnn_<release>.rs` files, oldest release first.
+fn checked_in_bindings() -> Vec<(u32, PathBuf)> {
+    println!("cargo:rerun-if-changed=src/bindings");
+    let mut bindings: Vec<(u32, PathBuf)> = fs::read_dir(bindings_dir())
+        .expect("Couldn't read src/bindings")
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let release = path
+                .file_stem()?
+                .to_str()?
+                .strip_prefix("ncnn_")?
+                .parse()
+                .ok()?;
+            Some((release, path))
+        })
+        .collect();
+    bindings.sort();
+    bindings
+}
+
+/// Picks the checked-in bindings for `release`, falling back to the newest older ones.
+///
+/// The C API only grows between releases, so older bindings stay usable with newer
+/// headers; they just lack the newest functions.
+fn select_bindings(release: Option<u32>) -> PathBuf {
+    let bindings = checked_in_bindings();
+    let newest = bindings.last().expect("No checked-in bindings").clone();
+    let release = match release {
+        Some(release) => release,
+        None => {
+            println!(
+                "cargo:warning=Couldn't read NCNN_VERSION_STRING from platform.h, \
+                 using the bindings for ncnn {}",
+                newest.0
+            );
+            return newest.1;
+        }
+    };
+    match bindings.into_iter().rev().find(|(r, _)| *r <= release) {
+        Some((r, path)) => {
+            if r != release {
+                println!(
+                    "cargo:warning=No checked-in bindings for ncnn {}, using those for {}; \
+                     enable the `bindgen` feature to generate matching ones",
+                    release, r
+                );
+            }
+            path
+        }
+        None => panic!(
+            "ncnn {} is older than any checked-in bindings, \
+             enable the `bindgen` feature to generate bindings for it",
+            release
+        ),
+    }
+}
+
+/// Runs bindgen over `c_api.h`, keeping only what is the same for every build
+/// configuration, so the output can be checked in.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(header: &Path, out: &Path) {
+    bindgen::Builder::default()
+        .header(header.to_string_lossy())
+        .allowlist_function("ncnn.*")
+        .allowlist_type("ncnn.*")
+        .allowlist_var("NCNN_MAT_PIXEL_.*")
+        .allowlist_var("NCNN_BORDER_.*")
+        // `FILE` differs between C libraries
+        .blocklist_function("ncnn_datareader_create_from_stdio")
+        .blocklist_type("FILE")
+        .blocklist_type("_IO_.*")
+        .layout_tests(false)
+        .generate()
+        .expect("Unable to generate bindings")
+        .write_to_file(out)
+        .expect("Couldn't write bindings!");
 }
 
 fn use_dynamic_linking() -> bool {
@@ -121,40 +899,36 @@ fn link_vulkan() {
 }
 
 fn main() {
-    let include_paths: Vec<PathBuf>;
-    if cfg!(feature = "build") {
+    let ncnn = if cfg!(feature = "build") {
         println!("cargo:rerun-if-env-changed=NCNN_DIR");
         println!("cargo:rerun-if-env-changed=NCNN_TAG");
+        println!("cargo:rerun-if-env-changed=NCNN_SRC_SHA256");
+        println!("cargo:rerun-if-env-changed=NCNN_SRC_COMMIT");
 
-        include_paths = if let Ok(ncnn_dir) = env::var("NCNN_DIR") {
+        if let Ok(ncnn_dir) = env::var("NCNN_DIR") {
             // use prebuild ncnn dir
-            let dir = PathBuf::from(ncnn_dir);
-            println!(
-                "cargo:rustc-link-search=native={}",
-                dir.join("lib").to_string_lossy()
-            );
-            vec![dir.join("include").join("ncnn")]
+            Ncnn::from_prefix(Path::new(&ncnn_dir))
         } else {
-            // fetch from github and build
-            fetch().unwrap();
-            build().unwrap();
+            // build from NCNN_SRC_DIR or the pinned NCNN_TAG, reusing a cached build
+            Ncnn::from_prefix(&build().unwrap())
+        }
+    } else {
+        find_ncnn()
+    };
 
-            println!(
-                "cargo:rustc-link-search=native={}",
-                output_dir().join("lib").to_string_lossy()
-            );
+    for dir in &ncnn.lib_dirs {
+        println!("cargo:rustc-link-search=native={}", dir.display());
+    }
 
-            vec![output_dir().join("include").join("ncnn")]
-        };
-    } else {
-        // how could Windows users find ncnn?
-        // I don't care!
-        include_paths = vec![
-            PathBuf::from("/usr/include/ncnn"),
-            PathBuf::from("/usr/local/include/ncnn"),
-        ];
-        println!("cargo:rustc-link-search=native={}", "/usr/local/lib");
-        println!("cargo:rustc-link-search=native={}", "/usr/lib");
+    let header = ncnn.include_dir.join("c_api.h");
+    build_shim(&header);
+
+    // platform.h does not record OpenMP, so `ncnn_bind::NCNN_OPENMP` reports it
+    if let Some(openmp) = ncnn.openmp {
+        println!(
+            "cargo:rustc-env=NCNN_BIND_OPENMP={}",
+            if openmp { "ON" } else { "OFF" }
+        );
     }
 
     // have to link stdc++ explicitly
@@ -162,9 +936,15 @@ fn main() {
     // `openmp-sys` is needed to link OpenMP
     if use_dynamic_linking() {
         println!("cargo:rustc-link-lib=dylib=ncnn");
-        println!("cargo:rustc-link-lib=dylib=stdc++");
     } else {
         println!("cargo:rustc-link-lib=static=ncnn");
+    }
+    for dep in &ncnn.deps {
+        println!("cargo:rustc-link-lib={}", dep);
+    }
+    if use_dynamic_linking() {
+        println!("cargo:rustc-link-lib=dylib=stdc++");
+    } else {
         println!("cargo:rustc-link-lib=static=stdc++");
     }
 
@@ -172,22 +952,26 @@ fn main() {
         println!("cargo:rustc-link-lib=dylib=pthread");
     }
 
-    if cfg!(feature = "vulkan") {
+    if ncnn.vulkan {
         link_vulkan();
     }
 
-    let header = search_include(&include_paths, "c_api.h");
+    let release = ncnn_version_string(&ncnn.include_dir).and_then(|v| ncnn_release(&v));
+    let bindings = output_dir().join("bindings.rs");
+    if cfg!(feature = "bindgen") {
+        #[cfg(feature = "bindgen")]
+        generate_bindings(&header, &bindings);
 
-    let bindings = bindgen::Builder::default()
-        .header(header)
-        .allowlist_type("regex")
-        .allowlist_function("ncnn.*")
-        .allowlist_var("NCNN.*")
-        .allowlist_type("ncnn.*")
-        .generate()
-        .expect("Unable to generate bindings");
-
-    bindings
-        .write_to_file(output_dir().join("bindings.rs"))
-        .expect("Couldn't write bindings!");
+        // what `tests/bindings.rs` compares the generated bindings against
+        if let Some(release) = release {
+            println!(
+                "cargo:rustc-env=NCNN_BIND_CHECKED_IN_BINDINGS={}",
+                bindings_dir()
+                    .join(format!("ncnn_{}.rs", release))
+                    .display()
+            );
+        }
+    } else {
+        fs::copy(select_bindings(release), &bindings).expect("Couldn't write bindings!");
+    }
 }
@@ -0,0 +1,899 @@
+/* automatically generated by rust-bindgen 0.64.0 */
+
+pub const NCNN_MAT_PIXEL_RGB: u32 = 1;
+pub const NCNN_MAT_PIXEL_BGR: u32 = 2;
+pub const NCNN_MAT_PIXEL_GRAY: u32 = 3;
+pub const NCNN_MAT_PIXEL_RGBA: u32 = 4;
+pub const NCNN_MAT_PIXEL_BGRA: u32 = 5;
+pub const NCNN_BORDER_CONSTANT: u32 = 0;
+pub const NCNN_BORDER_REPLICATE: u32 = 1;
+pub const NCNN_BORDER_REFLECT: u32 = 2;
+pub const NCNN_BORDER_TRANSPARENT: i32 = -233;
+extern "C" {
+    pub fn ncnn_version() -> *const ::std::os::raw::c_char;
+}
+pub type ncnn_allocator_t = *mut __ncnn_allocator_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_allocator_t {
+    pub pthis: *mut ::std::os::raw::c_void,
+    pub fast_malloc: ::std::option::Option<
+        unsafe extern "C" fn(
+            allocator: ncnn_allocator_t,
+            size: usize,
+        ) -> *mut ::std::os::raw::c_void,
+    >,
+    pub fast_free: ::std::option::Option<
+        unsafe extern "C" fn(allocator: ncnn_allocator_t, ptr: *mut ::std::os::raw::c_void),
+    >,
+}
+extern "C" {
+    pub fn ncnn_allocator_create_pool_allocator() -> ncnn_allocator_t;
+}
+extern "C" {
+    pub fn ncnn_allocator_create_unlocked_pool_allocator() -> ncnn_allocator_t;
+}
+extern "C" {
+    pub fn ncnn_allocator_destroy(allocator: ncnn_allocator_t);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_option_t {
+    _unused: [u8; 0],
+}
+pub type ncnn_option_t = *mut __ncnn_option_t;
+extern "C" {
+    pub fn ncnn_option_create() -> ncnn_option_t;
+}
+extern "C" {
+    pub fn ncnn_option_destroy(opt: ncnn_option_t);
+}
+extern "C" {
+    pub fn ncnn_option_get_num_threads(opt: ncnn_option_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_option_set_num_threads(opt: ncnn_option_t, num_threads: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_option_get_use_local_pool_allocator(opt: ncnn_option_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_option_set_use_local_pool_allocator(
+        opt: ncnn_option_t,
+        use_local_pool_allocator: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn ncnn_option_set_blob_allocator(opt: ncnn_option_t, allocator: ncnn_allocator_t);
+}
+extern "C" {
+    pub fn ncnn_option_set_workspace_allocator(opt: ncnn_option_t, allocator: ncnn_allocator_t);
+}
+extern "C" {
+    pub fn ncnn_option_get_use_vulkan_compute(opt: ncnn_option_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_option_set_use_vulkan_compute(
+        opt: ncnn_option_t,
+        use_vulkan_compute: ::std::os::raw::c_int,
+    );
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_mat_t {
+    _unused: [u8; 0],
+}
+pub type ncnn_mat_t = *mut __ncnn_mat_t;
+extern "C" {
+    pub fn ncnn_mat_create() -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_1d(w: ::std::os::raw::c_int, allocator: ncnn_allocator_t) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_2d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_3d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_4d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        d: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_1d(
+        w: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_2d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_3d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_4d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        d: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_1d_elem(
+        w: ::std::os::raw::c_int,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_2d_elem(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_3d_elem(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_4d_elem(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        d: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_1d_elem(
+        w: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_2d_elem(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_3d_elem(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_create_external_4d_elem(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        d: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        elemsize: usize,
+        elempack: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_destroy(mat: ncnn_mat_t);
+}
+extern "C" {
+    pub fn ncnn_mat_fill_float(mat: ncnn_mat_t, v: f32);
+}
+extern "C" {
+    pub fn ncnn_mat_clone(mat: ncnn_mat_t, allocator: ncnn_allocator_t) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_reshape_1d(
+        mat: ncnn_mat_t,
+        w: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_reshape_2d(
+        mat: ncnn_mat_t,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_reshape_3d(
+        mat: ncnn_mat_t,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_reshape_4d(
+        mat: ncnn_mat_t,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        d: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_get_dims(mat: ncnn_mat_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_mat_get_w(mat: ncnn_mat_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_mat_get_h(mat: ncnn_mat_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_mat_get_d(mat: ncnn_mat_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_mat_get_c(mat: ncnn_mat_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_mat_get_elemsize(mat: ncnn_mat_t) -> usize;
+}
+extern "C" {
+    pub fn ncnn_mat_get_elempack(mat: ncnn_mat_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_mat_get_cstep(mat: ncnn_mat_t) -> usize;
+}
+extern "C" {
+    pub fn ncnn_mat_get_data(mat: ncnn_mat_t) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn ncnn_mat_get_channel_data(
+        mat: ncnn_mat_t,
+        c: ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn ncnn_mat_from_pixels(
+        pixels: *const ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        stride: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_from_pixels_resize(
+        pixels: *const ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        stride: ::std::os::raw::c_int,
+        target_width: ::std::os::raw::c_int,
+        target_height: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_from_pixels_roi(
+        pixels: *const ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        stride: ::std::os::raw::c_int,
+        roix: ::std::os::raw::c_int,
+        roiy: ::std::os::raw::c_int,
+        roiw: ::std::os::raw::c_int,
+        roih: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_from_pixels_roi_resize(
+        pixels: *const ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        stride: ::std::os::raw::c_int,
+        roix: ::std::os::raw::c_int,
+        roiy: ::std::os::raw::c_int,
+        roiw: ::std::os::raw::c_int,
+        roih: ::std::os::raw::c_int,
+        target_width: ::std::os::raw::c_int,
+        target_height: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_mat_to_pixels(
+        mat: ncnn_mat_t,
+        pixels: *mut ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        stride: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn ncnn_mat_to_pixels_resize(
+        mat: ncnn_mat_t,
+        pixels: *mut ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        target_width: ::std::os::raw::c_int,
+        target_height: ::std::os::raw::c_int,
+        target_stride: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn ncnn_mat_substract_mean_normalize(
+        mat: ncnn_mat_t,
+        mean_vals: *const f32,
+        norm_vals: *const f32,
+    );
+}
+extern "C" {
+    pub fn ncnn_convert_packing(
+        src: ncnn_mat_t,
+        dst: *mut ncnn_mat_t,
+        elempack: ::std::os::raw::c_int,
+        opt: ncnn_option_t,
+    );
+}
+extern "C" {
+    pub fn ncnn_flatten(src: ncnn_mat_t, dst: *mut ncnn_mat_t, opt: ncnn_option_t);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_blob_t {
+    _unused: [u8; 0],
+}
+pub type ncnn_blob_t = *mut __ncnn_blob_t;
+extern "C" {
+    pub fn ncnn_blob_get_name(blob: ncnn_blob_t) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn ncnn_blob_get_producer(blob: ncnn_blob_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_blob_get_consumer(blob: ncnn_blob_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_blob_get_shape(
+        blob: ncnn_blob_t,
+        dims: *mut ::std::os::raw::c_int,
+        w: *mut ::std::os::raw::c_int,
+        h: *mut ::std::os::raw::c_int,
+        c: *mut ::std::os::raw::c_int,
+    );
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_paramdict_t {
+    _unused: [u8; 0],
+}
+pub type ncnn_paramdict_t = *mut __ncnn_paramdict_t;
+extern "C" {
+    pub fn ncnn_paramdict_create() -> ncnn_paramdict_t;
+}
+extern "C" {
+    pub fn ncnn_paramdict_destroy(pd: ncnn_paramdict_t);
+}
+extern "C" {
+    pub fn ncnn_paramdict_get_type(
+        pd: ncnn_paramdict_t,
+        id: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_paramdict_get_int(
+        pd: ncnn_paramdict_t,
+        id: ::std::os::raw::c_int,
+        def: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_paramdict_get_float(
+        pd: ncnn_paramdict_t,
+        id: ::std::os::raw::c_int,
+        def: f32,
+    ) -> f32;
+}
+extern "C" {
+    pub fn ncnn_paramdict_get_array(
+        pd: ncnn_paramdict_t,
+        id: ::std::os::raw::c_int,
+        def: ncnn_mat_t,
+    ) -> ncnn_mat_t;
+}
+extern "C" {
+    pub fn ncnn_paramdict_set_int(
+        pd: ncnn_paramdict_t,
+        id: ::std::os::raw::c_int,
+        i: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn ncnn_paramdict_set_float(pd: ncnn_paramdict_t, id: ::std::os::raw::c_int, f: f32);
+}
+extern "C" {
+    pub fn ncnn_paramdict_set_array(pd: ncnn_paramdict_t, id: ::std::os::raw::c_int, v: ncnn_mat_t);
+}
+pub type ncnn_datareader_t = *mut __ncnn_datareader_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_datareader_t {
+    pub pthis: *mut ::std::os::raw::c_void,
+    pub scan: ::std::option::Option<
+        unsafe extern "C" fn(
+            dr: ncnn_datareader_t,
+            format: *const ::std::os::raw::c_char,
+            p: *mut ::std::os::raw::c_void,
+        ) -> ::std::os::raw::c_int,
+    >,
+    pub read: ::std::option::Option<
+        unsafe extern "C" fn(
+            dr: ncnn_datareader_t,
+            buf: *mut ::std::os::raw::c_void,
+            size: usize,
+        ) -> usize,
+    >,
+}
+extern "C" {
+    pub fn ncnn_datareader_create() -> ncnn_datareader_t;
+}
+extern "C" {
+    pub fn ncnn_datareader_create_from_memory(
+        mem: *mut *const ::std::os::raw::c_uchar,
+    ) -> ncnn_datareader_t;
+}
+extern "C" {
+    pub fn ncnn_datareader_destroy(dr: ncnn_datareader_t);
+}
+pub type ncnn_modelbin_t = *mut __ncnn_modelbin_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_modelbin_t {
+    pub pthis: *mut ::std::os::raw::c_void,
+    pub load_1d: ::std::option::Option<
+        unsafe extern "C" fn(
+            mb: ncnn_modelbin_t,
+            w: ::std::os::raw::c_int,
+            type_: ::std::os::raw::c_int,
+        ) -> ncnn_mat_t,
+    >,
+    pub load_2d: ::std::option::Option<
+        unsafe extern "C" fn(
+            mb: ncnn_modelbin_t,
+            w: ::std::os::raw::c_int,
+            h: ::std::os::raw::c_int,
+            type_: ::std::os::raw::c_int,
+        ) -> ncnn_mat_t,
+    >,
+    pub load_3d: ::std::option::Option<
+        unsafe extern "C" fn(
+            mb: ncnn_modelbin_t,
+            w: ::std::os::raw::c_int,
+            h: ::std::os::raw::c_int,
+            c: ::std::os::raw::c_int,
+            type_: ::std::os::raw::c_int,
+        ) -> ncnn_mat_t,
+    >,
+}
+extern "C" {
+    pub fn ncnn_modelbin_create_from_datareader(dr: ncnn_datareader_t) -> ncnn_modelbin_t;
+}
+extern "C" {
+    pub fn ncnn_modelbin_create_from_mat_array(
+        weights: *const ncnn_mat_t,
+        n: ::std::os::raw::c_int,
+    ) -> ncnn_modelbin_t;
+}
+extern "C" {
+    pub fn ncnn_modelbin_destroy(mb: ncnn_modelbin_t);
+}
+pub type ncnn_layer_t = *mut __ncnn_layer_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_layer_t {
+    pub pthis: *mut ::std::os::raw::c_void,
+    pub load_param: ::std::option::Option<
+        unsafe extern "C" fn(layer: ncnn_layer_t, pd: ncnn_paramdict_t) -> ::std::os::raw::c_int,
+    >,
+    pub load_model: ::std::option::Option<
+        unsafe extern "C" fn(layer: ncnn_layer_t, mb: ncnn_modelbin_t) -> ::std::os::raw::c_int,
+    >,
+    pub create_pipeline: ::std::option::Option<
+        unsafe extern "C" fn(layer: ncnn_layer_t, opt: ncnn_option_t) -> ::std::os::raw::c_int,
+    >,
+    pub destroy_pipeline: ::std::option::Option<
+        unsafe extern "C" fn(layer: ncnn_layer_t, opt: ncnn_option_t) -> ::std::os::raw::c_int,
+    >,
+    pub forward_1: ::std::option::Option<
+        unsafe extern "C" fn(
+            layer: ncnn_layer_t,
+            bottom_blob: ncnn_mat_t,
+            top_blob: *mut ncnn_mat_t,
+            opt: ncnn_option_t,
+        ) -> ::std::os::raw::c_int,
+    >,
+    pub forward_n: ::std::option::Option<
+        unsafe extern "C" fn(
+            layer: ncnn_layer_t,
+            bottom_blobs: *const ncnn_mat_t,
+            n: ::std::os::raw::c_int,
+            top_blobs: *mut ncnn_mat_t,
+            n2: ::std::os::raw::c_int,
+            opt: ncnn_option_t,
+        ) -> ::std::os::raw::c_int,
+    >,
+    pub forward_inplace_1: ::std::option::Option<
+        unsafe extern "C" fn(
+            layer: ncnn_layer_t,
+            bottom_top_blob: ncnn_mat_t,
+            opt: ncnn_option_t,
+        ) -> ::std::os::raw::c_int,
+    >,
+    pub forward_inplace_n: ::std::option::Option<
+        unsafe extern "C" fn(
+            layer: ncnn_layer_t,
+            bottom_top_blobs: *mut ncnn_mat_t,
+            n: ::std::os::raw::c_int,
+            opt: ncnn_option_t,
+        ) -> ::std::os::raw::c_int,
+    >,
+}
+extern "C" {
+    pub fn ncnn_layer_create() -> ncnn_layer_t;
+}
+extern "C" {
+    pub fn ncnn_layer_create_by_typeindex(typeindex: ::std::os::raw::c_int) -> ncnn_layer_t;
+}
+extern "C" {
+    pub fn ncnn_layer_create_by_type(type_: *const ::std::os::raw::c_char) -> ncnn_layer_t;
+}
+extern "C" {
+    pub fn ncnn_layer_type_to_index(type_: *const ::std::os::raw::c_char) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_destroy(layer: ncnn_layer_t);
+}
+extern "C" {
+    pub fn ncnn_layer_get_name(layer: ncnn_layer_t) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn ncnn_layer_get_typeindex(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_type(layer: ncnn_layer_t) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn ncnn_layer_get_one_blob_only(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_support_inplace(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_support_vulkan(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_support_packing(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_support_bf16_storage(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_support_fp16_storage(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_support_image_storage(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_set_one_blob_only(layer: ncnn_layer_t, enable: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_layer_set_support_inplace(layer: ncnn_layer_t, enable: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_layer_set_support_vulkan(layer: ncnn_layer_t, enable: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_layer_set_support_packing(layer: ncnn_layer_t, enable: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_layer_set_support_bf16_storage(layer: ncnn_layer_t, enable: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_layer_set_support_fp16_storage(layer: ncnn_layer_t, enable: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_layer_set_support_image_storage(layer: ncnn_layer_t, enable: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn ncnn_layer_get_bottom_count(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_bottom(
+        layer: ncnn_layer_t,
+        i: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_top_count(layer: ncnn_layer_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_layer_get_top(
+        layer: ncnn_layer_t,
+        i: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_blob_get_bottom_shape(
+        layer: ncnn_layer_t,
+        i: ::std::os::raw::c_int,
+        dims: *mut ::std::os::raw::c_int,
+        w: *mut ::std::os::raw::c_int,
+        h: *mut ::std::os::raw::c_int,
+        c: *mut ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn ncnn_blob_get_top_shape(
+        layer: ncnn_layer_t,
+        i: ::std::os::raw::c_int,
+        dims: *mut ::std::os::raw::c_int,
+        w: *mut ::std::os::raw::c_int,
+        h: *mut ::std::os::raw::c_int,
+        c: *mut ::std::os::raw::c_int,
+    );
+}
+pub type ncnn_layer_creator_t = ::std::option::Option<
+    unsafe extern "C" fn(userdata: *mut ::std::os::raw::c_void) -> ncnn_layer_t,
+>;
+pub type ncnn_layer_destroyer_t = ::std::option::Option<
+    unsafe extern "C" fn(layer: ncnn_layer_t, userdata: *mut ::std::os::raw::c_void),
+>;
+pub type ncnn_net_custom_layer_factory_t = *mut __ncnn_net_custom_layer_factory_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_net_custom_layer_factory_t {
+    pub creator: ncnn_layer_creator_t,
+    pub destroyer: ncnn_layer_destroyer_t,
+    pub userdata: *mut ::std::os::raw::c_void,
+    pub next: ncnn_net_custom_layer_factory_t,
+}
+pub type ncnn_net_t = *mut __ncnn_net_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_net_t {
+    pub pthis: *mut ::std::os::raw::c_void,
+    pub custom_layer_factory: ncnn_net_custom_layer_factory_t,
+}
+extern "C" {
+    pub fn ncnn_net_create() -> ncnn_net_t;
+}
+extern "C" {
+    pub fn ncnn_net_destroy(net: ncnn_net_t);
+}
+extern "C" {
+    pub fn ncnn_net_set_option(net: ncnn_net_t, opt: ncnn_option_t);
+}
+extern "C" {
+    pub fn ncnn_net_register_custom_layer_by_type(
+        net: ncnn_net_t,
+        type_: *const ::std::os::raw::c_char,
+        creator: ncnn_layer_creator_t,
+        destroyer: ncnn_layer_destroyer_t,
+        userdata: *mut ::std::os::raw::c_void,
+    );
+}
+extern "C" {
+    pub fn ncnn_net_register_custom_layer_by_typeindex(
+        net: ncnn_net_t,
+        typeindex: ::std::os::raw::c_int,
+        creator: ncnn_layer_creator_t,
+        destroyer: ncnn_layer_destroyer_t,
+        userdata: *mut ::std::os::raw::c_void,
+    );
+}
+extern "C" {
+    pub fn ncnn_net_load_param(
+        net: ncnn_net_t,
+        path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_param_bin(
+        net: ncnn_net_t,
+        path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_model(
+        net: ncnn_net_t,
+        path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_param_memory(
+        net: ncnn_net_t,
+        mem: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_param_bin_memory(
+        net: ncnn_net_t,
+        mem: *const ::std::os::raw::c_uchar,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_model_memory(
+        net: ncnn_net_t,
+        mem: *const ::std::os::raw::c_uchar,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_param_datareader(
+        net: ncnn_net_t,
+        dr: ncnn_datareader_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_param_bin_datareader(
+        net: ncnn_net_t,
+        dr: ncnn_datareader_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_load_model_datareader(
+        net: ncnn_net_t,
+        dr: ncnn_datareader_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_net_clear(net: ncnn_net_t);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __ncnn_extractor_t {
+    _unused: [u8; 0],
+}
+pub type ncnn_extractor_t = *mut __ncnn_extractor_t;
+extern "C" {
+    pub fn ncnn_extractor_create(net: ncnn_net_t) -> ncnn_extractor_t;
+}
+extern "C" {
+    pub fn ncnn_extractor_destroy(ex: ncnn_extractor_t);
+}
+extern "C" {
+    pub fn ncnn_extractor_set_option(ex: ncnn_extractor_t, opt: ncnn_option_t);
+}
+extern "C" {
+    pub fn ncnn_extractor_input(
+        ex: ncnn_extractor_t,
+        name: *const ::std::os::raw::c_char,
+        mat: ncnn_mat_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_extractor_extract(
+        ex: ncnn_extractor_t,
+        name: *const ::std::os::raw::c_char,
+        mat: *mut ncnn_mat_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_extractor_input_index(
+        ex: ncnn_extractor_t,
+        index: ::std::os::raw::c_int,
+        mat: ncnn_mat_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_extractor_extract_index(
+        ex: ncnn_extractor_t,
+        index: ::std::os::raw::c_int,
+        mat: *mut ncnn_mat_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ncnn_copy_make_border(
+        src: ncnn_mat_t,
+        dst: ncnn_mat_t,
+        top: ::std::os::raw::c_int,
+        bottom: ::std::os::raw::c_int,
+        left: ::std::os::raw::c_int,
+        right: ::std::os::raw::c_int,
+        type_: ::std::os::raw::c_int,
+        v: f32,
+        opt: ncnn_option_t,
+    );
+}
+extern "C" {
+    pub fn ncnn_copy_make_border_3d(
+        src: ncnn_mat_t,
+        dst: ncnn_mat_t,
+        top: ::std::os::raw::c_int,
+        bottom: ::std::os::raw::c_int,
+        left: ::std::os::raw::c_int,
+        right: ::std::os::raw::c_int,
+        front: ::std::os::raw::c_int,
+        behind: ::std::os::raw::c_int,
+        type_: ::std::os::raw::c_int,
+        v: f32,
+        opt: ncnn_option_t,
+    );
+}
+extern "C" {
+    pub fn ncnn_copy_cut_border(
+        src: ncnn_mat_t,
+        dst: ncnn_mat_t,
+        top: ::std::os::raw::c_int,
+        bottom: ::std::os::raw::c_int,
+        left: ::std::os::raw::c_int,
+        right: ::std::os::raw::c_int,
+        opt: ncnn_option_t,
+    );
+}
+extern "C" {
+    pub fn ncnn_copy_cut_border_3d(
+        src: ncnn_mat_t,
+        dst: ncnn_mat_t,
+        top: ::std::os::raw::c_int,
+        bottom: ::std::os::raw::c_int,
+        left: ::std::os::raw::c_int,
+        right: ::std::os::raw::c_int,
+        front: ::std::os::raw::c_int,
+        behind: ::std::os::raw::c_int,
+        opt: ncnn_option_t,
+    );
+}
@@ -0,0 +1,106 @@
+// The built-in layer types, shared by the crate and `build.rs`, which `include!`s this file.
+
+/// Built-in ncnn layer types in `LayerType` order, as named in `ncnn_add_layer()`.
+///
+/// The index of a type is what `.param.bin` stores; `build.rs` turns the names into
+/// `WITH_LAYER_*` options.
+pub const LAYER_TYPES: [&str; 98] = [
+    "AbsVal",
+    "ArgMax",
+    "BatchNorm",
+    "Bias",
+    "BNLL",
+    "Concat",
+    "Convolution",
+    "Crop",
+    "Deconvolution",
+    "Dropout",
+    "Eltwise",
+    "ELU",
+    "Embed",
+    "Exp",
+    "Flatten",
+    "InnerProduct",
+    "Input",
+    "Log",
+    "LRN",
+    "MemoryData",
+    "MVN",
+    "Pooling",
+    "Power",
+    "PReLU",
+    "Proposal",
+    "Reduction",
+    "ReLU",
+    "Reshape",
+    "ROIPooling",
+    "Scale",
+    "Sigmoid",
+    "Slice",
+    "Softmax",
+    "Split",
+    "SPP",
+    "TanH",
+    "Threshold",
+    "Tile",
+    "RNN",
+    "LSTM",
+    "BinaryOp",
+    "UnaryOp",
+    "ConvolutionDepthWise",
+    "Padding",
+    "Squeeze",
+    "ExpandDims",
+    "Normalize",
+    "Permute",
+    "PriorBox",
+    "DetectionOutput",
+    "Interp",
+    "DeconvolutionDepthWise",
+    "ShuffleChannel",
+    "InstanceNorm",
+    "Clip",
+    "Reorg",
+    "YoloDetectionOutput",
+    "Quantize",
+    "Dequantize",
+    "Yolov3DetectionOutput",
+    "PSROIPooling",
+    "ROIAlign",
+    "Packing",
+    "Requantize",
+    "Cast",
+    "HardSigmoid",
+    "SELU",
+    "HardSwish",
+    "Noop",
+    "PixelShuffle",
+    "DeepCopy",
+    "Mish",
+    "StatisticsPooling",
+    "Swish",
+    "Gemm",
+    "GroupNorm",
+    "LayerNorm",
+    "Softplus",
+    "GRU",
+    "MultiHeadAttention",
+    "GELU",
+    "Convolution1D",
+    "Pooling1D",
+    "ConvolutionDepthWise1D",
+    "Convolution3D",
+    "ConvolutionDepthWise3D",
+    "Pooling3D",
+    "MatMul",
+    "Deconvolution1D",
+    "DeconvolutionDepthWise1D",
+    "Deconvolution3D",
+    "DeconvolutionDepthWise3D",
+    "Einsum",
+    "DeformableConv2D",
+    "GLU",
+    "Fold",
+    "Unfold",
+    "GridSample",
+];
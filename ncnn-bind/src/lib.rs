@@ -4,6 +4,16 @@
 
 // Suppress bindgen test warnings
 #![allow(deref_nullptr)]
+#[cfg(feature = "openmp")]
 extern crate openmp_sys;
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+mod layer_types;
+mod shim;
+pub use layer_types::*;
+pub use shim::*;
+
+/// `NCNN_OPENMP` of the ncnn this crate was built against, `"ON"` or `"OFF"`, if the build
+/// script could find out. Unlike the other build switches it is not in `platform.h`.
+pub const NCNN_OPENMP: ::std::option::Option<&str> = option_env!("NCNN_BIND_OPENMP");
@@ -0,0 +1,425 @@
+#include "shim.h"
+
+#include <stdlib.h>
+#include <string.h>
+
+#include "allocator.h"
+#include "blob.h"
+#include "cpu.h"
+#include "datareader.h"
+#include "layer.h"
+#include "mat.h"
+#include "net.h"
+#include "option.h"
+
+using ncnn::Allocator;
+using ncnn::DataReader;
+using ncnn::Extractor;
+using ncnn::Layer;
+using ncnn::Mat;
+using ncnn::Net;
+using ncnn::Option;
+
+namespace {
+
+// `ncnn::Allocator` forwarding to callbacks implemented in Rust.
+class RustAllocator : public Allocator
+{
+public:
+    RustAllocator(void* _userdata, ncnn_rs_fast_malloc_t _fast_malloc, ncnn_rs_fast_free_t _fast_free)
+        : userdata(_userdata), fast_malloc(_fast_malloc), fast_free(_fast_free)
+    {
+    }
+
+    virtual void* fastMalloc(size_t size)
+    {
+        return fast_malloc(userdata, size);
+    }
+
+    virtual void fastFree(void* ptr)
+    {
+        fast_free(userdata, ptr);
+    }
+
+private:
+    void* userdata;
+    ncnn_rs_fast_malloc_t fast_malloc;
+    ncnn_rs_fast_free_t fast_free;
+};
+
+// `ncnn::DataReader` over a buffer of known size, failing reads past its end instead of
+// running off it like `ncnn::DataReaderFromMemory`.
+class BoundedDataReader : public DataReader
+{
+public:
+    BoundedDataReader(const unsigned char* _mem, size_t _size)
+        : mem(_mem), remaining(_size)
+    {
+    }
+
+    virtual size_t read(void* buf, size_t size) const
+    {
+        size_t n = size < remaining ? size : remaining;
+        memcpy(buf, mem, n);
+        mem += n;
+        remaining -= n;
+        return n;
+    }
+
+private:
+    mutable const unsigned char* mem;
+    mutable size_t remaining;
+};
+
+Allocator* unwrap(ncnn_allocator_t allocator)
+{
+    return allocator ? (Allocator*)allocator->pthis : 0;
+}
+
+void* allocator_fast_malloc(ncnn_allocator_t allocator, size_t size)
+{
+    return unwrap(allocator)->fastMalloc(size);
+}
+
+void allocator_fast_free(ncnn_allocator_t allocator, void* ptr)
+{
+    unwrap(allocator)->fastFree(ptr);
+}
+
+} // namespace
+
+ncnn_allocator_t ncnn_rs_allocator_create(void* userdata, ncnn_rs_fast_malloc_t fast_malloc, ncnn_rs_fast_free_t fast_free)
+{
+    ncnn_allocator_t allocator = (ncnn_allocator_t)malloc(sizeof(struct __ncnn_allocator_t));
+    allocator->pthis = (void*)(new RustAllocator(userdata, fast_malloc, fast_free));
+    allocator->fast_malloc = allocator_fast_malloc;
+    allocator->fast_free = allocator_fast_free;
+    return allocator;
+}
+
+void ncnn_rs_allocator_destroy(ncnn_allocator_t allocator)
+{
+    if (allocator)
+    {
+        delete unwrap(allocator);
+        free(allocator);
+    }
+}
+
+void ncnn_rs_option_set_blob_allocator(ncnn_option_t opt, ncnn_allocator_t allocator)
+{
+    ((Option*)opt)->blob_allocator = unwrap(allocator);
+}
+
+void ncnn_rs_option_set_workspace_allocator(ncnn_option_t opt, ncnn_allocator_t allocator)
+{
+    ((Option*)opt)->workspace_allocator = unwrap(allocator);
+}
+
+int ncnn_rs_option_get_lightmode(const ncnn_option_t opt)
+{
+    return ((const Option*)opt)->lightmode;
+}
+
+void ncnn_rs_option_set_lightmode(ncnn_option_t opt, int enabled)
+{
+    ((Option*)opt)->lightmode = enabled != 0;
+}
+
+int ncnn_rs_option_get_use_fp16(const ncnn_option_t opt)
+{
+    return ((const Option*)opt)->use_fp16_storage;
+}
+
+void ncnn_rs_option_set_use_fp16(ncnn_option_t opt, int enabled)
+{
+    ((Option*)opt)->use_fp16_packed = enabled != 0;
+    ((Option*)opt)->use_fp16_storage = enabled != 0;
+    ((Option*)opt)->use_fp16_arithmetic = enabled != 0;
+}
+
+int ncnn_rs_option_get_use_int8_inference(const ncnn_option_t opt)
+{
+    return ((const Option*)opt)->use_int8_inference;
+}
+
+void ncnn_rs_option_set_use_int8_inference(ncnn_option_t opt, int enabled)
+{
+    ((Option*)opt)->use_int8_inference = enabled != 0;
+}
+
+ncnn_mat_t ncnn_rs_mat_create_1d(int w, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, (size_t)4u, unwrap(allocator)));
+}
+
+ncnn_mat_t ncnn_rs_mat_create_2d(int w, int h, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, h, (size_t)4u, unwrap(allocator)));
+}
+
+ncnn_mat_t ncnn_rs_mat_create_3d(int w, int h, int c, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, h, c, (size_t)4u, unwrap(allocator)));
+}
+
+ncnn_mat_t ncnn_rs_mat_create_4d(int w, int h, int d, int c, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, h, d, c, (size_t)4u, unwrap(allocator)));
+}
+
+ncnn_mat_t ncnn_rs_mat_create_external_1d(int w, void* data, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, data, (size_t)4u, unwrap(allocator)));
+}
+
+ncnn_mat_t ncnn_rs_mat_create_external_2d(int w, int h, void* data, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, h, data, (size_t)4u, unwrap(allocator)));
+}
+
+ncnn_mat_t ncnn_rs_mat_create_external_3d(int w, int h, int c, void* data, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, h, c, data, (size_t)4u, unwrap(allocator)));
+}
+
+ncnn_mat_t ncnn_rs_mat_create_external_4d(int w, int h, int d, int c, void* data, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(w, h, d, c, data, (size_t)4u, unwrap(allocator)));
+}
+
+#if NCNN_PIXEL
+ncnn_mat_t ncnn_rs_mat_from_pixels(const unsigned char* pixels, int type, int w, int h, int stride, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(Mat::from_pixels(pixels, type, w, h, stride, unwrap(allocator))));
+}
+
+ncnn_mat_t ncnn_rs_mat_from_pixels_resize(const unsigned char* pixels, int type, int w, int h, int stride, int target_width, int target_height, ncnn_allocator_t allocator)
+{
+    return (ncnn_mat_t)(new Mat(Mat::from_pixels_resize(pixels, type, w, h, stride, target_width, target_height, unwrap(allocator))));
+}
+#endif // NCNN_PIXEL
+
+int ncnn_rs_net_load_model_memory(ncnn_net_t net, const unsigned char* mem, size_t size)
+{
+    BoundedDataReader dr(mem, size);
+    return ((Net*)net->pthis)->load_model(dr);
+}
+
+int ncnn_rs_net_get_blob_count(const ncnn_net_t net)
+{
+    return (int)((const Net*)net->pthis)->blobs().size();
+}
+
+const char* ncnn_rs_net_get_blob_name(const ncnn_net_t net, int i)
+{
+#if NCNN_STRING
+    return ((const Net*)net->pthis)->blobs()[i].name.c_str();
+#else
+    return NULL;
+#endif
+}
+
+void ncnn_rs_extractor_set_blob_allocator(ncnn_extractor_t ex, ncnn_allocator_t allocator)
+{
+    ((Extractor*)ex)->set_blob_allocator(unwrap(allocator));
+}
+
+void ncnn_rs_extractor_set_workspace_allocator(ncnn_extractor_t ex, ncnn_allocator_t allocator)
+{
+    ((Extractor*)ex)->set_workspace_allocator(unwrap(allocator));
+}
+
+int ncnn_rs_layer_available(const char* type)
+{
+#if NCNN_STRING
+    Layer* layer = ncnn::create_layer(type);
+    if (!layer)
+        return 0;
+    delete layer;
+    return 1;
+#else
+    return 0;
+#endif
+}
+
+int ncnn_rs_build_flags(void)
+{
+    int flags = 0;
+#if NCNN_VULKAN
+    flags |= NCNN_RS_BUILD_VULKAN;
+#endif
+#if NCNN_INT8
+    flags |= NCNN_RS_BUILD_INT8;
+#endif
+#if NCNN_BF16
+    flags |= NCNN_RS_BUILD_BF16;
+#endif
+#if NCNN_SIMPLEOMP
+    flags |= NCNN_RS_BUILD_SIMPLEOMP;
+#endif
+#if NCNN_BENCHMARK
+    flags |= NCNN_RS_BUILD_BENCHMARK;
+#endif
+#if NCNN_RUNTIME_CPU
+    flags |= NCNN_RS_BUILD_RUNTIME_CPU;
+#endif
+#if NCNN_AVX
+    flags |= NCNN_RS_BUILD_AVX;
+#endif
+#if NCNN_FMA
+    flags |= NCNN_RS_BUILD_FMA;
+#endif
+#if NCNN_F16C
+    flags |= NCNN_RS_BUILD_F16C;
+#endif
+#if NCNN_XOP
+    flags |= NCNN_RS_BUILD_XOP;
+#endif
+#if NCNN_AVX2
+    flags |= NCNN_RS_BUILD_AVX2;
+#endif
+#if NCNN_AVXVNNI
+    flags |= NCNN_RS_BUILD_AVXVNNI;
+#endif
+#if NCNN_AVX512
+    flags |= NCNN_RS_BUILD_AVX512;
+#endif
+#if NCNN_AVX512VNNI
+    flags |= NCNN_RS_BUILD_AVX512VNNI;
+#endif
+#if NCNN_VFPV4
+    flags |= NCNN_RS_BUILD_VFPV4;
+#endif
+#if NCNN_ARM82
+    flags |= NCNN_RS_BUILD_ARM82;
+#endif
+#if NCNN_ARM82DOT
+    flags |= NCNN_RS_BUILD_ARM82DOT;
+#endif
+    return flags;
+}
+
+int ncnn_rs_get_cpu_count(void)
+{
+    return ncnn::get_cpu_count();
+}
+
+int ncnn_rs_get_little_cpu_count(void)
+{
+    return ncnn::get_little_cpu_count();
+}
+
+int ncnn_rs_get_big_cpu_count(void)
+{
+    return ncnn::get_big_cpu_count();
+}
+
+int ncnn_rs_get_cpu_powersave(void)
+{
+    return ncnn::get_cpu_powersave();
+}
+
+int ncnn_rs_set_cpu_powersave(int powersave)
+{
+    return ncnn::set_cpu_powersave(powersave);
+}
+
+int ncnn_rs_get_cpu_thread_affinity_mask(int powersave, int* cpus, int max_count)
+{
+    const ncnn::CpuSet& mask = ncnn::get_cpu_thread_affinity_mask(powersave);
+
+    int count = 0;
+    int cpu_count = ncnn::get_cpu_count();
+    for (int i = 0; i < cpu_count; i++)
+    {
+        if (!mask.is_enabled(i))
+            continue;
+
+        if (count < max_count)
+            cpus[count] = i;
+        count++;
+    }
+    return count;
+}
+
+int ncnn_rs_set_cpu_thread_affinity(const int* cpus, int count)
+{
+    ncnn::CpuSet mask;
+    mask.disable_all();
+    for (int i = 0; i < count; i++)
+    {
+        mask.enable(cpus[i]);
+    }
+    return ncnn::set_cpu_thread_affinity(mask);
+}
+
+int ncnn_rs_get_omp_num_threads(void)
+{
+    return ncnn::get_omp_num_threads();
+}
+
+void ncnn_rs_set_omp_num_threads(int num_threads)
+{
+    ncnn::set_omp_num_threads(num_threads);
+}
+
+int ncnn_rs_cpu_support_x86_avx(void)
+{
+    return ncnn::cpu_support_x86_avx();
+}
+
+int ncnn_rs_cpu_support_x86_fma(void)
+{
+    return ncnn::cpu_support_x86_fma();
+}
+
+int ncnn_rs_cpu_support_x86_xop(void)
+{
+    return ncnn::cpu_support_x86_xop();
+}
+
+int ncnn_rs_cpu_support_x86_f16c(void)
+{
+    return ncnn::cpu_support_x86_f16c();
+}
+
+int ncnn_rs_cpu_support_x86_avx2(void)
+{
+    return ncnn::cpu_support_x86_avx2();
+}
+
+int ncnn_rs_cpu_support_x86_avx_vnni(void)
+{
+    return ncnn::cpu_support_x86_avx_vnni();
+}
+
+int ncnn_rs_cpu_support_x86_avx512(void)
+{
+    return ncnn::cpu_support_x86_avx512();
+}
+
+int ncnn_rs_cpu_support_x86_avx512_vnni(void)
+{
+    return ncnn::cpu_support_x86_avx512_vnni();
+}
+
+int ncnn_rs_cpu_support_arm_neon(void)
+{
+    return ncnn::cpu_support_arm_neon();
+}
+
+int ncnn_rs_cpu_support_arm_vfpv4(void)
+{
+    return ncnn::cpu_support_arm_vfpv4();
+}
+
+int ncnn_rs_cpu_support_arm_asimdhp(void)
+{
+    return ncnn::cpu_support_arm_asimdhp();
+}
+
+int ncnn_rs_cpu_support_arm_asimddp(void)
+{
+    return ncnn::cpu_support_arm_asimddp();
+}
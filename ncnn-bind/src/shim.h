@@ -0,0 +1,106 @@
+// Helpers for parts of ncnn that `c_api.h` does not expose, or does not expose safely.
+#ifndef NCNN_RS_SHIM_H
+#define NCNN_RS_SHIM_H
+
+#include "c_api.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* allocator api */
+typedef void* (*ncnn_rs_fast_malloc_t)(void* userdata, size_t size);
+typedef void (*ncnn_rs_fast_free_t)(void* userdata, void* ptr);
+
+ncnn_allocator_t ncnn_rs_allocator_create(void* userdata, ncnn_rs_fast_malloc_t fast_malloc, ncnn_rs_fast_free_t fast_free);
+void ncnn_rs_allocator_destroy(ncnn_allocator_t allocator);
+
+/* option api */
+void ncnn_rs_option_set_blob_allocator(ncnn_option_t opt, ncnn_allocator_t allocator);
+void ncnn_rs_option_set_workspace_allocator(ncnn_option_t opt, ncnn_allocator_t allocator);
+int ncnn_rs_option_get_lightmode(const ncnn_option_t opt);
+void ncnn_rs_option_set_lightmode(ncnn_option_t opt, int enabled);
+/* use_fp16_packed, use_fp16_storage and use_fp16_arithmetic together */
+int ncnn_rs_option_get_use_fp16(const ncnn_option_t opt);
+void ncnn_rs_option_set_use_fp16(ncnn_option_t opt, int enabled);
+int ncnn_rs_option_get_use_int8_inference(const ncnn_option_t opt);
+void ncnn_rs_option_set_use_int8_inference(ncnn_option_t opt, int enabled);
+
+/* mat api, resolving `ncnn_allocator_t` to the underlying `ncnn::Allocator` */
+ncnn_mat_t ncnn_rs_mat_create_1d(int w, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_create_2d(int w, int h, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_create_3d(int w, int h, int c, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_create_4d(int w, int h, int d, int c, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_create_external_1d(int w, void* data, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_create_external_2d(int w, int h, void* data, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_create_external_3d(int w, int h, int c, void* data, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_create_external_4d(int w, int h, int d, int c, void* data, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_from_pixels(const unsigned char* pixels, int type, int w, int h, int stride, ncnn_allocator_t allocator);
+ncnn_mat_t ncnn_rs_mat_from_pixels_resize(const unsigned char* pixels, int type, int w, int h, int stride, int target_width, int target_height, ncnn_allocator_t allocator);
+
+/* net api */
+/* like ncnn_net_load_model_memory, but reading no further than size bytes */
+int ncnn_rs_net_load_model_memory(ncnn_net_t net, const unsigned char* mem, size_t size);
+/* blobs of the loaded model, names are NULL when ncnn was built without NCNN_STRING */
+int ncnn_rs_net_get_blob_count(const ncnn_net_t net);
+const char* ncnn_rs_net_get_blob_name(const ncnn_net_t net, int i);
+
+/* extractor api, allocators that ncnn_extractor_set_option does not copy */
+void ncnn_rs_extractor_set_blob_allocator(ncnn_extractor_t ex, ncnn_allocator_t allocator);
+void ncnn_rs_extractor_set_workspace_allocator(ncnn_extractor_t ex, ncnn_allocator_t allocator);
+
+/* layer api */
+/* whether ncnn can create a layer of this type, which WITH_LAYER_<type>=OFF builds cannot */
+int ncnn_rs_layer_available(const char* type);
+
+/* build configuration, the NCNN_* switches of platform.h */
+#define NCNN_RS_BUILD_VULKAN (1 << 0)
+#define NCNN_RS_BUILD_INT8 (1 << 1)
+#define NCNN_RS_BUILD_BF16 (1 << 2)
+#define NCNN_RS_BUILD_SIMPLEOMP (1 << 3)
+#define NCNN_RS_BUILD_BENCHMARK (1 << 4)
+#define NCNN_RS_BUILD_RUNTIME_CPU (1 << 5)
+#define NCNN_RS_BUILD_AVX (1 << 6)
+#define NCNN_RS_BUILD_FMA (1 << 7)
+#define NCNN_RS_BUILD_F16C (1 << 8)
+#define NCNN_RS_BUILD_XOP (1 << 9)
+#define NCNN_RS_BUILD_AVX2 (1 << 10)
+#define NCNN_RS_BUILD_AVXVNNI (1 << 11)
+#define NCNN_RS_BUILD_AVX512 (1 << 12)
+#define NCNN_RS_BUILD_AVX512VNNI (1 << 13)
+#define NCNN_RS_BUILD_VFPV4 (1 << 14)
+#define NCNN_RS_BUILD_ARM82 (1 << 15)
+#define NCNN_RS_BUILD_ARM82DOT (1 << 16)
+
+int ncnn_rs_build_flags(void);
+
+/* cpu api */
+int ncnn_rs_get_cpu_count(void);
+int ncnn_rs_get_little_cpu_count(void);
+int ncnn_rs_get_big_cpu_count(void);
+int ncnn_rs_get_cpu_powersave(void);
+int ncnn_rs_set_cpu_powersave(int powersave);
+/* affinity masks as lists of cpu indices, returning how many are enabled */
+int ncnn_rs_get_cpu_thread_affinity_mask(int powersave, int* cpus, int max_count);
+int ncnn_rs_set_cpu_thread_affinity(const int* cpus, int count);
+int ncnn_rs_get_omp_num_threads(void);
+void ncnn_rs_set_omp_num_threads(int num_threads);
+
+int ncnn_rs_cpu_support_x86_avx(void);
+int ncnn_rs_cpu_support_x86_fma(void);
+int ncnn_rs_cpu_support_x86_xop(void);
+int ncnn_rs_cpu_support_x86_f16c(void);
+int ncnn_rs_cpu_support_x86_avx2(void);
+int ncnn_rs_cpu_support_x86_avx_vnni(void);
+int ncnn_rs_cpu_support_x86_avx512(void);
+int ncnn_rs_cpu_support_x86_avx512_vnni(void);
+int ncnn_rs_cpu_support_arm_neon(void);
+int ncnn_rs_cpu_support_arm_vfpv4(void);
+int ncnn_rs_cpu_support_arm_asimdhp(void);
+int ncnn_rs_cpu_support_arm_asimddp(void);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif // NCNN_RS_SHIM_H
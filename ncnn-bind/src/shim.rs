@@ -0,0 +1,169 @@
+//! Declarations for `shim.cpp`, see `shim.h`.
+use crate::*;
+
+pub type ncnn_rs_fast_malloc_t = ::std::option::Option<
+    unsafe extern "C" fn(
+        userdata: *mut ::std::os::raw::c_void,
+        size: usize,
+    ) -> *mut ::std::os::raw::c_void,
+>;
+pub type ncnn_rs_fast_free_t = ::std::option::Option<
+    unsafe extern "C" fn(userdata: *mut ::std::os::raw::c_void, ptr: *mut ::std::os::raw::c_void),
+>;
+
+pub const NCNN_RS_BUILD_VULKAN: ::std::os::raw::c_int = 1 << 0;
+pub const NCNN_RS_BUILD_INT8: ::std::os::raw::c_int = 1 << 1;
+pub const NCNN_RS_BUILD_BF16: ::std::os::raw::c_int = 1 << 2;
+pub const NCNN_RS_BUILD_SIMPLEOMP: ::std::os::raw::c_int = 1 << 3;
+pub const NCNN_RS_BUILD_BENCHMARK: ::std::os::raw::c_int = 1 << 4;
+pub const NCNN_RS_BUILD_RUNTIME_CPU: ::std::os::raw::c_int = 1 << 5;
+pub const NCNN_RS_BUILD_AVX: ::std::os::raw::c_int = 1 << 6;
+pub const NCNN_RS_BUILD_FMA: ::std::os::raw::c_int = 1 << 7;
+pub const NCNN_RS_BUILD_F16C: ::std::os::raw::c_int = 1 << 8;
+pub const NCNN_RS_BUILD_XOP: ::std::os::raw::c_int = 1 << 9;
+pub const NCNN_RS_BUILD_AVX2: ::std::os::raw::c_int = 1 << 10;
+pub const NCNN_RS_BUILD_AVXVNNI: ::std::os::raw::c_int = 1 << 11;
+pub const NCNN_RS_BUILD_AVX512: ::std::os::raw::c_int = 1 << 12;
+pub const NCNN_RS_BUILD_AVX512VNNI: ::std::os::raw::c_int = 1 << 13;
+pub const NCNN_RS_BUILD_VFPV4: ::std::os::raw::c_int = 1 << 14;
+pub const NCNN_RS_BUILD_ARM82: ::std::os::raw::c_int = 1 << 15;
+pub const NCNN_RS_BUILD_ARM82DOT: ::std::os::raw::c_int = 1 << 16;
+
+extern "C" {
+    pub fn ncnn_rs_allocator_create(
+        userdata: *mut ::std::os::raw::c_void,
+        fast_malloc: ncnn_rs_fast_malloc_t,
+        fast_free: ncnn_rs_fast_free_t,
+    ) -> ncnn_allocator_t;
+    pub fn ncnn_rs_allocator_destroy(allocator: ncnn_allocator_t);
+
+    pub fn ncnn_rs_option_set_blob_allocator(opt: ncnn_option_t, allocator: ncnn_allocator_t);
+    pub fn ncnn_rs_option_set_workspace_allocator(opt: ncnn_option_t, allocator: ncnn_allocator_t);
+    pub fn ncnn_rs_option_get_lightmode(opt: ncnn_option_t) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_option_set_lightmode(opt: ncnn_option_t, enabled: ::std::os::raw::c_int);
+    pub fn ncnn_rs_option_get_use_fp16(opt: ncnn_option_t) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_option_set_use_fp16(opt: ncnn_option_t, enabled: ::std::os::raw::c_int);
+    pub fn ncnn_rs_option_get_use_int8_inference(opt: ncnn_option_t) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_option_set_use_int8_inference(
+        opt: ncnn_option_t,
+        enabled: ::std::os::raw::c_int,
+    );
+
+    pub fn ncnn_rs_mat_create_1d(
+        w: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_create_2d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_create_3d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_create_4d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        d: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_create_external_1d(
+        w: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_create_external_2d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_create_external_3d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_create_external_4d(
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        d: ::std::os::raw::c_int,
+        c: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_void,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_from_pixels(
+        pixels: *const ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        stride: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+    pub fn ncnn_rs_mat_from_pixels_resize(
+        pixels: *const ::std::os::raw::c_uchar,
+        type_: ::std::os::raw::c_int,
+        w: ::std::os::raw::c_int,
+        h: ::std::os::raw::c_int,
+        stride: ::std::os::raw::c_int,
+        target_width: ::std::os::raw::c_int,
+        target_height: ::std::os::raw::c_int,
+        allocator: ncnn_allocator_t,
+    ) -> ncnn_mat_t;
+
+    pub fn ncnn_rs_net_load_model_memory(
+        net: ncnn_net_t,
+        mem: *const ::std::os::raw::c_uchar,
+        size: usize,
+    ) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_net_get_blob_count(net: ncnn_net_t) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_net_get_blob_name(
+        net: ncnn_net_t,
+        i: ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+
+    pub fn ncnn_rs_extractor_set_blob_allocator(ex: ncnn_extractor_t, allocator: ncnn_allocator_t);
+    pub fn ncnn_rs_extractor_set_workspace_allocator(
+        ex: ncnn_extractor_t,
+        allocator: ncnn_allocator_t,
+    );
+
+    pub fn ncnn_rs_layer_available(type_: *const ::std::os::raw::c_char) -> ::std::os::raw::c_int;
+
+    pub fn ncnn_rs_build_flags() -> ::std::os::raw::c_int;
+
+    pub fn ncnn_rs_get_cpu_count() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_get_little_cpu_count() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_get_big_cpu_count() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_get_cpu_powersave() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_set_cpu_powersave(powersave: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_get_cpu_thread_affinity_mask(
+        powersave: ::std::os::raw::c_int,
+        cpus: *mut ::std::os::raw::c_int,
+        max_count: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_set_cpu_thread_affinity(
+        cpus: *const ::std::os::raw::c_int,
+        count: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_get_omp_num_threads() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_set_omp_num_threads(num_threads: ::std::os::raw::c_int);
+
+    pub fn ncnn_rs_cpu_support_x86_avx() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_x86_fma() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_x86_xop() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_x86_f16c() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_x86_avx2() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_x86_avx_vnni() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_x86_avx512() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_x86_avx512_vnni() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_arm_neon() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_arm_vfpv4() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_arm_asimdhp() -> ::std::os::raw::c_int;
+    pub fn ncnn_rs_cpu_support_arm_asimddp() -> ::std::os::raw::c_int;
+}
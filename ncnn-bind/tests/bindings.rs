@@ -0,0 +1,44 @@
+//! Checks the checked-in bindings against what bindgen generates from the ncnn headers.
+//!
+//! Run with `cargo test -p ncnn-bind --features bindgen`; set `NCNN_BIND_UPDATE_BINDINGS=1`
+//! to write the generated bindings to `src/bindings` instead.
+#![cfg(feature = "bindgen")]
+
+use std::fs;
+use std::path::Path;
+
+/// Drops formatting and the generator banner, which depend on the bindgen and rustfmt used.
+fn normalize(bindings: &str) -> String {
+    bindings
+        .lines()
+        .filter(|line| !line.starts_with("/* automatically generated"))
+        .flat_map(|line| line.split_whitespace())
+        .collect()
+}
+
+#[test]
+fn checked_in_bindings_match_header() {
+    let checked_in = Path::new(option_env!("NCNN_BIND_CHECKED_IN_BINDINGS").expect(
+        "couldn't read NCNN_VERSION_STRING from platform.h, so there are no bindings to compare",
+    ));
+    let generated = fs::read_to_string(Path::new(env!("OUT_DIR")).join("bindings.rs")).unwrap();
+
+    if std::env::var_os("NCNN_BIND_UPDATE_BINDINGS").is_some() {
+        fs::write(checked_in, &generated).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(checked_in).unwrap_or_else(|e| {
+        panic!(
+            "{}: {}; run with NCNN_BIND_UPDATE_BINDINGS=1 to add bindings for this ncnn",
+            checked_in.display(),
+            e
+        )
+    });
+    assert!(
+        normalize(&generated) == normalize(&expected),
+        "{} differs from the bindings generated from the installed headers; \
+         run with NCNN_BIND_UPDATE_BINDINGS=1 to update it",
+        checked_in.display()
+    );
+}
@@ -1,41 +1,440 @@
 use ncnn_bind::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::fmt;
+use std::os::raw::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
 
-pub struct Allocator {
-    ptr: ncnn_allocator_t,
-}
+/// Alignment ncnn expects from `fastMalloc`, see `NCNN_MALLOC_ALIGN` in `allocator.h`.
+const MALLOC_ALIGN: usize = 64;
+/// Bytes ncnn may read past the end of a block, see `NCNN_MALLOC_OVERREAD` in `allocator.h`.
+const MALLOC_OVERREAD: usize = 64;
 
-impl Allocator {
-    /// Creates a new pool allocator.
+/// Memory source for ncnn implemented in Rust, see [Allocator::new_custom].
+///
+/// ncnn calls into the allocator from whichever thread runs a layer, including its OpenMP
+/// workers, so implementations have to be thread-safe. Returned blocks must be aligned to
+/// 64 bytes and stay readable for 64 bytes past `size`, as ncnn's own `fastMalloc` guarantees.
+pub trait RustAllocator: Send + Sync + 'static {
+    /// Allocates `size` bytes, returning null when out of memory.
+    fn fast_malloc(&self, size: usize) -> *mut c_void;
+
+    /// Releases a block.
     ///
     /// # Safety
     ///
-    /// Using the allocator when creating matrix results in a segmentation fault.
-    pub unsafe fn new() -> crate::allocator::Allocator {
+    /// `ptr` must have been returned by [RustAllocator::fast_malloc] of the same allocator and
+    /// must not be used afterwards.
+    unsafe fn fast_free(&self, ptr: *mut c_void);
+
+    /// Number of allocations refused so far and the latest of them, for allocators that
+    /// record them like [TrackingAllocator] does.
+    ///
+    /// [crate::Extractor::extract] uses this to report why ncnn ran out of memory.
+    fn failures(&self) -> (usize, Option<AllocationError>) {
+        (0, None)
+    }
+}
+
+/// [RustAllocator] backed by a [GlobalAlloc], [System] by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlobalAllocator<A: GlobalAlloc = System>(pub A);
+
+impl<A: GlobalAlloc + Send + Sync + 'static> RustAllocator for GlobalAllocator<A> {
+    fn fast_malloc(&self, size: usize) -> *mut c_void {
+        // the block size is stored in front of the returned pointer for `fast_free`
+        let layout = match size
+            .checked_add(MALLOC_ALIGN + MALLOC_OVERREAD)
+            .and_then(|total| Layout::from_size_align(total, MALLOC_ALIGN).ok())
+        {
+            Some(layout) => layout,
+            None => return core::ptr::null_mut(),
+        };
+        unsafe {
+            let base = self.0.alloc(layout);
+            if base.is_null() {
+                return core::ptr::null_mut();
+            }
+            (base as *mut usize).write(layout.size());
+            base.add(MALLOC_ALIGN) as *mut c_void
+        }
+    }
+
+    unsafe fn fast_free(&self, ptr: *mut c_void) {
+        let base = (ptr as *mut u8).sub(MALLOC_ALIGN);
+        let size = (base as *const usize).read();
+        self.0
+            .dealloc(base, Layout::from_size_align_unchecked(size, MALLOC_ALIGN));
+    }
+}
+
+unsafe extern "C" fn rust_fast_malloc(userdata: *mut c_void, size: usize) -> *mut c_void {
+    let allocator = &*(userdata as *const Box<dyn RustAllocator>);
+    catch_unwind(AssertUnwindSafe(|| allocator.fast_malloc(size))).unwrap_or(core::ptr::null_mut())
+}
+
+unsafe extern "C" fn rust_fast_free(userdata: *mut c_void, ptr: *mut c_void) {
+    let allocator = &*(userdata as *const Box<dyn RustAllocator>);
+    if catch_unwind(AssertUnwindSafe(|| allocator.fast_free(ptr))).is_err() {
+        std::process::abort();
+    }
+}
+
+struct AllocatorInner {
+    ptr: ncnn_allocator_t,
+    destroy: unsafe extern "C" fn(ncnn_allocator_t),
+    custom: *mut Box<dyn RustAllocator>,
+}
+
+// ncnn allocators are called from ncnn's worker threads anyway; the one allocator that is not
+// thread-safe can only be created through the unsafe `Allocator::new_unlocked`.
+unsafe impl Send for AllocatorInner {}
+unsafe impl Sync for AllocatorInner {}
+
+impl Drop for AllocatorInner {
+    fn drop(&mut self) {
+        unsafe {
+            (self.destroy)(self.ptr);
+            if !self.custom.is_null() {
+                drop(Box::from_raw(self.custom));
+            }
+        }
+    }
+}
+
+/// Handle to an ncnn allocator.
+///
+/// Cloning is cheap and shares the same allocator. Every [crate::Mat] created with an allocator
+/// keeps a clone, so the allocator outlives all memory it handed out.
+#[derive(Clone)]
+pub struct Allocator {
+    inner: Arc<AllocatorInner>,
+}
+
+impl Allocator {
+    fn from_ptr(ptr: ncnn_allocator_t) -> Allocator {
         Allocator {
-            ptr: ncnn_allocator_create_pool_allocator(),
+            inner: Arc::new(AllocatorInner {
+                ptr,
+                destroy: ncnn_allocator_destroy,
+                custom: core::ptr::null_mut(),
+            }),
         }
     }
 
+    /// Creates a new pool allocator.
+    pub fn new() -> Allocator {
+        Self::from_ptr(unsafe { ncnn_allocator_create_pool_allocator() })
+    }
+
     /// Creates a new unlocked pool allocator.
     ///
+    /// It is faster than [Allocator::new] and is what ncnn recommends for workspace memory.
+    ///
     /// # Safety
     ///
-    /// Using the allocator when creating matrix results in a segmentation fault.
-    pub unsafe fn new_unlocked() -> crate::allocator::Allocator {
+    /// The allocator is not thread-safe: it must only be used by one inference at a time, and
+    /// that inference must run with a single thread or use it as its workspace allocator only.
+    pub unsafe fn new_unlocked() -> Allocator {
+        Self::from_ptr(unsafe { ncnn_allocator_create_unlocked_pool_allocator() })
+    }
+
+    /// Creates an allocator whose `fastMalloc`/`fastFree` are served by `allocator`.
+    pub fn new_custom<A: RustAllocator>(allocator: A) -> Allocator {
+        let custom = Box::into_raw(Box::new(Box::new(allocator) as Box<dyn RustAllocator>));
+        let ptr = unsafe {
+            ncnn_rs_allocator_create(
+                custom as *mut c_void,
+                Some(rust_fast_malloc),
+                Some(rust_fast_free),
+            )
+        };
         Allocator {
-            ptr: ncnn_allocator_create_unlocked_pool_allocator(),
+            inner: Arc::new(AllocatorInner {
+                ptr,
+                destroy: ncnn_rs_allocator_destroy,
+                custom,
+            }),
         }
     }
 
     pub(crate) fn ptr(&self) -> ncnn_allocator_t {
-        self.ptr
+        self.inner.ptr
     }
 }
 
-impl Drop for Allocator {
-    fn drop(&mut self) {
+impl Default for Allocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Calls straight into the ncnn allocator, so it can be wrapped, e.g. by [TrackingAllocator].
+impl RustAllocator for Allocator {
+    fn fast_malloc(&self, size: usize) -> *mut c_void {
+        let ptr = self.ptr();
         unsafe {
-            ncnn_allocator_destroy(self.ptr);
+            match (*ptr).fast_malloc {
+                Some(fast_malloc) => fast_malloc(ptr, size),
+                None => core::ptr::null_mut(),
+            }
+        }
+    }
+
+    unsafe fn fast_free(&self, ptr: *mut c_void) {
+        let allocator = self.ptr();
+        if let Some(fast_free) = (*allocator).fast_free {
+            fast_free(allocator, ptr);
+        }
+    }
+
+    fn failures(&self) -> (usize, Option<AllocationError>) {
+        match unsafe { self.inner.custom.as_ref() } {
+            Some(custom) => custom.failures(),
+            None => (0, None),
         }
     }
 }
+
+/// Failure counts of `allocators`, for [allocation_failure_since].
+pub(crate) fn allocation_failures(allocators: &[Allocator]) -> Vec<usize> {
+    allocators.iter().map(|a| a.failures().0).collect()
+}
+
+/// Returns the latest failure of an allocator in `allocators` that failed again since
+/// `allocation_failures(allocators)` returned `since`.
+pub(crate) fn allocation_failure_since(
+    allocators: &[Allocator],
+    since: &[usize],
+) -> Option<AllocationError> {
+    allocators
+        .iter()
+        .zip(since)
+        .map(|(allocator, &since)| (allocator.failures(), since))
+        .find(|&((failures, _), since)| failures != since)
+        .and_then(|((_, last), _)| last)
+}
+
+/// An allocation refused by a [TrackingAllocator].
+///
+/// When this happens during inference, ncnn aborts the layer and [crate::Extractor::extract]
+/// returns this error, which can be recovered with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationError {
+    /// Size of the refused block.
+    pub requested: usize,
+    /// Bytes in use when the allocation was attempted.
+    pub in_use: usize,
+    /// The configured limit, or `None` when the wrapped allocator itself ran out of memory.
+    pub limit: Option<usize>,
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            Some(limit) => write!(
+                f,
+                "Allocation of {} bytes exceeds the limit of {} bytes ({} bytes in use)",
+                self.requested, limit, self.in_use
+            ),
+            None => write!(
+                f,
+                "Allocation of {} bytes failed ({} bytes in use)",
+                self.requested, self.in_use
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AllocationError {}
+
+/// Allocation statistics of a [TrackingAllocator].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// Bytes currently allocated.
+    pub current_bytes: usize,
+    /// Highest value `current_bytes` has reached.
+    pub peak_bytes: usize,
+    /// Number of successful allocations.
+    pub allocations: usize,
+    /// Number of blocks released.
+    pub frees: usize,
+    /// Size of the largest block allocated.
+    pub largest_block: usize,
+    /// Number of allocations refused or failed.
+    pub failed_allocations: usize,
+}
+
+struct TrackingState {
+    stats: AllocatorStats,
+    blocks: HashMap<usize, usize>,
+    last_failure: Option<AllocationError>,
+}
+
+struct TrackingInner {
+    target: Box<dyn RustAllocator>,
+    limit: Option<usize>,
+    state: Mutex<TrackingState>,
+}
+
+/// [RustAllocator] that records statistics about another allocator and can cap its usage.
+///
+/// Clones share the same statistics, so keep one to query while ncnn uses the other:
+///
+/// ```no_run
+/// use ncnn_rs::{Allocator, Net, Option, TrackingAllocator};
+///
+/// let tracker = TrackingAllocator::with_limit(Allocator::new(), 256 << 20);
+/// let mut opt = Option::new();
+/// opt.set_blob_allocator(&Allocator::new_custom(tracker.clone()));
+///
+/// let mut net = Net::new();
+/// net.set_option(&opt);
+/// // ... load the model and run inference ...
+/// println!("peak: {} bytes", tracker.stats().peak_bytes);
+/// ```
+#[derive(Clone)]
+pub struct TrackingAllocator {
+    inner: Arc<TrackingInner>,
+}
+
+impl TrackingAllocator {
+    /// Tracks `target` without limiting it.
+    pub fn new<A: RustAllocator>(target: A) -> TrackingAllocator {
+        Self::with_optional_limit(target, None)
+    }
+
+    /// Tracks `target` and refuses allocations that would bring usage over `limit` bytes.
+    pub fn with_limit<A: RustAllocator>(target: A, limit: usize) -> TrackingAllocator {
+        Self::with_optional_limit(target, Some(limit))
+    }
+
+    fn with_optional_limit<A: RustAllocator>(target: A, limit: Option<usize>) -> Self {
+        TrackingAllocator {
+            inner: Arc::new(TrackingInner {
+                target: Box::new(target),
+                limit,
+                state: Mutex::new(TrackingState {
+                    stats: AllocatorStats::default(),
+                    blocks: HashMap::new(),
+                    last_failure: None,
+                }),
+            }),
+        }
+    }
+
+    /// Returns a snapshot of the statistics.
+    pub fn stats(&self) -> AllocatorStats {
+        self.inner.state.lock().unwrap().stats
+    }
+
+    /// Returns the usage limit in bytes.
+    pub fn limit(&self) -> Option<usize> {
+        self.inner.limit
+    }
+
+    /// Resets peak, counters, largest block and the last failure, keeping the bytes currently
+    /// in use.
+    pub fn reset_stats(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        let current_bytes = state.stats.current_bytes;
+        state.stats = AllocatorStats {
+            current_bytes,
+            peak_bytes: current_bytes,
+            ..AllocatorStats::default()
+        };
+        state.last_failure = None;
+    }
+
+    fn fail(&self, state: &mut TrackingState, requested: usize, limit: Option<usize>) {
+        state.stats.failed_allocations += 1;
+        state.last_failure = Some(AllocationError {
+            requested,
+            in_use: state.stats.current_bytes,
+            limit,
+        });
+    }
+}
+
+impl RustAllocator for TrackingAllocator {
+    fn fast_malloc(&self, size: usize) -> *mut c_void {
+        // reserve the bytes under the lock, so concurrent allocations count them against the
+        // limit, but call the wrapped allocator without it
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            if let Some(limit) = self.inner.limit {
+                if state.stats.current_bytes.saturating_add(size) > limit {
+                    self.fail(&mut state, size, Some(limit));
+                    return core::ptr::null_mut();
+                }
+            }
+            state.stats.current_bytes += size;
+        }
+
+        let ptr = self.inner.target.fast_malloc(size);
+        let mut state = self.inner.state.lock().unwrap();
+        if ptr.is_null() {
+            state.stats.current_bytes -= size;
+            self.fail(&mut state, size, None);
+            return ptr;
+        }
+
+        let stats = &mut state.stats;
+        stats.peak_bytes = stats.peak_bytes.max(stats.current_bytes);
+        stats.allocations += 1;
+        stats.largest_block = stats.largest_block.max(size);
+        state.blocks.insert(ptr as usize, size);
+        ptr
+    }
+
+    unsafe fn fast_free(&self, ptr: *mut c_void) {
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            if let Some(size) = state.blocks.remove(&(ptr as usize)) {
+                state.stats.current_bytes -= size;
+                state.stats.frees += 1;
+            }
+        }
+        self.inner.target.fast_free(ptr);
+    }
+
+    fn failures(&self) -> (usize, Option<AllocationError>) {
+        let state = self.inner.state.lock().unwrap();
+        (state.stats.failed_allocations, state.last_failure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::allocator::*;
+
+    #[test]
+    fn global_allocator_alignment() {
+        let allocator = GlobalAllocator::<System>::default();
+        let ptr = allocator.fast_malloc(100);
+        assert!(!ptr.is_null());
+        assert_eq!(ptr as usize % MALLOC_ALIGN, 0);
+        unsafe { allocator.fast_free(ptr) };
+    }
+
+    #[test]
+    fn tracking_allocator_limit() {
+        let tracker = TrackingAllocator::with_limit(GlobalAllocator::<System>::default(), 1000);
+        let a = tracker.fast_malloc(600);
+        assert!(!a.is_null());
+        assert_eq!(tracker.failures(), (0, None));
+        assert!(tracker.fast_malloc(600).is_null());
+        let other = TrackingAllocator::with_limit(GlobalAllocator::<System>::default(), 0);
+        assert!(other.fast_malloc(10).is_null());
+        assert_eq!(tracker.failures().1.map(|e| e.requested), Some(600));
+        unsafe { tracker.fast_free(a) };
+
+        let stats = tracker.stats();
+        assert_eq!(stats.current_bytes, 0);
+        assert_eq!(stats.peak_bytes, 600);
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.frees, 1);
+        assert_eq!(stats.failed_allocations, 1);
+    }
+}
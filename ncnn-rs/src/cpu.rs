@@ -0,0 +1,186 @@
+//! CPU topology, power saving and feature detection, as ncnn sees them.
+//!
+//! ```no_run
+//! use ncnn_rs::cpu::{self, PowerSave};
+//!
+//! // run on the big cores only, one thread each
+//! let threads = if cpu::big_cpu_count() > 0 && cpu::set_powersave(PowerSave::BigCores).is_ok() {
+//!     cpu::big_cpu_count()
+//! } else {
+//!     cpu::physical_cpu_count()
+//! };
+//! let mut opt = ncnn_rs::Option::new();
+//! opt.set_num_threads(threads as u32);
+//! ```
+use ncnn_bind::*;
+use std::fs;
+use std::os::raw::c_int;
+use std::path::Path;
+
+/// Which cores ncnn binds its worker threads to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerSave {
+    /// All cores, without binding.
+    All,
+    /// Little cores only, on big.LITTLE systems.
+    LittleCores,
+    /// Big cores only, on big.LITTLE systems.
+    BigCores,
+}
+
+impl PowerSave {
+    fn to_raw(self) -> c_int {
+        match self {
+            PowerSave::All => 0,
+            PowerSave::LittleCores => 1,
+            PowerSave::BigCores => 2,
+        }
+    }
+
+    fn from_raw(powersave: c_int) -> PowerSave {
+        match powersave {
+            1 => PowerSave::LittleCores,
+            2 => PowerSave::BigCores,
+            _ => PowerSave::All,
+        }
+    }
+}
+
+/// Number of logical CPUs.
+pub fn cpu_count() -> usize {
+    unsafe { ncnn_rs_get_cpu_count() as usize }
+}
+
+/// Number of little cores on big.LITTLE systems, 0 elsewhere.
+pub fn little_cpu_count() -> usize {
+    unsafe { ncnn_rs_get_little_cpu_count() as usize }
+}
+
+/// Number of big cores on big.LITTLE systems, or all cores elsewhere.
+pub fn big_cpu_count() -> usize {
+    unsafe { ncnn_rs_get_big_cpu_count() as usize }
+}
+
+/// Number of physical cores, not counting SMT siblings.
+///
+/// Read from sysfs on Linux and Android; elsewhere this is [cpu_count].
+pub fn physical_cpu_count() -> usize {
+    physical_cores_in(Path::new("/sys/devices/system/cpu")).unwrap_or_else(cpu_count)
+}
+
+/// Counts distinct `(package, core)` pairs in a sysfs CPU directory.
+fn physical_cores_in(sysfs_cpu: &Path) -> std::option::Option<usize> {
+    let mut cores = Vec::new();
+    for entry in fs::read_dir(sysfs_cpu).ok()? {
+        let path = entry.ok()?.path();
+        let name = path.file_name()?.to_str()?;
+        let is_cpu = name
+            .strip_prefix("cpu")
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if !is_cpu {
+            continue;
+        }
+        let read = |file: &str| fs::read_to_string(path.join("topology").join(file)).ok();
+        let core = (read("physical_package_id")?, read("core_id")?);
+        if !cores.contains(&core) {
+            cores.push(core);
+        }
+    }
+    (!cores.is_empty()).then_some(cores.len())
+}
+
+/// The cores ncnn currently binds its worker threads to.
+pub fn powersave() -> PowerSave {
+    PowerSave::from_raw(unsafe { ncnn_rs_get_cpu_powersave() })
+}
+
+/// Binds ncnn's worker threads to the given cores.
+///
+/// Fails where ncnn cannot bind threads, such as on macOS, or when there are no cores of
+/// the requested kind.
+pub fn set_powersave(powersave: PowerSave) -> anyhow::Result<()> {
+    if unsafe { ncnn_rs_set_cpu_powersave(powersave.to_raw()) } != 0 {
+        anyhow::bail!("Error setting powersave mode {:?}", powersave);
+    }
+    Ok(())
+}
+
+/// The CPUs a powersave mode binds threads to.
+pub fn thread_affinity_mask(powersave: PowerSave) -> Vec<usize> {
+    let mut cpus = vec![0 as c_int; cpu_count()];
+    let count = unsafe {
+        ncnn_rs_get_cpu_thread_affinity_mask(
+            powersave.to_raw(),
+            cpus.as_mut_ptr(),
+            cpus.len() as c_int,
+        )
+    };
+    cpus.truncate(count as usize);
+    cpus.into_iter().map(|cpu| cpu as usize).collect()
+}
+
+/// Binds ncnn's worker threads to the given CPUs.
+pub fn set_thread_affinity(cpus: &[usize]) -> anyhow::Result<()> {
+    let count = cpu_count();
+    if let Some(cpu) = cpus.iter().find(|&&cpu| cpu >= count) {
+        anyhow::bail!("CPU {} out of range, there are {} CPUs", cpu, count);
+    }
+    let cpus: Vec<c_int> = cpus.iter().map(|&cpu| cpu as c_int).collect();
+    if unsafe { ncnn_rs_set_cpu_thread_affinity(cpus.as_ptr(), cpus.len() as c_int) } != 0 {
+        anyhow::bail!("Error setting thread affinity to {:?}", cpus);
+    }
+    Ok(())
+}
+
+/// Number of OpenMP threads ncnn uses, 1 without OpenMP.
+pub fn omp_num_threads() -> usize {
+    unsafe { ncnn_rs_get_omp_num_threads() as usize }
+}
+
+pub fn set_omp_num_threads(num_threads: usize) {
+    unsafe { ncnn_rs_set_omp_num_threads(num_threads as c_int) }
+}
+
+/// The CPU supports AVX2.
+pub fn has_avx2() -> bool {
+    unsafe { ncnn_rs_cpu_support_x86_avx2() != 0 }
+}
+
+/// The CPU supports AVX-512 (F, CD, BW, DQ and VL).
+pub fn has_avx512() -> bool {
+    unsafe { ncnn_rs_cpu_support_x86_avx512() != 0 }
+}
+
+/// The CPU supports NEON.
+pub fn has_neon() -> bool {
+    unsafe { ncnn_rs_cpu_support_arm_neon() != 0 }
+}
+
+/// The CPU supports fp16 arithmetic, i.e. ARMv8.2 half-precision SIMD.
+///
+/// fp16 storage only needs NEON on ARM or F16C on x86, see [crate::Capabilities].
+pub fn has_fp16() -> bool {
+    unsafe { ncnn_rs_cpu_support_arm_asimdhp() != 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn physical_cores_from_sysfs() {
+        let dir = std::env::temp_dir().join(format!("ncnn-rs-cpu-{}", std::process::id()));
+        // two cores with two SMT siblings each
+        for (cpu, core) in [(0, 0), (1, 1), (2, 0), (3, 1)] {
+            let topology = dir.join(format!("cpu{}", cpu)).join("topology");
+            fs::create_dir_all(&topology).unwrap();
+            fs::write(topology.join("physical_package_id"), "0\n").unwrap();
+            fs::write(topology.join("core_id"), format!("{}\n", core)).unwrap();
+        }
+        fs::create_dir_all(dir.join("cpufreq")).unwrap();
+
+        assert_eq!(physical_cores_in(&dir), Some(2));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(physical_cores_in(&dir), None);
+    }
+}
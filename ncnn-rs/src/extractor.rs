@@ -1,43 +1,119 @@
+use crate::allocator::{allocation_failure_since, allocation_failures, Allocator};
+use crate::mat::Mat;
 use ncnn_bind::*;
-use std::{ffi::CString, marker::PhantomData};
+use std::{
+    ffi::{CStr, CString},
+    marker::PhantomData,
+};
 
 pub struct Extractor<'a> {
     ptr: ncnn_extractor_t,
+    // the net this extractor was created from, for its blob list
+    net: ncnn_net_t,
+    // every allocator blobs of this extractor may have come from
+    allocators: Vec<Allocator>,
     _phantom: PhantomData<&'a ()>,
 }
 
 impl<'a> Extractor<'a> {
-    pub(crate) fn from_ptr(ptr: ncnn_extractor_t) -> Self {
+    pub(crate) fn from_ptr(
+        ptr: ncnn_extractor_t,
+        net: ncnn_net_t,
+        allocators: Vec<Allocator>,
+    ) -> Self {
         Self {
             ptr,
+            net,
+            allocators,
             _phantom: PhantomData::default(),
         }
     }
 
     /// Sets extractor option.
+    ///
+    /// Only `num_threads` and `use_vulkan_compute` are taken from `opt`, allocators set on it
+    /// are ignored. Use [Extractor::set_blob_allocator] and
+    /// [Extractor::set_workspace_allocator] instead.
     pub fn set_option(&mut self, opt: &crate::option::Option) {
         unsafe { ncnn_extractor_set_option(self.ptr, opt.ptr()) };
     }
 
+    /// Sets the allocator for blobs computed by this extractor, including extracted outputs.
+    pub fn set_blob_allocator(&mut self, allocator: &Allocator) {
+        unsafe { ncnn_rs_extractor_set_blob_allocator(self.ptr, allocator.ptr()) };
+        // blobs computed before keep memory from the previous allocators
+        self.allocators.push(allocator.clone());
+    }
+
+    /// Sets the allocator for temporary memory used inside layers of this extractor.
+    pub fn set_workspace_allocator(&mut self, allocator: &Allocator) {
+        unsafe { ncnn_rs_extractor_set_workspace_allocator(self.ptr, allocator.ptr()) };
+        self.allocators.push(allocator.clone());
+    }
+
     /// Sets input tensor by a given name.
     pub fn input(&mut self, name: &str, mat: &'a crate::mat::Mat) -> anyhow::Result<()> {
         let c_str = CString::new(name).unwrap();
         if unsafe { ncnn_extractor_input(self.ptr, c_str.as_ptr(), mat.as_ptr()) } != 0 {
             anyhow::bail!("Error setting input for layer `{}`", name);
         } else {
+            // extracting the input blob, or one a layer computed in place, shares its data
+            self.allocators.extend_from_slice(mat.allocators());
             Ok(())
         }
     }
 
     /// Runs network inferrence and returns output tensor by a given name.
+    ///
+    /// Fails with [crate::AllocationError] when a [crate::TrackingAllocator] behind one of the
+    /// net's or extractor's allocators refused memory.
     pub fn extract(&self, name: &str, mat: &mut crate::mat::Mat) -> anyhow::Result<()> {
         let c_str = CString::new(name).unwrap();
+        let failures = allocation_failures(&self.allocators);
         if unsafe { ncnn_extractor_extract(self.ptr, c_str.as_ptr(), mat.as_mut_ptr()) } != 0 {
+            if let Some(err) = allocation_failure_since(&self.allocators, &failures) {
+                return Err(anyhow::Error::new(err)
+                    .context(format!("Error running extract on layer `{}`", name)));
+            }
             anyhow::bail!("Error running extract on layer `{}`", name);
         } else {
+            mat.retain_allocators(&self.allocators);
             Ok(())
         }
     }
+
+    /// Names of the blobs of the loaded model, in the order the `.param` declares them.
+    ///
+    /// Empty when ncnn was built without `NCNN_STRING`, as blobs then have no names.
+    pub fn blob_names(&self) -> Vec<String> {
+        let count = unsafe { ncnn_rs_net_get_blob_count(self.net) };
+        (0..count)
+            .map_while(|i| {
+                let name = unsafe { ncnn_rs_net_get_blob_name(self.net, i) };
+                if name.is_null() {
+                    return None;
+                }
+                Some(
+                    unsafe { CStr::from_ptr(name) }
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            })
+            .collect()
+    }
+
+    /// Extracts every blob of the loaded model, in the order of [Extractor::blob_names].
+    ///
+    /// Extracting a blob runs only the layers it needs that have not run yet, so going
+    /// through all of them costs one inference. Blobs of `Input` layers that were not
+    /// given an input fail to extract.
+    pub fn extract_all(&self) -> impl Iterator<Item = anyhow::Result<(String, Mat)>> + '_ {
+        self.blob_names().into_iter().map(move |name| {
+            let mut mat = Mat::new();
+            self.extract(&name, &mut mat)?;
+            Ok((name, mat))
+        })
+    }
 }
 
 impl<'a> Drop for Extractor<'a> {
@@ -47,3 +123,28 @@ impl<'a> Drop for Extractor<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::allocator::Allocator;
+    use crate::mat::Mat;
+    use crate::net::Net;
+
+    #[test]
+    fn input_blob_outlives_input() {
+        let mut net = Net::new();
+        net.load_param_memory("7767517\n1 1\nInput data 0 1 data 0=4 1=1 2=1\n")
+            .unwrap();
+        let mut out = Mat::new();
+        {
+            let allocator = Allocator::new();
+            let mut data = Mat::new_3d(4, 1, 1, Some(&allocator));
+            data.fill(2.0);
+            let mut ex = net.create_extractor();
+            ex.input("data", &data).unwrap();
+            ex.extract("data", &mut out).unwrap();
+        }
+        assert_eq!(out.allocators().len(), 1);
+        assert_eq!(&out.channel_data::<f32>(0)[..4], &[2.0; 4]);
+    }
+}
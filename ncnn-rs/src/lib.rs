@@ -1,9 +1,16 @@
 mod allocator;
+pub mod cpu;
 mod datareader;
 mod extractor;
 mod mat;
+pub mod modelbin;
 mod net;
+pub mod npy;
 mod option;
+pub mod param;
+mod profile;
+pub mod quantize;
+mod version;
 
 pub use allocator::*;
 pub use datareader::*;
@@ -11,14 +18,7 @@ pub use extractor::*;
 pub use mat::*;
 pub use net::*;
 pub use option::*;
+pub use profile::*;
+pub use version::*;
 
 pub use ncnn_bind as ffi;
-
-use std::ffi::CStr;
-
-pub fn version() -> &'static str {
-    let c_buf = unsafe { ffi::ncnn_version() };
-    let c_str = unsafe { CStr::from_ptr(c_buf) };
-    let str_slice: &str = c_str.to_str().unwrap();
-    str_slice
-}
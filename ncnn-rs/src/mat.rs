@@ -4,6 +4,8 @@ use ncnn_bind::*;
 use std::ops::{Index, IndexMut, RangeTo};
 use std::os::raw::c_void;
 
+pub mod ops;
+
 pub enum MatPixelType {
     BGR,
     BGRA,
@@ -52,6 +54,8 @@ impl MatPixelType {
 
 pub struct Mat {
     ptr: ncnn_mat_t,
+    // keeps the allocators alive until the matrix data is released
+    allocators: Vec<Allocator>,
 }
 
 // https://github.com/Tencent/ncnn/blob/5eb56b2ea5a99fb5a3d6f3669ef1743b73a9a53e/src/mat.h#L224
@@ -68,11 +72,12 @@ impl Mat {
     pub fn new_1d(w: i32, alloc: Option<&Allocator>) -> Self {
         Self {
             ptr: unsafe {
-                ncnn_mat_create_1d(
+                ncnn_rs_mat_create_1d(
                     w,
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -80,12 +85,13 @@ impl Mat {
     pub fn new_2d(w: i32, h: i32, alloc: Option<&Allocator>) -> Self {
         Self {
             ptr: unsafe {
-                ncnn_mat_create_2d(
+                ncnn_rs_mat_create_2d(
                     w,
                     h,
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -93,13 +99,14 @@ impl Mat {
     pub fn new_3d(w: i32, h: i32, c: i32, alloc: Option<&Allocator>) -> Self {
         Self {
             ptr: unsafe {
-                ncnn_mat_create_3d(
+                ncnn_rs_mat_create_3d(
                     w,
                     h,
                     c,
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -107,7 +114,7 @@ impl Mat {
     pub fn new_4d(w: i32, h: i32, d: i32, c: i32, alloc: Option<&Allocator>) -> Self {
         Self {
             ptr: unsafe {
-                ncnn_mat_create_4d(
+                ncnn_rs_mat_create_4d(
                     w,
                     h,
                     d,
@@ -115,6 +122,7 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -125,11 +133,12 @@ impl Mat {
     /// Data pointer must not be aliased, it must be valid for the entire lifetime of Mat and it must be of correct size.
     pub unsafe fn new_external_1d(w: i32, data: *mut c_void, alloc: Option<&Allocator>) -> Self {
         Self {
-            ptr: ncnn_mat_create_external_1d(
+            ptr: ncnn_rs_mat_create_external_1d(
                 w,
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -145,12 +154,13 @@ impl Mat {
         alloc: Option<&Allocator>,
     ) -> Self {
         Self {
-            ptr: ncnn_mat_create_external_2d(
+            ptr: ncnn_rs_mat_create_external_2d(
                 w,
                 h,
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -167,13 +177,14 @@ impl Mat {
         alloc: Option<&Allocator>,
     ) -> Self {
         Self {
-            ptr: ncnn_mat_create_external_3d(
+            ptr: ncnn_rs_mat_create_external_3d(
                 w,
                 h,
                 c,
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -191,7 +202,7 @@ impl Mat {
         alloc: Option<&Allocator>,
     ) -> Self {
         Self {
-            ptr: ncnn_mat_create_external_4d(
+            ptr: ncnn_rs_mat_create_external_4d(
                 w,
                 h,
                 d,
@@ -199,6 +210,7 @@ impl Mat {
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            allocators: alloc.cloned().into_iter().collect(),
         }
     }
 
@@ -211,13 +223,13 @@ impl Mat {
         alloc: Option<&Allocator>,
     ) -> anyhow::Result<Mat> {
         let len = width * height * pixel_type.stride();
-        if data.len() != len as _ {
+        if data.len() != len as usize {
             anyhow::bail!("Expected data length {}, provided {}", len, data.len());
         }
 
         Ok(Self {
             ptr: unsafe {
-                ncnn_mat_from_pixels(
+                ncnn_rs_mat_from_pixels(
                     data.as_ptr(),
                     pixel_type.to_int(),
                     width,
@@ -226,6 +238,7 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            allocators: alloc.cloned().into_iter().collect(),
         })
     }
 
@@ -247,7 +260,7 @@ impl Mat {
         //     anyhow::bail!("Expected data length {}, provided {}", len, data.len());
         // }
         unsafe {
-            let ptr = ncnn_mat_from_pixels_resize(
+            let ptr = ncnn_rs_mat_from_pixels_resize(
                 data.as_ptr(),
                 pixel_type,
                 w,
@@ -257,7 +270,10 @@ impl Mat {
                 model_h,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             );
-            Ok(Mat { ptr })
+            Ok(Mat {
+                ptr,
+                allocators: alloc.cloned().into_iter().collect(),
+            })
         }
     }
 
@@ -300,6 +316,18 @@ impl Mat {
         unsafe { ncnn_mat_get_c(self.ptr) }
     }
 
+    /// Returns matrix dims and extents as a [crate::param::Shape]
+    pub fn shape(&self) -> crate::param::Shape {
+        use crate::param::Shape;
+        let [w, h, d, c] = [self.w(), self.h(), self.d(), self.c()].map(|v| v.max(0) as usize);
+        match self.dims() {
+            1 => Shape::new_1d(w),
+            2 => Shape::new_2d(w, h),
+            4 => Shape::new_4d(w, h, d, c),
+            _ => Shape::new_3d(w, h, c),
+        }
+    }
+
     pub fn elemsize(&self) -> u64 {
         (unsafe { ncnn_mat_get_elemsize(self.ptr) }) as u64
     }
@@ -326,7 +354,10 @@ impl Mat {
     }
 
     pub unsafe fn from_ptr(ptr: ncnn_mat_t) -> Self {
-        Self { ptr: ptr }
+        Self {
+            ptr,
+            allocators: Vec::new(),
+        }
     }
 
     /// The impl of C API has used `data` so you can't retrieve the Matrix.
@@ -348,10 +379,39 @@ impl Mat {
         }
     }
 
+    /// The values of a float32 matrix, channel after channel, skipping the padding between
+    /// channels.
+    ///
+    /// Fails for other element types and for packed matrices, like blobs extracted with fp16
+    /// storage or packing enabled.
+    pub fn to_vec(&self) -> anyhow::Result<Vec<f32>> {
+        if self.elemsize() != 4 || self.elempack() != 1 {
+            anyhow::bail!(
+                "Only unpacked float32 matrices can be flattened, not elemsize {} elempack {}",
+                self.elemsize(),
+                self.elempack()
+            );
+        }
+        let len = (self.w() * self.h() * self.d().max(1)) as usize;
+        Ok((0..self.c())
+            .flat_map(|c| self.channel_data::<f32>(c)[..len].iter().copied())
+            .collect())
+    }
+
     pub unsafe fn set_ptr(&mut self, ptr: ncnn_mat_t) {
         self.ptr = ptr;
     }
 
+    /// Allocators this matrix keeps alive.
+    pub(crate) fn allocators(&self) -> &[Allocator] {
+        &self.allocators
+    }
+
+    /// Ties the lifetime of `allocators` to this matrix, for data ncnn allocated from them.
+    pub(crate) fn retain_allocators(&mut self, allocators: &[Allocator]) {
+        self.allocators = allocators.to_vec();
+    }
+
     /// ```c
     /// NCNN_FORCEINLINE size_t Mat::total() const {
     ///  return cstep * c;
@@ -396,6 +456,7 @@ impl Default for Mat {
     fn default() -> Self {
         Self {
             ptr: unsafe { ncnn_mat_create() },
+            allocators: Vec::new(),
         }
     }
 }
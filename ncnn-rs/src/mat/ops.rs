@@ -0,0 +1,233 @@
+//! Element-wise math and reductions over [Mat].
+//!
+//! All functions work on unpacked fp32 matrices (`elempack == 1`, `elemsize == 4`), which is what
+//! [crate::Extractor::extract] returns with the default option. Channel padding (`cstep` may be
+//! larger than `w * h * d`) is skipped, so results never depend on uninitialized memory.
+use crate::mat::Mat;
+
+/// Axis of a [Mat], named after ncnn's `w`, `h`, `d` and `c` dimensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    W,
+    H,
+    D,
+    C,
+}
+
+impl Axis {
+    fn index(self) -> usize {
+        match self {
+            Axis::W => 0,
+            Axis::H => 1,
+            Axis::D => 2,
+            Axis::C => 3,
+        }
+    }
+}
+
+/// Logical extent and memory strides of a matrix, in `w, h, d, c` order.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    shape: [usize; 4],
+    strides: [usize; 4],
+}
+
+impl Layout {
+    fn of(mat: &Mat) -> anyhow::Result<Layout> {
+        if mat.dims() == 0 || mat.data().is_null() {
+            anyhow::bail!("Expected a non-empty matrix");
+        }
+        if mat.elempack() != 1 || mat.elemsize() != 4 {
+            anyhow::bail!(
+                "Expected unpacked fp32 matrix, got elemsize {} elempack {}",
+                mat.elemsize(),
+                mat.elempack()
+            );
+        }
+        let w = mat.w().max(1) as usize;
+        let h = mat.h().max(1) as usize;
+        let d = mat.d().max(1) as usize;
+        let c = mat.c().max(1) as usize;
+        Ok(Layout {
+            shape: [w, h, d, c],
+            strides: [1, w, w * h, mat.cstep() as usize],
+        })
+    }
+
+    /// Memory offsets of every logical element, in `w`-fastest order.
+    fn offsets(&self) -> Vec<usize> {
+        self.lanes_excluding(None)
+    }
+
+    /// Memory offsets of the first element of every lane along `axis`, in `w`-fastest order of
+    /// the remaining axes.
+    fn lanes(&self, axis: Axis) -> Vec<usize> {
+        self.lanes_excluding(Some(axis.index()))
+    }
+
+    fn lanes_excluding(&self, skip: Option<usize>) -> Vec<usize> {
+        let extent = |i: usize| if Some(i) == skip { 1 } else { self.shape[i] };
+        let mut offsets = Vec::with_capacity((0..4).map(extent).product());
+        for q in 0..extent(3) {
+            for z in 0..extent(2) {
+                for y in 0..extent(1) {
+                    for x in 0..extent(0) {
+                        offsets.push(
+                            x * self.strides[0]
+                                + y * self.strides[1]
+                                + z * self.strides[2]
+                                + q * self.strides[3],
+                        );
+                    }
+                }
+            }
+        }
+        offsets
+    }
+}
+
+/// Creates an fp32 matrix with the same dimensionality as `like`, but the given `w, h, d, c`.
+fn new_like(like: &Mat, shape: [usize; 4]) -> Mat {
+    let [w, h, d, c] = shape.map(|v| v as i32);
+    match like.dims() {
+        1 => Mat::new_1d(w, None),
+        2 => Mat::new_2d(w, h, None),
+        3 => Mat::new_3d(w, h, c, None),
+        _ => Mat::new_4d(w, h, d, c, None),
+    }
+}
+
+/// Applies the logistic function to every element in place.
+pub fn sigmoid(mat: &mut Mat) -> anyhow::Result<()> {
+    let layout = Layout::of(mat)?;
+    let data = mat.as_slice_mut::<f32>();
+    for offset in layout.offsets() {
+        data[offset] = 1.0 / (1.0 + (-data[offset]).exp());
+    }
+    Ok(())
+}
+
+/// Normalizes every lane along `axis` with a numerically stable softmax, in place.
+pub fn softmax(mat: &mut Mat, axis: Axis) -> anyhow::Result<()> {
+    let layout = Layout::of(mat)?;
+    let (len, stride) = (layout.shape[axis.index()], layout.strides[axis.index()]);
+    let data = mat.as_slice_mut::<f32>();
+    for base in layout.lanes(axis) {
+        let lane = (0..len).map(|i| base + i * stride);
+        let max = lane
+            .clone()
+            .map(|i| data[i])
+            .fold(f32::NEG_INFINITY, f32::max);
+        let mut sum = 0.0;
+        for i in lane.clone() {
+            data[i] = (data[i] - max).exp();
+            sum += data[i];
+        }
+        for i in lane {
+            data[i] /= sum;
+        }
+    }
+    Ok(())
+}
+
+fn reduce(mat: &Mat, axis: Axis, scale: f32) -> anyhow::Result<Mat> {
+    let layout = Layout::of(mat)?;
+    let (len, stride) = (layout.shape[axis.index()], layout.strides[axis.index()]);
+    let mut shape = layout.shape;
+    shape[axis.index()] = 1;
+
+    let mut out = new_like(mat, shape);
+    let out_layout = Layout::of(&out)?;
+    let data = mat.as_slice::<f32>();
+    let out_data = out.as_slice_mut::<f32>();
+    for (base, dst) in layout.lanes(axis).into_iter().zip(out_layout.offsets()) {
+        out_data[dst] = (0..len).map(|i| data[base + i * stride]).sum::<f32>() * scale;
+    }
+    Ok(out)
+}
+
+/// Sums along `axis`. The reduced axis is kept with size 1.
+pub fn sum(mat: &Mat, axis: Axis) -> anyhow::Result<Mat> {
+    reduce(mat, axis, 1.0)
+}
+
+/// Averages along `axis`. The reduced axis is kept with size 1.
+pub fn mean(mat: &Mat, axis: Axis) -> anyhow::Result<Mat> {
+    let len = Layout::of(mat)?.shape[axis.index()];
+    reduce(mat, axis, 1.0 / len as f32)
+}
+
+/// Returns the index of the largest element of every lane along `axis`, ordered with `w` varying
+/// fastest over the remaining axes. For a `w x h x c` score map, `argmax(m, Axis::C)` yields the
+/// class of each pixel in row-major order.
+pub fn argmax(mat: &Mat, axis: Axis) -> anyhow::Result<Vec<usize>> {
+    let layout = Layout::of(mat)?;
+    let (len, stride) = (layout.shape[axis.index()], layout.strides[axis.index()]);
+    let data = mat.as_slice::<f32>();
+    Ok(layout
+        .lanes(axis)
+        .into_iter()
+        .map(|base| {
+            (0..len).fold(0, |best, i| {
+                if data[base + i * stride] > data[base + best * stride] {
+                    i
+                } else {
+                    best
+                }
+            })
+        })
+        .collect())
+}
+
+/// Returns the `k` largest elements as `(index, value)` pairs, largest first. Indices count
+/// logical elements with `w` varying fastest, so for a classifier output they are class ids.
+pub fn top_k(mat: &Mat, k: usize) -> anyhow::Result<Vec<(usize, f32)>> {
+    let layout = Layout::of(mat)?;
+    let data = mat.as_slice::<f32>();
+    let mut scores: Vec<(usize, f32)> = layout
+        .offsets()
+        .into_iter()
+        .enumerate()
+        .map(|(i, offset)| (i, data[offset]))
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores.truncate(k);
+    Ok(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill_sequence(m: &mut Mat) {
+        let layout = Layout::of(m).unwrap();
+        let data = m.as_slice_mut::<f32>();
+        for (i, offset) in layout.offsets().into_iter().enumerate() {
+            data[offset] = i as f32;
+        }
+    }
+
+    #[test]
+    fn softmax_over_channels() {
+        let mut m = Mat::new_3d(3, 2, 4, None);
+        fill_sequence(&mut m);
+        softmax(&mut m, Axis::C).unwrap();
+        let total = sum(&m, Axis::C).unwrap();
+        for v in &total.as_slice::<f32>()[..6] {
+            assert!((v - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn reductions_skip_padding() {
+        let mut m = Mat::new_3d(3, 1, 2, None);
+        m.fill(7.0);
+        fill_sequence(&mut m);
+        assert_eq!(argmax(&m, Axis::W).unwrap(), vec![2, 2]);
+        assert_eq!(argmax(&m, Axis::C).unwrap(), vec![1, 1, 1]);
+        let avg = mean(&m, Axis::W).unwrap();
+        assert_eq!(avg.c(), 2);
+        assert_eq!(avg.w(), 1);
+        assert_eq!(top_k(&m, 2).unwrap(), vec![(5, 5.0), (4, 4.0)]);
+    }
+}
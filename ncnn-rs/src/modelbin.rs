@@ -0,0 +1,595 @@
+//! The ncnn `.bin` weights file: the weights of every layer, in layer order, with nothing
+//! to tell where one ends and the next starts but the layer parameters.
+//!
+//! [layout] lists the weight blobs a layer reads, which is what it takes to walk a `.bin`
+//! alongside its [crate::param::Graph]. [read] decodes every blob, however it is stored,
+//! and [write] encodes them back:
+//!
+//! ```no_run
+//! use ncnn_rs::modelbin;
+//! use ncnn_rs::param::Graph;
+//!
+//! let graph = Graph::load("squeezenet.param")?;
+//! let mut weights = modelbin::read(&graph, &std::fs::read("squeezenet.bin")?)?;
+//! modelbin::convert_to_f16(&mut weights);
+//! std::fs::write("squeezenet-fp16.bin", modelbin::write(&graph, &weights)?)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use crate::param::{Graph, Layer};
+
+/// A weight blob a layer reads from the `.bin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightSpec {
+    /// What the weights are, like `weight` or `bias`, as ncnn names the blob in the layer.
+    pub name: &'static str,
+    /// Number of values.
+    pub len: usize,
+    /// Preceded by a 4-byte storage tag, saying whether the values are float32, fp16 or
+    /// quantized. Untagged blobs are always float32.
+    pub tagged: bool,
+}
+
+/// The weight blobs a layer reads, in `.bin` order, empty for layers without weights.
+///
+/// Covers every built-in layer of ncnn 20220729 that reads weights, including the scales of
+/// int8 layers, and the constant matrices later `Gemm`s take. Custom layers are taken to have
+/// no weights.
+pub fn layout(layer: &Layer) -> Vec<WeightSpec> {
+    let pd = &layer.params;
+    let int = |id, default| pd.get_int(id, default).max(0) as usize;
+    let blob = |name, len, tagged| WeightSpec { name, len, tagged };
+    let mut specs = Vec::new();
+    match layer.layer_type.as_str() {
+        "Convolution"
+        | "ConvolutionDepthWise"
+        | "Deconvolution"
+        | "DeconvolutionDepthWise"
+        | "Convolution1D"
+        | "ConvolutionDepthWise1D"
+        | "Deconvolution1D"
+        | "DeconvolutionDepthWise1D"
+        | "Convolution3D"
+        | "ConvolutionDepthWise3D"
+        | "Deconvolution3D"
+        | "DeconvolutionDepthWise3D"
+        | "DeformableConv2D" => {
+            specs.push(blob("weight", int(6, 0), true));
+            if int(5, 0) != 0 {
+                specs.push(blob("bias", int(0, 0), false));
+            }
+            let int8_scale_term = int(8, 0);
+            let weight_scales = match layer.layer_type.as_str() {
+                "Convolution" => int(0, 0),
+                // per group, or one for all with 2 and 102
+                "ConvolutionDepthWise" if int8_scale_term % 100 == 1 => int(7, 1),
+                "ConvolutionDepthWise" => 1,
+                // no int8 deconvolution, nor 1D, 3D or deformable convolution
+                _ => 0,
+            };
+            if int8_scale_term != 0 && weight_scales != 0 {
+                specs.push(blob("weight_int8_scales", weight_scales, false));
+                specs.push(blob("bottom_int8_scales", 1, false));
+                if int8_scale_term > 100 {
+                    specs.push(blob("top_int8_scales", 1, false));
+                }
+            }
+        }
+        "InnerProduct" => {
+            specs.push(blob("weight", int(2, 0), true));
+            if int(1, 0) != 0 {
+                specs.push(blob("bias", int(0, 0), false));
+            }
+            if int(8, 0) != 0 {
+                specs.push(blob("weight_int8_scales", int(0, 0), false));
+                specs.push(blob("bottom_int8_scales", 1, false));
+            }
+        }
+        "RNN" | "LSTM" | "GRU" => {
+            let num_output = int(0, 0);
+            let directions = if int(2, 0) == 2 { 2 } else { 1 };
+            // LSTM projects its hidden state to num_output if hidden_size differs
+            let hidden = match layer.layer_type.as_str() {
+                "LSTM" => int(3, num_output as i32),
+                _ => num_output,
+            };
+            let gates = match layer.layer_type.as_str() {
+                "LSTM" => 4,
+                "GRU" => 3,
+                _ => 1,
+            };
+            specs.push(blob("weight_xc", int(1, 0), true));
+            // GRU keeps a fourth bias for the candidate's recurrent part
+            let bias_gates = if gates == 3 { 4 } else { gates };
+            specs.push(blob("bias_c", hidden * bias_gates * directions, true));
+            specs.push(blob(
+                "weight_hc",
+                num_output * hidden * gates * directions,
+                true,
+            ));
+            if hidden != num_output {
+                specs.push(blob("weight_hr", hidden * num_output * directions, true));
+            }
+        }
+        "MultiHeadAttention" => {
+            let embed_dim = int(0, 0);
+            let weight_data_size = int(2, 0);
+            let qdim = weight_data_size.checked_div(embed_dim).unwrap_or(0);
+            let kdim = int(3, embed_dim as i32);
+            let vdim = int(4, embed_dim as i32);
+            specs.push(blob("q_weight", weight_data_size, true));
+            specs.push(blob("q_bias", embed_dim, false));
+            specs.push(blob("k_weight", embed_dim * kdim, true));
+            specs.push(blob("k_bias", embed_dim, false));
+            specs.push(blob("v_weight", embed_dim * vdim, true));
+            specs.push(blob("v_bias", embed_dim, false));
+            specs.push(blob("out_weight", qdim * embed_dim, true));
+            specs.push(blob("out_bias", qdim, false));
+        }
+        "Gemm" => {
+            let (m, n, k) = (int(7, 0), int(8, 0), int(9, 0));
+            if int(4, 0) != 0 {
+                specs.push(blob("A", m * k, true));
+            }
+            if int(5, 0) != 0 {
+                specs.push(blob("B", n * k, true));
+            }
+            if int(6, 0) != 0 {
+                // broadcast of C: scalar, per row (1 and 2), full matrix, per column
+                let len = match pd.get_int(10, 0) {
+                    0 => Some(1),
+                    1 | 2 => Some(m),
+                    3 => Some(m * n),
+                    4 => Some(n),
+                    _ => None,
+                };
+                if let Some(len) = len {
+                    specs.push(blob("C", len, false));
+                }
+            }
+        }
+        "Quantize" => specs.push(blob("scale", int(0, 1), false)),
+        "Dequantize" => {
+            specs.push(blob("scale", int(0, 1), false));
+            if int(1, 0) != 0 {
+                specs.push(blob("bias", int(1, 0), false));
+            }
+        }
+        "Requantize" => {
+            specs.push(blob("scale_in", int(0, 1), false));
+            specs.push(blob("scale_out", int(1, 1), false));
+            if int(2, 0) != 0 {
+                specs.push(blob("bias", int(2, 0), false));
+            }
+        }
+        "Padding" if int(6, 0) != 0 => {
+            specs.push(blob("per_channel_pad", int(6, 0), false));
+        }
+        "Embed" => {
+            specs.push(blob("weight", int(3, 0), true));
+            if int(2, 0) != 0 {
+                specs.push(blob("bias", int(0, 0), false));
+            }
+        }
+        "BatchNorm" => {
+            let channels = int(0, 0);
+            for name in ["slope", "mean", "var", "bias"] {
+                specs.push(blob(name, channels, false));
+            }
+        }
+        "Scale" => {
+            // -233 takes the scale from a second bottom instead
+            let size = pd.get_int(0, 0);
+            if size != -233 {
+                specs.push(blob("scale", size.max(0) as usize, false));
+            }
+            if int(1, 0) != 0 {
+                specs.push(blob("bias", size.max(0) as usize, false));
+            }
+        }
+        "Bias" => specs.push(blob("bias", int(0, 0), false)),
+        "PReLU" => specs.push(blob("slope", int(0, 0), false)),
+        "Normalize" => specs.push(blob("scale", int(3, 0), false)),
+        "InstanceNorm" | "LayerNorm" | "GroupNorm" => {
+            let (size, affine) = match layer.layer_type.as_str() {
+                "GroupNorm" => (int(1, 0), int(3, 1)),
+                _ => (int(0, 0), int(2, 1)),
+            };
+            if affine != 0 {
+                specs.push(blob("gamma", size, false));
+                specs.push(blob("beta", size, false));
+            }
+        }
+        "MemoryData" => {
+            let len = int(0, 0) * int(1, 0).max(1) * int(11, 0).max(1) * int(2, 0).max(1);
+            specs.push(blob("data", len, false));
+        }
+        _ => {}
+    }
+    specs
+}
+
+/// Storage tags, the first 4 bytes of a tagged blob. An all-zero tag also means float32,
+/// and any other tag a [Weights::Table].
+const TAG_F32: u32 = 0x0002C056;
+const TAG_F16: u32 = 0x01306B47;
+const TAG_INT8: u32 = 0x000D4B38;
+
+/// Weight values as a `.bin` stores them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Weights {
+    F32(Vec<f32>),
+    /// IEEE half precision, as bits.
+    F16(Vec<u16>),
+    /// Quantized weights, which int8 layers scale by their `weight_int8_scales`.
+    Int8(Vec<i8>),
+    /// Indices into a table of 256 values.
+    Table {
+        table: Vec<f32>,
+        indices: Vec<u8>,
+    },
+}
+
+impl Weights {
+    pub fn len(&self) -> usize {
+        match self {
+            Weights::F32(values) => values.len(),
+            Weights::F16(values) => values.len(),
+            Weights::Int8(values) => values.len(),
+            Weights::Table { indices, .. } => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Name of the storage, like `fp32`.
+    pub fn storage(&self) -> &'static str {
+        match self {
+            Weights::F32(_) => "fp32",
+            Weights::F16(_) => "fp16",
+            Weights::Int8(_) => "int8",
+            Weights::Table { .. } => "table",
+        }
+    }
+
+    /// The values as float32, int8 weights unscaled.
+    pub fn to_f32(&self) -> Vec<f32> {
+        match self {
+            Weights::F32(values) => values.clone(),
+            Weights::F16(values) => values.iter().map(|&half| f16_to_f32(half)).collect(),
+            Weights::Int8(values) => values.iter().map(|&value| value as f32).collect(),
+            Weights::Table { table, indices } => {
+                indices.iter().map(|&index| table[index as usize]).collect()
+            }
+        }
+    }
+}
+
+/// A weight blob of a layer and its values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightBlob {
+    pub spec: WeightSpec,
+    pub weights: Weights,
+}
+
+/// The rest of a `.bin`, read blob by blob.
+struct Reader<'a> {
+    bin: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// `len` bytes, followed by padding to 4 bytes.
+    fn take(&mut self, len: usize, layer: &Layer, spec: &WeightSpec) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .bin
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "The .bin ends in the {} of layer `{}`; is it the one for this .param?",
+                    spec.name,
+                    layer.name
+                )
+            })?;
+        self.offset += len.next_multiple_of(4);
+        Ok(bytes)
+    }
+
+    fn f32s(&mut self, len: usize, layer: &Layer, spec: &WeightSpec) -> anyhow::Result<Vec<f32>> {
+        Ok(self
+            .take(len * 4, layer, spec)?
+            .chunks_exact(4)
+            .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+            .collect())
+    }
+
+    fn blob(&mut self, layer: &Layer, spec: &WeightSpec) -> anyhow::Result<Weights> {
+        if !spec.tagged {
+            return Ok(Weights::F32(self.f32s(spec.len, layer, spec)?));
+        }
+        let tag = u32::from_le_bytes(self.take(4, layer, spec)?.try_into().unwrap());
+        Ok(match tag {
+            0 | TAG_F32 => Weights::F32(self.f32s(spec.len, layer, spec)?),
+            TAG_F16 => Weights::F16(
+                self.take(spec.len * 2, layer, spec)?
+                    .chunks_exact(2)
+                    .map(|value| u16::from_le_bytes(value.try_into().unwrap()))
+                    .collect(),
+            ),
+            TAG_INT8 => Weights::Int8(
+                self.take(spec.len, layer, spec)?
+                    .iter()
+                    .map(|&value| value as i8)
+                    .collect(),
+            ),
+            _ => Weights::Table {
+                table: self.f32s(256, layer, spec)?,
+                indices: self.take(spec.len, layer, spec)?.to_vec(),
+            },
+        })
+    }
+}
+
+/// Reads the weight blobs of every layer of `graph`, as [layout] lists them, from its `.bin`.
+pub fn read(graph: &Graph, bin: &[u8]) -> anyhow::Result<Vec<Vec<WeightBlob>>> {
+    let mut reader = Reader { bin, offset: 0 };
+    let mut weights = Vec::with_capacity(graph.layers.len());
+    for layer in &graph.layers {
+        let mut blobs = Vec::new();
+        for spec in layout(layer) {
+            let weights = reader.blob(layer, &spec)?;
+            blobs.push(WeightBlob { spec, weights });
+        }
+        weights.push(blobs);
+    }
+    if reader.offset < bin.len() {
+        anyhow::bail!(
+            "{} bytes left in the .bin after the last layer; is it the one for this .param?",
+            bin.len() - reader.offset
+        );
+    }
+    Ok(weights)
+}
+
+/// Reads the weights of every layer as float32, decoding fp16 and table storage; fails for
+/// int8 weights, which only make sense with their scales.
+pub fn read_f32(graph: &Graph, bin: &[u8]) -> anyhow::Result<Vec<Vec<Vec<f32>>>> {
+    let weights = read(graph, bin)?;
+    let mut values = Vec::with_capacity(weights.len());
+    for (layer, blobs) in graph.layers.iter().zip(weights) {
+        let mut layer_values = Vec::with_capacity(blobs.len());
+        for blob in blobs {
+            if let Weights::Int8(_) = blob.weights {
+                anyhow::bail!(
+                    "Layer `{}` stores its {} as int8, only float weights are supported",
+                    layer.name,
+                    blob.spec.name
+                );
+            }
+            layer_values.push(blob.weights.to_f32());
+        }
+        values.push(layer_values);
+    }
+    Ok(values)
+}
+
+/// Appends float32 weights, with the all-zero storage tag that marks float32 if `tagged`.
+pub fn write_f32(out: &mut Vec<u8>, values: &[f32], tagged: bool) {
+    if tagged {
+        out.extend_from_slice(&[0; 4]);
+    }
+    for value in values {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Appends bytes padded to 4, as ncnn aligns fp16, int8 and table blobs.
+fn write_padded(out: &mut Vec<u8>, bytes: impl IntoIterator<Item = u8>) {
+    out.extend(bytes);
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+/// Writes the `.bin` of `graph`, with weight blobs laid out as [read] returns them.
+///
+/// Fails if the blobs do not match [layout], or an untagged blob is not float32.
+pub fn write(graph: &Graph, weights: &[Vec<WeightBlob>]) -> anyhow::Result<Vec<u8>> {
+    if graph.layers.len() != weights.len() {
+        anyhow::bail!(
+            "Weights of {} layers given for {} layers",
+            weights.len(),
+            graph.layers.len()
+        );
+    }
+    let mut out = Vec::new();
+    for (layer, blobs) in graph.layers.iter().zip(weights) {
+        let specs = layout(layer);
+        let given: Vec<WeightSpec> = blobs.iter().map(|blob| blob.spec).collect();
+        let lens: Vec<usize> = blobs.iter().map(|blob| blob.weights.len()).collect();
+        if given != specs || lens != specs.iter().map(|spec| spec.len).collect::<Vec<_>>() {
+            anyhow::bail!("Weights of layer `{}` do not match its params", layer.name);
+        }
+        for WeightBlob { spec, weights } in blobs {
+            match weights {
+                Weights::F32(values) => write_f32(&mut out, values, spec.tagged),
+                _ if !spec.tagged => anyhow::bail!(
+                    "The {} of layer `{}` is stored without a tag, so must be float32",
+                    spec.name,
+                    layer.name
+                ),
+                Weights::F16(values) => {
+                    out.extend_from_slice(&TAG_F16.to_le_bytes());
+                    write_padded(
+                        &mut out,
+                        values.iter().flat_map(|value| value.to_le_bytes()),
+                    );
+                }
+                Weights::Int8(values) => {
+                    out.extend_from_slice(&TAG_INT8.to_le_bytes());
+                    write_padded(&mut out, values.iter().map(|&value| value as u8));
+                }
+                Weights::Table { table, indices } => {
+                    // any tag other than the known ones, ncnn only sums the bytes
+                    out.extend_from_slice(&[1, 0, 0, 0]);
+                    write_f32(&mut out, table, false);
+                    write_padded(&mut out, indices.iter().copied());
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Stores every float32 blob that can be fp16, the tagged ones, as fp16, like
+/// `ncnnoptimize` with storage type 1. Returns how many blobs were converted.
+pub fn convert_to_f16(weights: &mut [Vec<WeightBlob>]) -> usize {
+    let mut converted = 0;
+    for blob in weights.iter_mut().flatten() {
+        if let (true, Weights::F32(values)) = (blob.spec.tagged, &blob.weights) {
+            blob.weights = Weights::F16(values.iter().map(|&value| f32_to_f16(value)).collect());
+            converted += 1;
+        }
+    }
+    converted
+}
+
+/// Converts to IEEE half precision, rounding to nearest even.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        // inf, or nan keeping it a nan
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // subnormal, shifting in the implicit leading bit
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = rest > halfway || (rest == halfway && half & 1 == 1);
+        return sign | (half + round as u32) as u16;
+    }
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    let round = rest > 0x1000 || (rest == 0x1000 && half & 1 == 1);
+    // rounding may carry into the exponent, up to inf, which is what it should be
+    sign | (half + round as u32) as u16
+}
+
+/// Converts from IEEE half precision, exactly.
+pub fn f16_to_f32(value: u16) -> f32 {
+    let sign = ((value & 0x8000) as u32) << 16;
+    let exponent = ((value >> 10) & 0x1f) as u32;
+    let mantissa = (value & 0x3ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // subnormal, normalized for float32
+            let shift = mantissa.leading_zeros() - 21;
+            let mantissa = (mantissa << shift) & 0x3ff;
+            sign | ((127 - 15 + 1 - shift) << 23) | (mantissa << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_of_conv_bn() {
+        let graph: Graph = "7767517
+3 3
+Input        data 0 1 data 0=8 1=8 2=3
+Convolution  conv 1 1 data conv 0=16 1=3 5=1 6=432
+BatchNorm    bn   1 1 conv bn 0=16"
+            .parse()
+            .unwrap();
+        assert!(layout(&graph.layers[0]).is_empty());
+        assert_eq!(
+            layout(&graph.layers[1]),
+            [
+                WeightSpec {
+                    name: "weight",
+                    len: 432,
+                    tagged: true
+                },
+                WeightSpec {
+                    name: "bias",
+                    len: 16,
+                    tagged: false
+                },
+            ]
+        );
+        let bn: usize = layout(&graph.layers[2]).iter().map(|spec| spec.len).sum();
+        assert_eq!(bn, 64);
+
+        let lens = |layer: &str| -> Vec<usize> {
+            let layer: Graph = format!("7767517\n1 1\n{}", layer).parse().unwrap();
+            layout(&layer.layers[0])
+                .iter()
+                .map(|spec| spec.len)
+                .collect()
+        };
+        assert_eq!(lens("Convolution1D c 0 1 x 0=4 1=3 5=1 6=24"), [24, 4]);
+        assert_eq!(
+            lens("LSTM l 0 1 x 0=8 1=512 2=2"),
+            [512, 8 * 4 * 2, 8 * 8 * 4 * 2]
+        );
+        assert_eq!(lens("GRU g 0 1 x 0=8 1=384"), [384, 8 * 4, 8 * 8 * 3]);
+        assert_eq!(
+            lens("MultiHeadAttention a 0 1 x 0=16 1=2 2=256"),
+            [256, 16, 256, 16, 256, 16, 256, 16]
+        );
+        assert_eq!(lens("Requantize r 0 1 x 0=1 1=1 2=8"), [1, 1, 8]);
+
+        let mut bin = Vec::new();
+        write_f32(&mut bin, &[1.0, 2.0], true);
+        assert_eq!(bin.len(), 12);
+        assert_eq!(&bin[..4], &[0; 4]);
+
+        let mut bin = Vec::new();
+        for (layer, values) in graph.layers.iter().zip([1.0, 2.0, 3.0]) {
+            for spec in layout(layer) {
+                write_f32(&mut bin, &vec![values; spec.len], spec.tagged);
+            }
+        }
+        let weights = read_f32(&graph, &bin).unwrap();
+        assert_eq!(weights[1][0], vec![2.0; 432]);
+        assert_eq!(weights[2][3], vec![3.0; 16]);
+        assert!(read_f32(&graph, &bin[..bin.len() - 4]).is_err());
+
+        // fp16 halves the convolution weight, and reads back
+        let mut blobs = read(&graph, &bin).unwrap();
+        assert_eq!(convert_to_f16(&mut blobs), 1);
+        assert_eq!(blobs[1][0].weights.storage(), "fp16");
+        assert_eq!(blobs[1][1].weights.storage(), "fp32");
+        let half = write(&graph, &blobs).unwrap();
+        assert_eq!(half.len(), bin.len() - 432 * 2);
+        assert_eq!(read(&graph, &half).unwrap(), blobs);
+        assert_eq!(read_f32(&graph, &half).unwrap(), weights);
+
+        for value in [0.0, -1.5, 65504.0, 6.1e-5, 5.96e-8, 0.1] {
+            let half = f32_to_f16(value);
+            assert!((f16_to_f32(half) - value).abs() <= value.abs() / 1024.0);
+        }
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+}
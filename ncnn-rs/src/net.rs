@@ -1,29 +1,46 @@
+use crate::allocator::Allocator;
 use crate::datareader::DataReader;
-use crate::Extractor;
+use crate::{has_layer, param, Extractor};
 use ncnn_bind::*;
 use std::ffi::CString;
 
 pub struct Net {
     ptr: ncnn_net_t,
+    allocators: Vec<Allocator>,
 }
 
 impl Net {
     pub fn new() -> Net {
         Net {
             ptr: unsafe { ncnn_net_create() },
+            allocators: Vec::new(),
         }
     }
 
+    /// Sets network option, including the allocators extractors will use by default.
     pub fn set_option(&mut self, opt: &crate::option::Option) {
         unsafe {
             ncnn_net_set_option(self.ptr, opt.ptr());
         }
+        self.allocators = opt.allocators();
     }
 
+    /// Loads a text `.param`.
+    ///
+    /// On failure the error lists the problems [crate::param::validate_with] finds in the
+    /// file, checking layer types against the linked ncnn.
     pub fn load_param(&mut self, path: &str) -> anyhow::Result<()> {
         let c_str = CString::new(path).unwrap();
         if unsafe { ncnn_net_load_param(self.ptr, c_str.as_ptr()) } != 0 {
-            anyhow::bail!("Error loading params {}", path);
+            let problems = diagnose_param(path);
+            if problems.is_empty() {
+                anyhow::bail!("Error loading params {}", path);
+            }
+            anyhow::bail!(
+                "Error loading params {}:\n  {}",
+                path,
+                problems.join("\n  ")
+            );
         } else {
             Ok(())
         }
@@ -38,6 +55,32 @@ impl Net {
         }
     }
 
+    /// Loads a text `.param` from memory, like one written by [crate::param::GraphBuilder].
+    pub fn load_param_memory(&mut self, param: &str) -> anyhow::Result<()> {
+        let c_str = CString::new(param)?;
+        if unsafe { ncnn_net_load_param_memory(self.ptr, c_str.as_ptr()) } != 0 {
+            let problems = param_errors(param);
+            if problems.is_empty() {
+                anyhow::bail!("Error loading params from memory");
+            }
+            anyhow::bail!(
+                "Error loading params from memory:\n  {}",
+                problems.join("\n  ")
+            );
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Loads `.bin` weights from memory, failing if the loaded params read past its end.
+    pub fn load_model_memory(&mut self, model: &[u8]) -> anyhow::Result<()> {
+        if unsafe { ncnn_rs_net_load_model_memory(self.ptr, model.as_ptr(), model.len()) } != 0 {
+            anyhow::bail!("Error loading model from memory");
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn load_model_datareader(&mut self, dr: &DataReader) -> anyhow::Result<()> {
         if unsafe { ncnn_net_load_model_datareader(self.ptr, dr.ptr()) } != 0 {
             anyhow::bail!("Error loading model from datareader");
@@ -51,10 +94,27 @@ impl Net {
         unsafe {
             ptr = ncnn_extractor_create(self.ptr);
         }
-        Extractor::from_ptr(ptr)
+        Extractor::from_ptr(ptr, self.ptr, self.allocators.clone())
     }
 }
 
+/// The errors in a `.param` file, for explaining why ncnn rejected it.
+fn diagnose_param(path: &str) -> Vec<String> {
+    match std::fs::read_to_string(path) {
+        Ok(param) => param_errors(&param),
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+/// The errors [param::validate_with] finds, checking layer types against the linked ncnn.
+fn param_errors(param: &str) -> Vec<String> {
+    param::validate_with(param, has_layer)
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == param::Severity::Error)
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
 impl Drop for Net {
     fn drop(&mut self) {
         unsafe {
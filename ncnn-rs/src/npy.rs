@@ -0,0 +1,154 @@
+//! Blobs as NumPy `.npy` files, for finding where ncnn's results first diverge from another
+//! framework's.
+//!
+//! [dump] writes every blob of a run with [crate::Extractor::extract_all], next to a
+//! `manifest.txt` of `blob file shape` lines:
+//!
+//! ```no_run
+//! use ncnn_rs::{npy, Mat, Net};
+//!
+//! let mut net = Net::new();
+//! net.load_param("squeezenet.param")?;
+//! net.load_model("squeezenet.bin")?;
+//! let mut data = Mat::new_3d(227, 227, 3, None);
+//! data.fill(0.5);
+//! let mut ex = net.create_extractor();
+//! ex.input("data", &data)?;
+//! for blob in npy::dump(&ex, "blobs")? {
+//!     println!("{} {}", blob.name, blob.shape);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! which NumPy reads back with
+//!
+//! ```text
+//! for line in open("blobs/manifest.txt"):
+//!     name, file, shape = line.split()
+//!     blob = numpy.load("blobs/" + file)
+//! ```
+use crate::param::Shape;
+use crate::{Extractor, Mat};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// A blob [dump] wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DumpedBlob {
+    pub name: String,
+    /// File name in the dump directory.
+    pub file: String,
+    pub shape: Shape,
+}
+
+/// A float32 `.npy` of `values`, with the extents of `shape` outermost first.
+fn encode(shape: &Shape, values: impl Iterator<Item = f32>) -> Vec<u8> {
+    let extents: Vec<String> = shape.extents().iter().map(usize::to_string).collect();
+    // a one-element tuple needs its trailing comma
+    let dims = match &extents[..] {
+        [extent] => format!("{},", extent),
+        _ => extents.join(", "),
+    };
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}), }}",
+        dims
+    );
+    // magic, version and header length take 10 bytes, the header ends with a newline and
+    // pads the data to 64 bytes
+    let padded = (10 + header.len() + 1).next_multiple_of(64) - 10;
+    header.extend(std::iter::repeat_n(' ', padded - header.len() - 1));
+    header.push('\n');
+
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend((header.len() as u16).to_le_bytes());
+    npy.extend(header.as_bytes());
+    for value in values {
+        npy.extend(value.to_le_bytes());
+    }
+    npy
+}
+
+/// The `.npy` of an extracted float32 blob, shaped `[c, d, h, w]` without the unused extents.
+///
+/// Fails for blobs [Mat::to_vec] cannot flatten.
+pub fn to_bytes(mat: &Mat) -> anyhow::Result<Vec<u8>> {
+    Ok(encode(&mat.shape(), mat.to_vec()?.into_iter()))
+}
+
+/// Writes an extracted float32 blob to a `.npy` file.
+pub fn write(mat: &Mat, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    std::fs::write(path, to_bytes(mat)?)
+        .map_err(|e| anyhow::anyhow!("Error writing {}: {}", path.display(), e))
+}
+
+/// A blob name usable as a file name, keeping it readable but safe on any file system.
+pub fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// The file name [dump] gives the `index`th blob.
+fn file_name(index: usize, name: &str) -> String {
+    format!("{:04}_{}.npy", index, file_stem(name))
+}
+
+/// Extracts every blob of the extractor's model into a `.npy` file in `dir`, creating it,
+/// and lists them in `manifest.txt` there.
+///
+/// Files are numbered in blob order, so listing the directory follows the graph.
+pub fn dump(ex: &Extractor, dir: impl AsRef<Path>) -> anyhow::Result<Vec<DumpedBlob>> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)
+        .map_err(|e| anyhow::anyhow!("Error creating {}: {}", dir.display(), e))?;
+    let mut dumped = Vec::new();
+    let mut manifest = String::new();
+    for (index, blob) in ex.extract_all().enumerate() {
+        let (name, mat) = blob?;
+        let blob = DumpedBlob {
+            file: file_name(index, &name),
+            shape: mat.shape(),
+            name,
+        };
+        write(&mat, dir.join(&blob.file))
+            .map_err(|e| e.context(format!("Error dumping blob `{}`", blob.name)))?;
+        writeln!(manifest, "{} {} {}", blob.name, blob.file, blob.shape).unwrap();
+        dumped.push(blob);
+    }
+    let path = dir.join("manifest.txt");
+    std::fs::write(&path, manifest)
+        .map_err(|e| anyhow::anyhow!("Error writing {}: {}", path.display(), e))?;
+    Ok(dumped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npy_header() {
+        let npy = encode(&Shape::new_3d(2, 1, 3), (0..6).map(|v| v as f32));
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        assert!(
+            header.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (3, 1, 2), }")
+        );
+        assert!(header.ends_with(" \n"));
+        assert_eq!(npy.len(), 10 + header_len + 6 * 4);
+        assert_eq!(&npy[npy.len() - 4..], &5f32.to_le_bytes());
+
+        let npy = encode(&Shape::new_1d(4), std::iter::empty());
+        assert!(String::from_utf8_lossy(&npy).contains("'shape': (4,)"));
+        assert_eq!(
+            file_name(7, "onnx::Conv_12/out"),
+            "0007_onnx__Conv_12_out.npy"
+        );
+    }
+}
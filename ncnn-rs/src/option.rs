@@ -1,8 +1,11 @@
+use crate::allocator::Allocator;
 use ncnn_bind::*;
 use std::os::raw::c_int;
 
 pub struct Option {
     ptr: ncnn_option_t,
+    blob_allocator: std::option::Option<Allocator>,
+    workspace_allocator: std::option::Option<Allocator>,
 }
 
 impl Option {
@@ -11,7 +14,11 @@ impl Option {
         unsafe {
             ptr = ncnn_option_create();
         }
-        Option { ptr }
+        Option {
+            ptr,
+            blob_allocator: None,
+            workspace_allocator: None,
+        }
     }
 
     pub fn set_num_threads(&mut self, num_threads: u32) {
@@ -34,9 +41,63 @@ impl Option {
         unsafe { ncnn_option_get_use_vulkan_compute(self.ptr) != 0 }
     }
 
+    /// Sets whether layers free intermediate blobs as soon as they are consumed.
+    pub fn set_lightmode(&mut self, enabled: bool) {
+        unsafe { ncnn_rs_option_set_lightmode(self.ptr, enabled as c_int) };
+    }
+
+    pub fn get_lightmode(&self) -> bool {
+        unsafe { ncnn_rs_option_get_lightmode(self.ptr) != 0 }
+    }
+
+    /// Sets whether fp16 is used for packing, storage and arithmetic where the CPU supports it.
+    pub fn set_fp16(&mut self, enabled: bool) {
+        unsafe { ncnn_rs_option_set_use_fp16(self.ptr, enabled as c_int) };
+    }
+
+    pub fn get_fp16(&self) -> bool {
+        unsafe { ncnn_rs_option_get_use_fp16(self.ptr) != 0 }
+    }
+
+    /// Sets whether layers with int8 weights run in int8.
+    pub fn set_int8_inference(&mut self, enabled: bool) {
+        unsafe { ncnn_rs_option_set_use_int8_inference(self.ptr, enabled as c_int) };
+    }
+
+    pub fn get_int8_inference(&self) -> bool {
+        unsafe { ncnn_rs_option_get_use_int8_inference(self.ptr) != 0 }
+    }
+
+    /// Sets the allocator for blobs, i.e. layer inputs and outputs, including extracted outputs.
+    ///
+    /// Applies through [crate::Net::set_option], which keeps the allocator alive, but not
+    /// through [crate::Extractor::set_option].
+    pub fn set_blob_allocator(&mut self, allocator: &Allocator) {
+        unsafe { ncnn_rs_option_set_blob_allocator(self.ptr, allocator.ptr()) };
+        self.blob_allocator = Some(allocator.clone());
+    }
+
+    /// Sets the allocator for temporary memory used inside layers.
+    ///
+    /// Applies through [crate::Net::set_option], which keeps the allocator alive, but not
+    /// through [crate::Extractor::set_option].
+    pub fn set_workspace_allocator(&mut self, allocator: &Allocator) {
+        unsafe { ncnn_rs_option_set_workspace_allocator(self.ptr, allocator.ptr()) };
+        self.workspace_allocator = Some(allocator.clone());
+    }
+
     pub(crate) fn ptr(&self) -> ncnn_option_t {
         self.ptr
     }
+
+    /// Allocators a user of this option has to keep alive.
+    pub(crate) fn allocators(&self) -> Vec<Allocator> {
+        self.blob_allocator
+            .iter()
+            .chain(self.workspace_allocator.iter())
+            .cloned()
+            .collect()
+    }
 }
 
 impl Drop for Option {
@@ -56,4 +117,14 @@ mod tests {
         opt.set_num_threads(4);
         assert_eq!(4, opt.get_num_threads());
     }
+
+    #[test]
+    fn allocators_outlive_handles() {
+        use crate::allocator::Allocator;
+        use crate::option::*;
+        let mut opt = Option::new();
+        opt.set_blob_allocator(&Allocator::new());
+        opt.set_workspace_allocator(&Allocator::new());
+        assert_eq!(2, opt.allocators().len());
+    }
 }
@@ -0,0 +1,451 @@
+//! Reading and writing ncnn `.param` files without libncnn.
+//!
+//! A [Graph] holds the layers and blobs of a model as written in its text `.param` or
+//! binary `.param.bin`, and writes them back out in either form:
+//!
+//! ```no_run
+//! use ncnn_rs::param::Graph;
+//!
+//! let graph = Graph::load("squeezenet.param")?;
+//! for index in graph.topological_order()? {
+//!     let layer = &graph.layers[index];
+//!     println!("{} {} {:?}", layer.layer_type, layer.name, layer.params.get_int(0, 0));
+//! }
+//! graph.save("squeezenet.param.bin")?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+mod binary;
+mod builder;
+mod diff;
+mod export;
+mod optimize;
+mod shape;
+mod summary;
+mod text;
+mod validate;
+
+pub use binary::LAYER_TYPES;
+pub use builder::{BinaryOp, Conv2d, GraphBuilder, Model, Resize};
+pub use diff::{diff, GraphDiff, LayerDiff, ParamChange};
+pub use optimize::{Change, OptimizeReport};
+pub use shape::Shape;
+pub use summary::{model_summary, LayerSummary, Summary};
+pub use validate::{validate, validate_with, Diagnostic, Severity};
+
+/// First value of every `.param` and `.param.bin` file.
+pub const MAGIC: i32 = 7767517;
+
+/// Number of parameter ids a layer can have, `NCNN_MAX_PARAM_COUNT`.
+pub const MAX_PARAM_COUNT: i32 = 32;
+
+/// A layer parameter value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamValue {
+    Int(i32),
+    Float(f32),
+    IntArray(Vec<i32>),
+    FloatArray(Vec<f32>),
+    /// A value from a `.param.bin`, which does not record whether it is an int or a float.
+    Raw(u32),
+    /// An array from a `.param.bin`, which does not record whether it holds ints or floats,
+    /// or a text array mixing both, as bits.
+    RawArray(Vec<u32>),
+}
+
+impl ParamValue {
+    pub fn is_array(&self) -> bool {
+        matches!(
+            self,
+            ParamValue::IntArray(_) | ParamValue::FloatArray(_) | ParamValue::RawArray(_)
+        )
+    }
+
+    /// The value as an int; floats are truncated and raw values reinterpreted, as ncnn does.
+    pub fn as_int(&self) -> std::option::Option<i32> {
+        match *self {
+            ParamValue::Int(i) => Some(i),
+            ParamValue::Float(f) => Some(f as i32),
+            ParamValue::Raw(bits) => Some(bits as i32),
+            _ => None,
+        }
+    }
+
+    /// The value as a float; ints are converted and raw values reinterpreted, as ncnn does.
+    pub fn as_float(&self) -> std::option::Option<f32> {
+        match *self {
+            ParamValue::Int(i) => Some(i as f32),
+            ParamValue::Float(f) => Some(f),
+            ParamValue::Raw(bits) => Some(f32::from_bits(bits)),
+            _ => None,
+        }
+    }
+
+    pub fn as_ints(&self) -> std::option::Option<Vec<i32>> {
+        match self {
+            ParamValue::IntArray(v) => Some(v.clone()),
+            ParamValue::FloatArray(v) => Some(v.iter().map(|&f| f as i32).collect()),
+            ParamValue::RawArray(v) => Some(v.iter().map(|&bits| bits as i32).collect()),
+            _ => None,
+        }
+    }
+
+    pub fn as_floats(&self) -> std::option::Option<Vec<f32>> {
+        match self {
+            ParamValue::IntArray(v) => Some(v.iter().map(|&i| i as f32).collect()),
+            ParamValue::FloatArray(v) => Some(v.clone()),
+            ParamValue::RawArray(v) => Some(v.iter().map(|&bits| f32::from_bits(bits)).collect()),
+            _ => None,
+        }
+    }
+}
+
+/// The `id=value` parameters of a layer, in file order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamDict {
+    entries: Vec<(i32, ParamValue)>,
+}
+
+impl ParamDict {
+    pub fn new() -> ParamDict {
+        ParamDict::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, id: i32) -> std::option::Option<&ParamValue> {
+        self.entries.iter().find(|(i, _)| *i == id).map(|(_, v)| v)
+    }
+
+    pub fn contains(&self, id: i32) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Sets a parameter, keeping its position if it is already set.
+    pub fn set(&mut self, id: i32, value: ParamValue) {
+        match self.entries.iter_mut().find(|(i, _)| *i == id) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((id, value)),
+        }
+    }
+
+    pub fn remove(&mut self, id: i32) -> std::option::Option<ParamValue> {
+        let index = self.entries.iter().position(|(i, _)| *i == id)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &ParamValue)> {
+        self.entries.iter().map(|(id, v)| (*id, v))
+    }
+
+    /// The int parameter `id`, or `default` if it is not set, like `ParamDict::get` in ncnn.
+    pub fn get_int(&self, id: i32, default: i32) -> i32 {
+        self.get(id).and_then(ParamValue::as_int).unwrap_or(default)
+    }
+
+    pub fn get_float(&self, id: i32, default: f32) -> f32 {
+        self.get(id)
+            .and_then(ParamValue::as_float)
+            .unwrap_or(default)
+    }
+
+    pub fn get_ints(&self, id: i32) -> std::option::Option<Vec<i32>> {
+        self.get(id).and_then(ParamValue::as_ints)
+    }
+
+    pub fn get_floats(&self, id: i32) -> std::option::Option<Vec<f32>> {
+        self.get(id).and_then(ParamValue::as_floats)
+    }
+}
+
+/// A layer, connected to others through the indices of its bottom (input) and top (output)
+/// blobs in [Graph::blobs].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub layer_type: String,
+    pub name: String,
+    pub bottoms: Vec<usize>,
+    pub tops: Vec<usize>,
+    pub params: ParamDict,
+}
+
+/// A blob, the data flowing between layers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blob {
+    pub name: String,
+}
+
+/// The layers and blobs of an ncnn model.
+///
+/// Blobs are numbered in order of first appearance, as ncnn numbers them, so indices here
+/// are the ones `ncnn_extractor_extract_index` takes. A `.param.bin` has no names; layers
+/// and blobs read from one are named after their indices.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Graph {
+    pub layers: Vec<Layer>,
+    pub blobs: Vec<Blob>,
+}
+
+impl Graph {
+    /// Parses a text `.param` or a binary `.param.bin`, telling them apart by the magic.
+    pub fn parse(data: &[u8]) -> anyhow::Result<Graph> {
+        if data.starts_with(&MAGIC.to_le_bytes()) {
+            Graph::parse_binary(data)
+        } else {
+            Graph::parse_text(std::str::from_utf8(data)?)
+        }
+    }
+
+    pub fn parse_text(param: &str) -> anyhow::Result<Graph> {
+        text::parse(param)
+    }
+
+    pub fn parse_binary(param_bin: &[u8]) -> anyhow::Result<Graph> {
+        binary::parse(param_bin)
+    }
+
+    /// Reads a text `.param` or a binary `.param.bin`.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Graph> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path.display(), e))?;
+        Graph::parse(&data).map_err(|e| e.context(format!("Error parsing {}", path.display())))
+    }
+
+    /// Writes a binary `.param.bin` if the path ends in `.bin`, a text `.param` otherwise.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let data = if path.extension().is_some_and(|ext| ext == "bin") {
+            self.to_binary()?
+        } else {
+            self.to_text().into_bytes()
+        };
+        std::fs::write(path, data)
+            .map_err(|e| anyhow::anyhow!("Error writing {}: {}", path.display(), e))
+    }
+
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// Serializes to `.param.bin`, failing for layer types ncnn has no index for.
+    pub fn to_binary(&self) -> anyhow::Result<Vec<u8>> {
+        binary::write(self)
+    }
+
+    pub fn layer_index(&self, name: &str) -> std::option::Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    pub fn blob_index(&self, name: &str) -> std::option::Option<usize> {
+        self.blobs.iter().position(|blob| blob.name == name)
+    }
+
+    /// The layer producing `blob`.
+    pub fn producer(&self, blob: usize) -> std::option::Option<usize> {
+        self.layers
+            .iter()
+            .position(|layer| layer.tops.contains(&blob))
+    }
+
+    /// The layers consuming `blob`, in file order.
+    pub fn consumers(&self, blob: usize) -> Vec<usize> {
+        (0..self.layers.len())
+            .filter(|&i| self.layers[i].bottoms.contains(&blob))
+            .collect()
+    }
+
+    /// Blobs produced by `Input` layers.
+    pub fn inputs(&self) -> Vec<usize> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.layer_type == "Input")
+            .flat_map(|layer| layer.tops.iter().copied())
+            .collect()
+    }
+
+    /// Blobs no layer consumes, the outputs of the model.
+    pub fn outputs(&self) -> Vec<usize> {
+        let mut consumed = vec![false; self.blobs.len()];
+        for layer in &self.layers {
+            for &bottom in &layer.bottoms {
+                consumed[bottom] = true;
+            }
+        }
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.tops.iter().copied())
+            .filter(|&top| !consumed[top])
+            .collect()
+    }
+
+    /// Renumbers blobs in order of first appearance, as ncnn numbers them, dropping blobs no
+    /// layer refers to. Useful after adding, removing or rewiring layers.
+    pub fn compact_blobs(&mut self) {
+        let mut renumbered: Vec<std::option::Option<usize>> = vec![None; self.blobs.len()];
+        let mut blobs = Vec::with_capacity(self.blobs.len());
+        for layer in &mut self.layers {
+            for blob in layer.bottoms.iter_mut().chain(layer.tops.iter_mut()) {
+                *blob = *renumbered[*blob].get_or_insert_with(|| {
+                    blobs.push(Blob {
+                        name: std::mem::take(&mut self.blobs[*blob].name),
+                    });
+                    blobs.len() - 1
+                });
+            }
+        }
+        self.blobs = blobs;
+    }
+
+    /// Layer indices ordered so every layer comes after the producers of its bottoms.
+    ///
+    /// Layers keep their file order where the graph allows it. Fails if the graph has a cycle.
+    pub fn topological_order(&self) -> anyhow::Result<Vec<usize>> {
+        let mut producers = HashMap::new();
+        for (i, layer) in self.layers.iter().enumerate() {
+            for &top in &layer.tops {
+                producers.entry(top).or_insert(i);
+            }
+        }
+
+        let mut pending = vec![0; self.layers.len()];
+        let mut dependents = vec![Vec::new(); self.layers.len()];
+        for (i, layer) in self.layers.iter().enumerate() {
+            for bottom in &layer.bottoms {
+                if let Some(&producer) = producers.get(bottom) {
+                    pending[i] += 1;
+                    dependents[producer].push(i);
+                }
+            }
+        }
+
+        let mut ready: BTreeSet<usize> = (0..self.layers.len())
+            .filter(|&i| pending[i] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.layers.len());
+        while let Some(i) = ready.pop_first() {
+            order.push(i);
+            for &dependent in &dependents[i] {
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        if order.len() < self.layers.len() {
+            let stuck = (0..self.layers.len())
+                .find(|&i| pending[i] > 0)
+                .map(|i| self.layers[i].name.as_str())
+                .unwrap_or_default();
+            anyhow::bail!("Graph has a cycle through layer `{}`", stuck);
+        }
+        Ok(order)
+    }
+}
+
+impl FromStr for Graph {
+    type Err = anyhow::Error;
+
+    fn from_str(param: &str) -> anyhow::Result<Graph> {
+        Graph::parse_text(param)
+    }
+}
+
+/// Writes the text `.param` form.
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        text::write(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Vec<std::path::PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        let mut params: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "param"))
+            .collect();
+        params.sort();
+        params
+    }
+
+    #[test]
+    fn parse_and_round_trip_params() {
+        let params = params();
+        assert!(params.len() > 30);
+        for path in params {
+            let graph = Graph::load(&path).unwrap();
+            let name = path.display();
+            assert!(!graph.layers.is_empty(), "{}", name);
+            assert_eq!(graph.topological_order().unwrap().len(), graph.layers.len());
+
+            let text = graph.to_text();
+            assert_eq!(text.parse::<Graph>().unwrap(), graph, "{}", name);
+
+            let binary = graph.to_binary().unwrap();
+            let from_binary = Graph::parse(&binary).unwrap();
+            assert_eq!(from_binary.to_binary().unwrap(), binary, "{}", name);
+            // raw values have to be told apart as ints or floats to be written as text
+            let text = from_binary.to_text().parse::<Graph>().unwrap();
+            assert_eq!(text.to_binary().unwrap(), binary, "{}", name);
+            for (a, b) in graph.layers.iter().zip(&from_binary.layers) {
+                assert_eq!(a.layer_type, b.layer_type, "{}", name);
+                assert_eq!((&a.bottoms, &a.tops), (&b.bottoms, &b.tops), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_layer_params() {
+        let graph: Graph = "7767517\n\
+            3 3\n\
+            Input data 0 1 data 0=4 1=4 2=3\n\
+            Convolution conv 1 1 data conv 0=8 1=3 5=1 6=216 9=2 -23310=1,1.000000e-01\n\
+            Softmax prob 1 1 conv prob\n"
+            .parse()
+            .unwrap();
+        assert_eq!(graph.blobs.len(), 3);
+        assert_eq!(graph.inputs(), vec![0]);
+        assert_eq!(graph.outputs(), vec![2]);
+
+        let conv = &graph.layers[1];
+        assert_eq!(conv.params.get_int(0, 0), 8);
+        assert_eq!(conv.params.get_int(4, 0), 0);
+        assert_eq!(
+            conv.params.get(10),
+            Some(&ParamValue::FloatArray(vec![0.1]))
+        );
+        assert_eq!(graph.producer(1), Some(1));
+        assert_eq!(graph.consumers(1), vec![2]);
+
+        // ncnn tells ints from floats per array element
+        let graph: Graph = "7767517\n1 1\nInput data 0 1 data -23300=2,1,0.5\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            graph.layers[0].params.get(0),
+            Some(&ParamValue::RawArray(vec![1, 0.5f32.to_bits()]))
+        );
+        assert!(graph.to_text().contains(" -23300=2,1,0.5"));
+
+        assert!("7767517\n1 1\nInput data 0 1\n".parse::<Graph>().is_err());
+        assert!("7767517\n1 1\nInput data 0 1 data 32=1\n"
+            .parse::<Graph>()
+            .is_err());
+    }
+}
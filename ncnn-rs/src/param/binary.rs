@@ -0,0 +1,190 @@
+//! The binary `.param.bin` format written by `ncnn2mem`: little-endian `i32`s throughout.
+//!
+//! ```text
+//! magic layer_count blob_count
+//! per layer: type_index bottom_count top_count bottoms... tops... params... -233
+//! per param: id value, or -23300-id len values... for arrays
+//! ```
+//!
+//! Layer types are stored as indices into [LAYER_TYPES], and names are not stored at all.
+use super::{Blob, Graph, Layer, ParamDict, ParamValue, MAGIC, MAX_PARAM_COUNT};
+
+pub use ncnn_bind::LAYER_TYPES;
+
+/// Set in the type index of custom layers, `LayerType::CustomBit`.
+const CUSTOM_BIT: i32 = 1 << 8;
+
+/// Terminates a layer's parameters.
+const PARAM_END: i32 = -233;
+
+const ARRAY_ID_BASE: i32 = -23300;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + 4)
+            .ok_or_else(|| anyhow::anyhow!("Unexpected end of param at byte {}", self.offset))?;
+        self.offset += 4;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_i32(&mut self) -> anyhow::Result<i32> {
+        self.read_u32().map(|v| v as i32)
+    }
+
+    fn read_count(&mut self, what: &str) -> anyhow::Result<usize> {
+        let offset = self.offset;
+        let count = self.read_i32()?;
+        usize::try_from(count)
+            .map_err(|_| anyhow::anyhow!("Invalid {} {} at byte {}", what, count, offset))
+    }
+
+    fn read_blob(&mut self, blob_count: usize) -> anyhow::Result<usize> {
+        let offset = self.offset;
+        let blob = self.read_count("blob index")?;
+        if blob >= blob_count {
+            anyhow::bail!(
+                "Blob index {} at byte {} out of range, {} blobs declared",
+                blob,
+                offset,
+                blob_count
+            );
+        }
+        Ok(blob)
+    }
+}
+
+pub(super) fn parse(data: &[u8]) -> anyhow::Result<Graph> {
+    let mut reader = Reader { data, offset: 0 };
+    let magic = reader.read_i32()?;
+    if magic != MAGIC {
+        anyhow::bail!("Expected magic {}, found {}", MAGIC, magic);
+    }
+    let layer_count = reader.read_count("layer count")?;
+    let blob_count = reader.read_count("blob count")?;
+
+    let mut graph = Graph {
+        layers: Vec::with_capacity(layer_count),
+        blobs: (0..blob_count)
+            .map(|i| Blob {
+                name: i.to_string(),
+            })
+            .collect(),
+    };
+    for i in 0..layer_count {
+        let offset = reader.offset;
+        let type_index = reader.read_i32()?;
+        if type_index & CUSTOM_BIT != 0 {
+            anyhow::bail!(
+                "Layer {} at byte {} is custom layer {}, which has no name to read it by",
+                i,
+                offset,
+                type_index & !CUSTOM_BIT
+            );
+        }
+        let layer_type = usize::try_from(type_index)
+            .ok()
+            .and_then(|index| LAYER_TYPES.get(index))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Unknown layer type index {} at byte {}", type_index, offset)
+            })?;
+
+        let bottom_count = reader.read_count("bottom count")?;
+        let top_count = reader.read_count("top count")?;
+        let bottoms = (0..bottom_count)
+            .map(|_| reader.read_blob(blob_count))
+            .collect::<anyhow::Result<_>>()?;
+        let tops = (0..top_count)
+            .map(|_| reader.read_blob(blob_count))
+            .collect::<anyhow::Result<_>>()?;
+
+        let mut params = ParamDict::new();
+        loop {
+            let offset = reader.offset;
+            let id = reader.read_i32()?;
+            if id == PARAM_END {
+                break;
+            }
+            let (id, value) = if id <= ARRAY_ID_BASE {
+                let len = reader.read_count("array length")?;
+                let values = (0..len)
+                    .map(|_| reader.read_u32())
+                    .collect::<anyhow::Result<_>>()?;
+                (ARRAY_ID_BASE - id, ParamValue::RawArray(values))
+            } else {
+                (id, ParamValue::Raw(reader.read_u32()?))
+            };
+            if !(0..MAX_PARAM_COUNT).contains(&id) {
+                anyhow::bail!("Param id {} at byte {} out of range", id, offset);
+            }
+            params.set(id, value);
+        }
+
+        graph.layers.push(Layer {
+            layer_type: layer_type.to_string(),
+            name: i.to_string(),
+            bottoms,
+            tops,
+            params,
+        });
+    }
+    Ok(graph)
+}
+
+pub(super) fn value_bits(value: &ParamValue) -> Vec<u32> {
+    match value {
+        ParamValue::Int(i) => vec![*i as u32],
+        ParamValue::Float(f) => vec![f.to_bits()],
+        ParamValue::Raw(bits) => vec![*bits],
+        ParamValue::IntArray(v) => v.iter().map(|&i| i as u32).collect(),
+        ParamValue::FloatArray(v) => v.iter().map(|f| f.to_bits()).collect(),
+        ParamValue::RawArray(v) => v.clone(),
+    }
+}
+
+pub(super) fn write(graph: &Graph) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut push = |v: i32| out.extend_from_slice(&v.to_le_bytes());
+
+    push(MAGIC);
+    push(graph.layers.len() as i32);
+    push(graph.blobs.len() as i32);
+    for layer in &graph.layers {
+        let type_index = LAYER_TYPES
+            .iter()
+            .position(|&t| t == layer.layer_type)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Layer `{}` has type `{}`, which .param.bin cannot store",
+                    layer.name,
+                    layer.layer_type
+                )
+            })?;
+        push(type_index as i32);
+        push(layer.bottoms.len() as i32);
+        push(layer.tops.len() as i32);
+        for &blob in layer.bottoms.iter().chain(&layer.tops) {
+            push(blob as i32);
+        }
+        for (id, value) in layer.params.iter() {
+            let bits = value_bits(value);
+            if value.is_array() {
+                push(ARRAY_ID_BASE - id);
+                push(bits.len() as i32);
+            } else {
+                push(id);
+            }
+            for bits in bits {
+                push(bits as i32);
+            }
+        }
+        push(PARAM_END);
+    }
+    Ok(out)
+}
@@ -0,0 +1,522 @@
+//! Building a [Graph] and its weights in code, for small generated networks.
+use super::{Blob, Graph, Layer, ParamDict, ParamValue, Shape};
+use crate::modelbin;
+use crate::Net;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The operation of a `BinaryOp` layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Max,
+    Min,
+    Pow,
+}
+
+impl BinaryOp {
+    fn to_raw(self) -> i32 {
+        match self {
+            BinaryOp::Add => 0,
+            BinaryOp::Sub => 1,
+            BinaryOp::Mul => 2,
+            BinaryOp::Div => 3,
+            BinaryOp::Max => 4,
+            BinaryOp::Min => 5,
+            BinaryOp::Pow => 6,
+        }
+    }
+}
+
+/// How an `Interp` layer resizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resize {
+    Nearest,
+    Bilinear,
+    Bicubic,
+}
+
+/// Hyperparameters of a 2D convolution with square kernel, stride and padding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Conv2d {
+    pub num_output: usize,
+    pub kernel: usize,
+    pub stride: usize,
+    pub pad: usize,
+    pub dilation: usize,
+    /// Number of groups; a depthwise convolution has as many as input channels.
+    pub group: usize,
+}
+
+impl Conv2d {
+    /// A convolution with stride, dilation and group 1, and no padding.
+    pub fn new(num_output: usize, kernel: usize) -> Conv2d {
+        Conv2d {
+            num_output,
+            kernel,
+            stride: 1,
+            pad: 0,
+            dilation: 1,
+            group: 1,
+        }
+    }
+}
+
+/// A graph and the contents of its `.bin`, as [GraphBuilder::build] makes them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    pub graph: Graph,
+    pub weights: Vec<u8>,
+}
+
+impl Model {
+    /// Reads a `.param` or `.param.bin` and its `.bin`.
+    pub fn load<P: AsRef<Path>, Q: AsRef<Path>>(param: P, bin: Q) -> anyhow::Result<Model> {
+        let graph = Graph::load(param)?;
+        let bin = bin.as_ref();
+        let weights = std::fs::read(bin)
+            .map_err(|e| anyhow::anyhow!("Error reading {}: {}", bin.display(), e))?;
+        Ok(Model { graph, weights })
+    }
+
+    /// Writes the text `.param` and the `.bin`.
+    pub fn save<P: AsRef<Path>, Q: AsRef<Path>>(&self, param: P, bin: Q) -> anyhow::Result<()> {
+        self.graph.save(param)?;
+        let bin = bin.as_ref();
+        std::fs::write(bin, &self.weights)
+            .map_err(|e| anyhow::anyhow!("Error writing {}: {}", bin.display(), e))
+    }
+
+    /// Loads the graph and weights into `net` without going through files.
+    pub fn load_into(&self, net: &mut Net) -> anyhow::Result<()> {
+        net.load_param_memory(&self.graph.to_text())?;
+        net.load_model_memory(&self.weights)
+    }
+}
+
+/// Builds a graph layer by layer, checking that every bottom blob exists and that weights
+/// match what the layer reads, and lays out the `.bin` to go with it.
+///
+/// Layers must be added after the layers producing their bottoms. A blob consumed by more
+/// than one layer gets the `Split` layer ncnn expects when the graph is built.
+///
+/// ```
+/// use ncnn_rs::param::{BinaryOp, GraphBuilder, Shape};
+///
+/// // (x - mean) / std for a 3 channel image
+/// let mut builder = GraphBuilder::new();
+/// let data = builder.input("data", Shape::new_3d(224, 224, 3))?;
+/// let mean = builder.memory_data("mean", Shape::new_1d(3), vec![0.485, 0.456, 0.406])?;
+/// let centered = builder.binary_op("centered", &data, &mean, BinaryOp::Sub)?;
+/// let inv_std = vec![1.0 / 0.229, 1.0 / 0.224, 1.0 / 0.225];
+/// builder.scale("normalized", &centered, inv_std, None)?;
+/// let model = builder.build()?;
+/// assert_eq!(model.graph.layers.len(), 4);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GraphBuilder {
+    graph: Graph,
+    /// Weight blobs of every layer, in `.bin` order.
+    weights: Vec<Vec<Vec<f32>>>,
+    /// Blob name to index.
+    blobs: HashMap<String, usize>,
+}
+
+impl GraphBuilder {
+    pub fn new() -> GraphBuilder {
+        GraphBuilder::default()
+    }
+
+    /// Adds a layer with a single top named after it, and returns the top's name.
+    ///
+    /// `weights` are the blobs [modelbin::layout] lists for the layer, in that order.
+    pub fn layer(
+        &mut self,
+        layer_type: &str,
+        name: &str,
+        bottoms: &[&str],
+        params: ParamDict,
+        weights: Vec<Vec<f32>>,
+    ) -> anyhow::Result<String> {
+        self.layer_with_tops(layer_type, name, bottoms, &[name], params, weights)?;
+        Ok(name.to_string())
+    }
+
+    /// Adds a layer with any number of tops, see [GraphBuilder::layer].
+    pub fn layer_with_tops(
+        &mut self,
+        layer_type: &str,
+        name: &str,
+        bottoms: &[&str],
+        tops: &[&str],
+        params: ParamDict,
+        weights: Vec<Vec<f32>>,
+    ) -> anyhow::Result<()> {
+        if self.graph.layer_index(name).is_some() {
+            anyhow::bail!("Layer `{}` already exists", name);
+        }
+        let bottoms = bottoms
+            .iter()
+            .map(|bottom| {
+                self.blobs.get(*bottom).copied().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Layer `{}` reads blob `{}`, which no layer produces",
+                        name,
+                        bottom
+                    )
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for (i, top) in tops.iter().enumerate() {
+            if self.blobs.contains_key(*top) || tops[..i].contains(top) {
+                anyhow::bail!("Blob `{}` of layer `{}` already exists", top, name);
+            }
+        }
+
+        let layer = Layer {
+            layer_type: layer_type.to_string(),
+            name: name.to_string(),
+            bottoms,
+            tops: Vec::new(),
+            params,
+        };
+        let layout = modelbin::layout(&layer);
+        if layout.len() != weights.len() {
+            anyhow::bail!(
+                "Layer `{}` reads {} weight blobs, {} given",
+                name,
+                layout.len(),
+                weights.len()
+            );
+        }
+        for (spec, values) in layout.iter().zip(&weights) {
+            if spec.len != values.len() {
+                anyhow::bail!(
+                    "Layer `{}` reads {} values of {}, {} given",
+                    name,
+                    spec.len,
+                    spec.name,
+                    values.len()
+                );
+            }
+        }
+
+        let mut layer = layer;
+        for top in tops {
+            self.blobs.insert(top.to_string(), self.graph.blobs.len());
+            layer.tops.push(self.graph.blobs.len());
+            self.graph.blobs.push(Blob {
+                name: top.to_string(),
+            });
+        }
+        self.graph.layers.push(layer);
+        self.weights.push(weights);
+        Ok(())
+    }
+
+    /// Shape of a blob as far as [Graph::infer_shapes] can tell.
+    fn shape_of(&self, blob: &str) -> std::option::Option<Shape> {
+        let index = *self.blobs.get(blob)?;
+        self.graph.infer_shapes().ok()?[index]
+    }
+
+    /// An `Input` layer for blobs of `shape`, which ncnn takes as a hint only.
+    pub fn input(&mut self, name: &str, shape: Shape) -> anyhow::Result<String> {
+        self.layer("Input", name, &[], shape_params(shape), Vec::new())
+    }
+
+    /// A `MemoryData` layer holding a constant.
+    pub fn memory_data(
+        &mut self,
+        name: &str,
+        shape: Shape,
+        data: Vec<f32>,
+    ) -> anyhow::Result<String> {
+        self.layer("MemoryData", name, &[], shape_params(shape), vec![data])
+    }
+
+    /// A `Convolution`, or a `ConvolutionDepthWise` if grouped, with weights in
+    /// `[num_output][channels / group][kernel][kernel]` order.
+    ///
+    /// Fails if `weight` does not split evenly into `num_output` kernels, or, when the shape
+    /// of `bottom` is known, does not hold exactly that many kernels over its channels.
+    pub fn convolution(
+        &mut self,
+        name: &str,
+        bottom: &str,
+        conv: Conv2d,
+        weight: Vec<f32>,
+        bias: std::option::Option<Vec<f32>>,
+    ) -> anyhow::Result<String> {
+        check_outputs(name, conv.num_output, &weight)?;
+        if let Some(input) = self.shape_of(bottom) {
+            let expected =
+                conv.num_output * conv.kernel * conv.kernel * (input.c / conv.group.max(1));
+            if weight.len() != expected {
+                anyhow::bail!(
+                    "Layer `{}` over {} channels reads {} weights, {} given",
+                    name,
+                    input.c,
+                    expected,
+                    weight.len()
+                );
+            }
+        }
+        let mut params = params(&[
+            (0, conv.num_output),
+            (1, conv.kernel),
+            (2, conv.dilation),
+            (3, conv.stride),
+            (4, conv.pad),
+            (5, bias.is_some() as usize),
+            (6, weight.len()),
+        ]);
+        let layer_type = if conv.group > 1 {
+            params.set(7, ParamValue::Int(conv.group as i32));
+            "ConvolutionDepthWise"
+        } else {
+            "Convolution"
+        };
+        let weights = std::iter::once(weight).chain(bias).collect();
+        self.layer(layer_type, name, &[bottom], params, weights)
+    }
+
+    /// An `InnerProduct` with weights in `[num_output][inputs]` order.
+    ///
+    /// Fails if `weight` does not split evenly into `num_output` rows.
+    pub fn inner_product(
+        &mut self,
+        name: &str,
+        bottom: &str,
+        num_output: usize,
+        weight: Vec<f32>,
+        bias: std::option::Option<Vec<f32>>,
+    ) -> anyhow::Result<String> {
+        check_outputs(name, num_output, &weight)?;
+        let params = params(&[
+            (0, num_output),
+            (1, bias.is_some() as usize),
+            (2, weight.len()),
+        ]);
+        let weights = std::iter::once(weight).chain(bias).collect();
+        self.layer("InnerProduct", name, &[bottom], params, weights)
+    }
+
+    /// A per-channel `Scale`, with an optional per-channel bias added after.
+    pub fn scale(
+        &mut self,
+        name: &str,
+        bottom: &str,
+        scale: Vec<f32>,
+        bias: std::option::Option<Vec<f32>>,
+    ) -> anyhow::Result<String> {
+        let params = params(&[(0, scale.len()), (1, bias.is_some() as usize)]);
+        let weights = std::iter::once(scale).chain(bias).collect();
+        self.layer("Scale", name, &[bottom], params, weights)
+    }
+
+    /// A `BinaryOp` of two blobs, broadcasting the smaller.
+    pub fn binary_op(
+        &mut self,
+        name: &str,
+        a: &str,
+        b: &str,
+        op: BinaryOp,
+    ) -> anyhow::Result<String> {
+        let mut params = ParamDict::new();
+        params.set(0, ParamValue::Int(op.to_raw()));
+        self.layer("BinaryOp", name, &[a, b], params, Vec::new())
+    }
+
+    /// An `Interp` resizing to `width` x `height`.
+    pub fn resize(
+        &mut self,
+        name: &str,
+        bottom: &str,
+        width: usize,
+        height: usize,
+        resize: Resize,
+    ) -> anyhow::Result<String> {
+        let resize_type = match resize {
+            Resize::Nearest => 1,
+            Resize::Bilinear => 2,
+            Resize::Bicubic => 3,
+        };
+        let params = params(&[(0, resize_type), (3, height), (4, width)]);
+        self.layer("Interp", name, &[bottom], params, Vec::new())
+    }
+
+    /// A `Concat` along `axis`, counted like the `Concat` parameter, outermost first.
+    pub fn concat(&mut self, name: &str, bottoms: &[&str], axis: i32) -> anyhow::Result<String> {
+        let mut params = ParamDict::new();
+        params.set(0, ParamValue::Int(axis));
+        self.layer("Concat", name, bottoms, params, Vec::new())
+    }
+
+    /// A layer without parameters or weights, like `ReLU`, `Sigmoid` or `Softmax`.
+    pub fn unary(&mut self, layer_type: &str, name: &str, bottom: &str) -> anyhow::Result<String> {
+        self.layer(layer_type, name, &[bottom], ParamDict::new(), Vec::new())
+    }
+
+    /// Finishes the graph, inserting `Split` layers for blobs with more than one consumer,
+    /// and writes the `.bin`.
+    pub fn build(self) -> anyhow::Result<Model> {
+        let GraphBuilder {
+            graph: built,
+            weights,
+            ..
+        } = self;
+
+        let mut consumers: Vec<Vec<(usize, usize)>> = vec![Vec::new(); built.blobs.len()];
+        for (i, layer) in built.layers.iter().enumerate() {
+            for (slot, &bottom) in layer.bottoms.iter().enumerate() {
+                consumers[bottom].push((i, slot));
+            }
+        }
+
+        let mut graph = Graph {
+            layers: Vec::with_capacity(built.layers.len()),
+            blobs: built.blobs,
+        };
+        // (layer, bottom slot) to the split output it reads instead, by original layer index
+        let mut rewired = HashMap::new();
+        let mut splits = 0;
+        let mut bin = Vec::new();
+        for (i, (mut layer, weights)) in built.layers.into_iter().zip(weights).enumerate() {
+            for (slot, bottom) in layer.bottoms.iter_mut().enumerate() {
+                if let Some(&blob) = rewired.get(&(i, slot)) {
+                    *bottom = blob;
+                }
+            }
+            for (spec, values) in modelbin::layout(&layer).iter().zip(&weights) {
+                modelbin::write_f32(&mut bin, values, spec.tagged);
+            }
+            let tops = layer.tops.clone();
+            graph.layers.push(layer);
+
+            for top in tops.into_iter().filter(|&top| consumers[top].len() > 1) {
+                let mut split = Layer {
+                    layer_type: "Split".to_string(),
+                    name: format!("splitncnn_{}", splits),
+                    bottoms: vec![top],
+                    tops: Vec::new(),
+                    params: ParamDict::new(),
+                };
+                splits += 1;
+                for (n, &consumer) in consumers[top].iter().enumerate() {
+                    let name = format!("{}_splitncnn_{}", graph.blobs[top].name, n);
+                    rewired.insert(consumer, graph.blobs.len());
+                    split.tops.push(graph.blobs.len());
+                    graph.blobs.push(Blob { name });
+                }
+                graph.layers.push(split);
+            }
+        }
+        graph.compact_blobs();
+        Ok(Model {
+            graph,
+            weights: bin,
+        })
+    }
+}
+
+/// Checks that `weight` holds the same number of values for each of `num_output` outputs.
+fn check_outputs(name: &str, num_output: usize, weight: &[f32]) -> anyhow::Result<()> {
+    if num_output == 0 || !weight.len().is_multiple_of(num_output) {
+        anyhow::bail!(
+            "Layer `{}` has {} outputs, which {} weights do not split evenly into",
+            name,
+            num_output,
+            weight.len()
+        );
+    }
+    Ok(())
+}
+
+fn params(values: &[(i32, usize)]) -> ParamDict {
+    let mut params = ParamDict::new();
+    for &(id, value) in values {
+        params.set(id, ParamValue::Int(value as i32));
+    }
+    params
+}
+
+/// `Input` and `MemoryData` parameters for a shape, leaving out unused extents.
+fn shape_params(shape: Shape) -> ParamDict {
+    let mut params = params(&[(0, shape.w)]);
+    if shape.dims >= 2 {
+        params.set(1, ParamValue::Int(shape.h as i32));
+    }
+    if shape.dims == 4 {
+        params.set(11, ParamValue::Int(shape.d as i32));
+    }
+    if shape.dims >= 3 {
+        params.set(2, ParamValue::Int(shape.c as i32));
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_with_split_and_weights() {
+        let mut builder = GraphBuilder::new();
+        let data = builder.input("data", Shape::new_3d(8, 8, 3)).unwrap();
+        let conv = builder
+            .convolution(
+                "conv",
+                &data,
+                Conv2d::new(4, 1),
+                vec![0.5; 12],
+                Some(vec![1.0; 4]),
+            )
+            .unwrap();
+        let relu = builder.unary("ReLU", "relu", &conv).unwrap();
+        // conv feeds both relu and the sum, so needs a split
+        let sum = builder
+            .binary_op("sum", &conv, &relu, BinaryOp::Add)
+            .unwrap();
+
+        assert!(builder.unary("ReLU", "relu", &conv).is_err());
+        assert!(builder.unary("ReLU", "other", "missing").is_err());
+        assert!(builder
+            .inner_product("fc", &sum, 2, vec![0.0; 3], None)
+            .is_err());
+        assert!(builder
+            .convolution("bad", &sum, Conv2d::new(2, 3), vec![0.0; 2 * 9 * 3], None)
+            .is_err());
+        assert!(builder
+            .inner_product("fc", &sum, 2, vec![0.0; 2 * 8 * 8 * 4], None)
+            .is_ok());
+        assert!(builder
+            .scale("bad", &sum, vec![1.0; 4], Some(vec![0.0; 3]))
+            .is_err());
+
+        let model = builder.build().unwrap();
+        let text = model.graph.to_text();
+        let lines: Vec<String> = text
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines[4..7],
+            [
+                "Split splitncnn_0 1 2 conv conv_splitncnn_0 conv_splitncnn_1",
+                "ReLU relu 1 1 conv_splitncnn_0 relu",
+                "BinaryOp sum 2 1 conv_splitncnn_1 relu sum 0=0",
+            ]
+        );
+        assert_eq!(text.parse::<Graph>().unwrap(), model.graph);
+        assert!(model.graph.topological_order().is_ok());
+
+        // tagged conv weight, conv bias, tagged fc weight
+        assert_eq!(model.weights.len(), 4 + 12 * 4 + 4 * 4 + 4 + 512 * 4);
+        assert_eq!(&model.weights[4..8], &0.5f32.to_le_bytes());
+    }
+}
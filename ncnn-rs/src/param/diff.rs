@@ -0,0 +1,268 @@
+//! Semantic differences between two graphs, matching layers by name.
+use super::{binary, summary, Graph, Layer, ParamValue};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// The shape hints `ncnnoptimize` writes, which [diff] ignores.
+const SHAPE_HINT_ID: i32 = 30;
+
+/// A parameter set, unset or changed between two versions of a layer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ParamChange {
+    pub id: i32,
+    pub before: std::option::Option<ParamValue>,
+    pub after: std::option::Option<ParamValue>,
+}
+
+/// Differences of a layer present in both graphs.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LayerDiff {
+    pub name: String,
+    pub type_before: String,
+    pub type_after: String,
+    pub params: Vec<ParamChange>,
+    /// Bottom blob names, if the layer is wired differently beyond blob renames.
+    pub inputs: std::option::Option<(Vec<String>, Vec<String>)>,
+    /// Weight counts, if they changed.
+    pub weights: std::option::Option<(u64, u64)>,
+}
+
+/// Differences between two graphs, see [diff].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GraphDiff {
+    /// `(type, name)` of layers only in the new graph.
+    pub added_layers: Vec<(String, String)>,
+    /// `(type, name)` of layers only in the old graph.
+    pub removed_layers: Vec<(String, String)>,
+    pub changed_layers: Vec<LayerDiff>,
+    /// `(old, new)` names of blobs produced by the same top of the same layer.
+    pub renamed_blobs: Vec<(String, String)>,
+    /// Total weight counts of both graphs.
+    pub weights: (u64, u64),
+}
+
+impl GraphDiff {
+    /// Whether there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added_layers.is_empty()
+            && self.removed_layers.is_empty()
+            && self.changed_layers.is_empty()
+            && self.renamed_blobs.is_empty()
+    }
+}
+
+/// Whether two values are the same, however they were read: an `Int` from a `.param` equals
+/// the `Raw` of the same bits from a `.param.bin`.
+fn same_value(a: &ParamValue, b: &ParamValue) -> bool {
+    a.is_array() == b.is_array() && binary::value_bits(a) == binary::value_bits(b)
+}
+
+fn param_changes(a: &Layer, b: &Layer) -> Vec<ParamChange> {
+    let mut ids: Vec<i32> = a
+        .params
+        .iter()
+        .chain(b.params.iter())
+        .map(|(id, _)| id)
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.into_iter()
+        .filter(|&id| id != SHAPE_HINT_ID)
+        .filter_map(|id| {
+            let (before, after) = (a.params.get(id), b.params.get(id));
+            let same = match (before, after) {
+                (Some(before), Some(after)) => same_value(before, after),
+                (before, after) => before.is_none() && after.is_none(),
+            };
+            (!same).then(|| ParamChange {
+                id,
+                before: before.cloned(),
+                after: after.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Weight counts of every layer, as [Graph::summary] counts them.
+fn weights(graph: &Graph) -> Vec<u64> {
+    graph.layers.iter().map(summary::layer_weights).collect()
+}
+
+/// Compares two graphs, usually two exports of the same model.
+///
+/// Layers are matched by name. Matched layers are compared by type, parameters (ignoring the
+/// `-23330` shape hints), weight counts and wiring; a blob the matched layers produce under
+/// another name counts as renamed rather than as rewiring its consumers.
+pub fn diff(old: &Graph, new: &Graph) -> GraphDiff {
+    let (old_weights, new_weights) = (weights(old), weights(new));
+    let new_layers: HashMap<&str, usize> = new
+        .layers
+        .iter()
+        .enumerate()
+        .map(|(i, layer)| (layer.name.as_str(), i))
+        .collect();
+
+    let mut result = GraphDiff {
+        weights: (old_weights.iter().sum(), new_weights.iter().sum()),
+        ..GraphDiff::default()
+    };
+    // old blob name to new blob name, through the tops of matched layers
+    let mut renames: HashMap<&str, &str> = HashMap::new();
+    let mut matched = Vec::new();
+    for (i, a) in old.layers.iter().enumerate() {
+        match new_layers.get(a.name.as_str()) {
+            Some(&j) => {
+                let b = &new.layers[j];
+                for (&top_a, &top_b) in a.tops.iter().zip(&b.tops) {
+                    let (name_a, name_b) = (&old.blobs[top_a].name, &new.blobs[top_b].name);
+                    renames.insert(name_a, name_b);
+                    if name_a != name_b {
+                        result.renamed_blobs.push((name_a.clone(), name_b.clone()));
+                    }
+                }
+                matched.push((i, j));
+            }
+            None => result
+                .removed_layers
+                .push((a.layer_type.clone(), a.name.clone())),
+        }
+    }
+    let old_names: HashSet<&str> = old.layers.iter().map(|layer| layer.name.as_str()).collect();
+    result.added_layers = new
+        .layers
+        .iter()
+        .filter(|layer| !old_names.contains(layer.name.as_str()))
+        .map(|layer| (layer.layer_type.clone(), layer.name.clone()))
+        .collect();
+
+    for (i, j) in matched {
+        let (a, b) = (&old.layers[i], &new.layers[j]);
+        let names = |graph: &Graph, blobs: &[usize]| -> Vec<String> {
+            blobs
+                .iter()
+                .map(|&blob| graph.blobs[blob].name.clone())
+                .collect()
+        };
+        let (inputs_a, inputs_b) = (names(old, &a.bottoms), names(new, &b.bottoms));
+        let renamed_inputs: Vec<&str> = inputs_a
+            .iter()
+            .map(|name| renames.get(name.as_str()).copied().unwrap_or(name))
+            .collect();
+        let (weights_a, weights_b) = (old_weights[i], new_weights[j]);
+        let layer = LayerDiff {
+            name: a.name.clone(),
+            type_before: a.layer_type.clone(),
+            type_after: b.layer_type.clone(),
+            params: param_changes(a, b),
+            inputs: (renamed_inputs != inputs_b).then_some((inputs_a, inputs_b)),
+            weights: (weights_a != weights_b).then_some((weights_a, weights_b)),
+        };
+        if layer.type_before != layer.type_after
+            || !layer.params.is_empty()
+            || layer.inputs.is_some()
+            || layer.weights.is_some()
+        {
+            result.changed_layers.push(layer);
+        }
+    }
+    result
+}
+
+/// A value as the text `.param` writes it, or `unset`.
+struct Value<'a>(std::option::Option<&'a ParamValue>);
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(value) => super::text::write_value(f, value),
+            None => write!(f, "unset"),
+        }
+    }
+}
+
+/// A report in the style of a unified diff: `-` for removed, `+` for added and `~` for changed.
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            writeln!(f, "no differences")?;
+        }
+        for (layer_type, name) in &self.removed_layers {
+            writeln!(f, "- {} {}", layer_type, name)?;
+        }
+        for (layer_type, name) in &self.added_layers {
+            writeln!(f, "+ {} {}", layer_type, name)?;
+        }
+        for layer in &self.changed_layers {
+            writeln!(f, "~ {} {}", layer.type_after, layer.name)?;
+            if layer.type_before != layer.type_after {
+                writeln!(f, "    type: {} -> {}", layer.type_before, layer.type_after)?;
+            }
+            for change in &layer.params {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    change.id,
+                    Value(change.before.as_ref()),
+                    Value(change.after.as_ref())
+                )?;
+            }
+            if let Some((before, after)) = &layer.inputs {
+                writeln!(f, "    inputs: {} -> {}", before.join(","), after.join(","))?;
+            }
+            if let Some((before, after)) = layer.weights {
+                writeln!(f, "    weights: {} -> {}", before, after)?;
+            }
+        }
+        for (before, after) in &self.renamed_blobs {
+            writeln!(f, "~ blob {} -> {}", before, after)?;
+        }
+        write!(f, "weights: {} -> {}", self.weights.0, self.weights.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn diff_int8_export() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        let fp32 = Graph::load(dir.join("squeezenet.param")).unwrap();
+        let int8 = Graph::load(dir.join("squeezenet_int8.param")).unwrap();
+
+        assert!(diff(&fp32, &fp32).is_empty());
+        // a .param.bin reads the same values as Raw
+        assert!(same_value(&ParamValue::Int(2), &ParamValue::Raw(2)));
+        assert!(!same_value(
+            &ParamValue::Int(2),
+            &ParamValue::RawArray(vec![2])
+        ));
+
+        // only the int8 scale term is new, on every convolution
+        let changes = diff(&fp32, &int8);
+        assert!(changes.added_layers.is_empty() && changes.removed_layers.is_empty());
+        let convolutions = fp32
+            .layers
+            .iter()
+            .filter(|layer| layer.layer_type == "Convolution")
+            .count();
+        assert_eq!(changes.changed_layers.len(), convolutions);
+        let conv1 = &changes.changed_layers[0];
+        assert_eq!(conv1.name, "conv1");
+        assert_eq!(
+            conv1.params,
+            [ParamChange {
+                id: 8,
+                before: None,
+                after: Some(ParamValue::Int(2)),
+            }]
+        );
+        assert!(changes
+            .to_string()
+            .contains("~ Convolution conv1\n    8: unset -> 2\n"));
+    }
+}
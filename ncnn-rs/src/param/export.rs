@@ -0,0 +1,297 @@
+//! Exporting a [Graph] for visualisation: Graphviz DOT, and JSON with the `serde` feature.
+use super::{Graph, Layer};
+use std::fmt::Write;
+
+/// Quotes a DOT identifier or label.
+fn quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+/// The few parameters that say what a layer does at a glance, like `3x3/2 64` for a
+/// convolution.
+fn key_params(layer: &Layer) -> String {
+    let pd = &layer.params;
+    let window = |kernel_id: i32, stride_id: i32| {
+        let kernel_w = pd.get_int(kernel_id, 0);
+        let kernel_h = pd.get_int(kernel_id + 10, kernel_w);
+        let stride = pd.get_int(stride_id, 1);
+        if stride > 1 {
+            format!("{}x{}/{}", kernel_w, kernel_h, stride)
+        } else {
+            format!("{}x{}", kernel_w, kernel_h)
+        }
+    };
+    match layer.layer_type.as_str() {
+        "Convolution" | "Deconvolution" => format!("{} {}", window(1, 3), pd.get_int(0, 0)),
+        "ConvolutionDepthWise" | "DeconvolutionDepthWise" => format!(
+            "{} {} g{}",
+            window(1, 3),
+            pd.get_int(0, 0),
+            pd.get_int(7, 1)
+        ),
+        "Pooling" => {
+            let kind = if pd.get_int(0, 0) == 0 { "max" } else { "avg" };
+            if pd.get_int(4, 0) != 0 {
+                format!("global {}", kind)
+            } else {
+                format!("{} {}", kind, window(1, 2))
+            }
+        }
+        "InnerProduct" => pd.get_int(0, 0).to_string(),
+        "Concat" | "Softmax" => format!("axis {}", pd.get_int(0, 0)),
+        "Slice" => format!("axis {}", pd.get_int(1, 0)),
+        "Interp" if pd.get_int(3, 0) > 0 => {
+            format!("{}x{}", pd.get_int(4, 0), pd.get_int(3, 0))
+        }
+        "Interp" => format!("x{}", pd.get_float(2, 1.0)),
+        "BinaryOp" | "UnaryOp" | "Eltwise" => format!("op {}", pd.get_int(0, 0)),
+        _ => String::new(),
+    }
+}
+
+impl Graph {
+    /// A Graphviz DOT digraph with layers as nodes and blobs as edges.
+    ///
+    /// Nodes are labelled with the layer type, name and key parameters, and edges with the
+    /// blob name and, where [Graph::infer_shapes] can tell, its shape. Blobs without a
+    /// producer or without consumers get nodes of their own so inputs and outputs show.
+    pub fn to_dot(&self) -> String {
+        let shapes = self
+            .infer_shapes()
+            .unwrap_or_else(|_| vec![None; self.blobs.len()]);
+        let blob_label = |blob: usize| match shapes[blob] {
+            Some(shape) => quote(&format!("{}\n{}", self.blobs[blob].name, shape)),
+            None => quote(&self.blobs[blob].name),
+        };
+        let mut producers = vec![None; self.blobs.len()];
+        let mut consumed = vec![false; self.blobs.len()];
+        for (i, layer) in self.layers.iter().enumerate() {
+            for &top in &layer.tops {
+                producers[top].get_or_insert(i);
+            }
+            for &bottom in &layer.bottoms {
+                consumed[bottom] = true;
+            }
+        }
+
+        let mut dot = String::new();
+        // writing to a String cannot fail
+        let mut line = |s: String| writeln!(dot, "{}", s).unwrap();
+        line("digraph ncnn {".to_string());
+        line("  rankdir=TB;".to_string());
+        line("  node [shape=box, style=\"rounded,filled\", fillcolor=white];".to_string());
+        for (i, layer) in self.layers.iter().enumerate() {
+            let color = match layer.layer_type.as_str() {
+                "Input" => "lightgrey",
+                t if t.starts_with("Convolution") || t.starts_with("Deconvolution") => "lightblue",
+                "Pooling" => "palegreen",
+                "InnerProduct" => "lightsalmon",
+                "Concat" | "Slice" | "Split" | "Crop" | "Reshape" | "Permute" => "khaki",
+                _ => "white",
+            };
+            let mut label = format!("{}\n{}", layer.layer_type, layer.name);
+            let params = key_params(layer);
+            if !params.is_empty() {
+                label = format!("{}\n{}", label, params);
+            }
+            line(format!(
+                "  L{} [label={}, fillcolor={}];",
+                i,
+                quote(&label),
+                color
+            ));
+        }
+        for (blob, producer) in producers.iter().enumerate() {
+            if producer.is_none() || !consumed[blob] {
+                line(format!(
+                    "  B{} [label={}, shape=ellipse, style=solid];",
+                    blob,
+                    blob_label(blob)
+                ));
+            }
+            if let (Some(producer), false) = (producer, consumed[blob]) {
+                line(format!("  L{} -> B{};", producer, blob));
+            }
+        }
+        for (i, layer) in self.layers.iter().enumerate() {
+            for &bottom in &layer.bottoms {
+                match producers[bottom] {
+                    Some(producer) => line(format!(
+                        "  L{} -> L{} [label={}];",
+                        producer,
+                        i,
+                        blob_label(bottom)
+                    )),
+                    None => line(format!("  B{} -> L{};", bottom, i)),
+                }
+            }
+        }
+        line("}".to_string());
+        dot
+    }
+
+    /// A JSON object with layers referring to blobs by name, see the `Serialize` impl.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        // nothing in a Graph fails to serialize to JSON
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use super::super::{text, Graph, ParamDict, ParamValue, Shape};
+    use serde::ser::{Serialize, SerializeMap, Serializer};
+
+    /// Ints and floats as JSON numbers and arrays as JSON arrays. Values from a `.param.bin`
+    /// are told apart the way the text writer does.
+    impl Serialize for ParamValue {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let raw = |bits: u32| {
+                if text::raw_is_int(bits) {
+                    ParamValue::Int(bits as i32)
+                } else {
+                    ParamValue::Float(f32::from_bits(bits))
+                }
+            };
+            match self {
+                ParamValue::Int(i) => i.serialize(serializer),
+                ParamValue::Float(f) => f.serialize(serializer),
+                ParamValue::Raw(bits) => raw(*bits).serialize(serializer),
+                ParamValue::IntArray(v) => v.serialize(serializer),
+                ParamValue::FloatArray(v) => v.serialize(serializer),
+                ParamValue::RawArray(v) if v.iter().all(|&bits| text::raw_is_int(bits)) => {
+                    serializer.collect_seq(v.iter().map(|&bits| bits as i32))
+                }
+                ParamValue::RawArray(v) if text::raw_is_mixed(v) => {
+                    serializer.collect_seq(v.iter().map(|&bits| raw(bits)))
+                }
+                ParamValue::RawArray(v) => {
+                    serializer.collect_seq(v.iter().map(|&bits| f32::from_bits(bits)))
+                }
+            }
+        }
+    }
+
+    /// A map from parameter id to value, in file order.
+    impl Serialize for ParamDict {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(self.len()))?;
+            for (id, value) in self.iter() {
+                map.serialize_entry(&id.to_string(), value)?;
+            }
+            map.end()
+        }
+    }
+
+    #[derive(serde::Serialize)]
+    struct JsonLayer<'a> {
+        #[serde(rename = "type")]
+        layer_type: &'a str,
+        name: &'a str,
+        inputs: Vec<&'a str>,
+        outputs: Vec<&'a str>,
+        params: &'a ParamDict,
+        output_shapes: Vec<std::option::Option<Shape>>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct JsonGraph<'a> {
+        inputs: Vec<&'a str>,
+        outputs: Vec<&'a str>,
+        layers: Vec<JsonLayer<'a>>,
+    }
+
+    /// Layers in file order with their blobs by name, parameters by id and inferred output
+    /// shapes, plus the names of the model's inputs and outputs:
+    ///
+    /// ```json
+    /// {"inputs": ["data"], "outputs": ["prob"], "layers": [{"type": "Input", "name": "data",
+    ///  "inputs": [], "outputs": ["data"], "params": {"0": 227}, "output_shapes": [...]}]}
+    /// ```
+    impl Serialize for Graph {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let shapes = self
+                .infer_shapes()
+                .unwrap_or_else(|_| vec![None; self.blobs.len()]);
+            let names = |blobs: &[usize]| -> Vec<&str> {
+                blobs.iter().map(|&b| self.blobs[b].name.as_str()).collect()
+            };
+            JsonGraph {
+                inputs: names(&self.inputs()),
+                outputs: names(&self.outputs()),
+                layers: self
+                    .layers
+                    .iter()
+                    .map(|layer| JsonLayer {
+                        layer_type: &layer.layer_type,
+                        name: &layer.name,
+                        inputs: names(&layer.bottoms),
+                        outputs: names(&layer.tops),
+                        params: &layer.params,
+                        output_shapes: layer.tops.iter().map(|&top| shapes[top]).collect(),
+                    })
+                    .collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    #[test]
+    fn export_dot() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        let graph = super::Graph::load(dir.join("squeezenet.param")).unwrap();
+        let dot = graph.to_dot();
+
+        assert!(dot.starts_with("digraph ncnn {\n"));
+        assert!(dot.contains("L1 [label=\"Convolution\\nconv1\\n3x3/2 64\", fillcolor=lightblue];"));
+        assert!(dot.contains("L1 -> L2 [label=\"conv1_relu_conv1\\n64x113x113\"];"));
+        // the output blob gets a node of its own
+        let prob = graph.blob_index("output").unwrap();
+        assert!(dot.contains(&format!("  L{} -> B{};", graph.layers.len() - 1, prob)));
+        let edges = dot.matches(" -> ").count();
+        let bottoms: usize = graph.layers.iter().map(|layer| layer.bottoms.len()).sum();
+        assert_eq!(edges, bottoms + graph.outputs().len());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn export_json() {
+        let graph: super::Graph = "7767517\n2 2\n\
+            Input data 0 1 data 0=4 1=4 2=3\n\
+            Convolution conv 1 1 data conv 0=8 1=3 5=1 6=216 9=2 \
+            -23310=2,1.000000e-01,2.000000e-01 -23311=2,1,0.5\n"
+            .parse()
+            .unwrap();
+        // a .param.bin does not record int or float, values are told apart like in text
+        let graph = super::Graph::parse(&graph.to_binary().unwrap()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        let params = &json["layers"][1]["params"];
+        assert_eq!(params["0"], serde_json::json!(8));
+        assert_eq!(params["10"], serde_json::json!([0.1, 0.2]));
+        assert_eq!(params["11"], serde_json::json!([1, 0.5]));
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        let graph = super::Graph::load(dir.join("squeezenet.param")).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(json["inputs"], serde_json::json!(["data"]));
+        assert_eq!(json["outputs"], serde_json::json!(["output"]));
+        let layers = json["layers"].as_array().unwrap();
+        assert_eq!(layers.len(), graph.layers.len());
+        let prob = layers.last().unwrap();
+        assert_eq!(prob["inputs"], serde_json::json!(["pool10"]));
+        assert_eq!(
+            prob["output_shapes"],
+            serde_json::json!([{"dims": 1, "w": 1000, "h": 1, "d": 1, "c": 1}])
+        );
+    }
+}
@@ -0,0 +1,458 @@
+//! Fusing and removing layers the way `ncnnoptimize` does, working on the `.param` and
+//! `.bin` alone.
+use super::{Graph, Model, ParamValue};
+use crate::modelbin;
+use std::fmt;
+
+/// Layers with weights per output channel and a built-in activation, which can absorb a
+/// `BatchNorm`, `Scale` or activation reading their output.
+const FUSE_TARGETS: [&str; 5] = [
+    "Convolution",
+    "ConvolutionDepthWise",
+    "Deconvolution",
+    "DeconvolutionDepthWise",
+    "InnerProduct",
+];
+
+/// Parameter ids of the built-in activation of every layer in [FUSE_TARGETS].
+const ACTIVATION_TYPE: i32 = 9;
+const ACTIVATION_PARAMS: i32 = 10;
+
+/// Something [Model::optimize] did.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Change {
+    /// A layer folded into the layer producing its input, which takes over its output.
+    Fused {
+        layer_type: String,
+        name: String,
+        into: String,
+    },
+    /// A layer doing nothing at inference.
+    Removed { layer_type: String, name: String },
+    /// A top of a `Split` no layer reads.
+    DeadBlob { blob: String, layer: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Fused {
+                layer_type,
+                name,
+                into,
+            } => write!(f, "fused {} {} into {}", layer_type, name, into),
+            Change::Removed { layer_type, name } => write!(f, "removed {} {}", layer_type, name),
+            Change::DeadBlob { blob, layer } => {
+                write!(f, "removed unused blob {} of {}", blob, layer)
+            }
+        }
+    }
+}
+
+/// What [Model::optimize] changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OptimizeReport {
+    pub changes: Vec<Change>,
+    /// Layer counts before and after.
+    pub layers: (usize, usize),
+    /// Blob counts before and after.
+    pub blobs: (usize, usize),
+    /// `.bin` sizes before and after, in bytes.
+    pub weight_bytes: (usize, usize),
+}
+
+impl fmt::Display for OptimizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        write!(
+            f,
+            "layers: {} -> {}, blobs: {} -> {}, weights: {} -> {} bytes",
+            self.layers.0,
+            self.layers.1,
+            self.blobs.0,
+            self.blobs.1,
+            self.weight_bytes.0,
+            self.weight_bytes.1
+        )
+    }
+}
+
+impl Model {
+    /// Fuses and removes layers like `ncnnoptimize`, returning the optimized model and what
+    /// changed.
+    ///
+    /// In order, this removes `Dropout` and `Noop` layers, `Split` tops no layer reads and
+    /// `Split` layers left with a single top, then folds `BatchNorm` and `Scale` into the
+    /// convolution or inner product before them, and finally fuses the `ReLU`, `Clip`,
+    /// `Sigmoid`, `Mish` and `HardSwish` layers that follow one. Output blobs keep their
+    /// names. Weights are written back as float32, see [crate::modelbin::read_f32].
+    pub fn optimize(&self) -> anyhow::Result<(Model, OptimizeReport)> {
+        let mut optimizer = Optimizer {
+            weights: modelbin::read_f32(&self.graph, &self.weights)?,
+            removed: vec![false; self.graph.layers.len()],
+            graph: self.graph.clone(),
+            changes: Vec::new(),
+        };
+        optimizer.remove_identities();
+        optimizer.remove_dead_split_tops();
+        optimizer.fuse_batchnorm_scale();
+        optimizer.fuse_activations();
+
+        let changes = std::mem::take(&mut optimizer.changes);
+        let model = optimizer.finish();
+        let report = OptimizeReport {
+            changes,
+            layers: (self.graph.layers.len(), model.graph.layers.len()),
+            blobs: (self.graph.blobs.len(), model.graph.blobs.len()),
+            weight_bytes: (self.weights.len(), model.weights.len()),
+        };
+        Ok((model, report))
+    }
+}
+
+/// A graph being optimized, with layers marked removed rather than taken out.
+struct Optimizer {
+    graph: Graph,
+    /// Weight blobs of every layer.
+    weights: Vec<Vec<Vec<f32>>>,
+    removed: Vec<bool>,
+    changes: Vec<Change>,
+}
+
+impl Optimizer {
+    fn producer(&self, blob: usize) -> std::option::Option<usize> {
+        (0..self.graph.layers.len())
+            .find(|&i| !self.removed[i] && self.graph.layers[i].tops.contains(&blob))
+    }
+
+    fn consumers(&self, blob: usize) -> Vec<usize> {
+        (0..self.graph.layers.len())
+            .filter(|&i| !self.removed[i] && self.graph.layers[i].bottoms.contains(&blob))
+            .collect()
+    }
+
+    /// The layer producing the single bottom of `layer`, if no other layer reads it.
+    fn sole_producer(&self, layer: usize) -> std::option::Option<usize> {
+        let [bottom] = self.graph.layers[layer].bottoms[..] else {
+            return None;
+        };
+        if self.consumers(bottom) != [layer] {
+            return None;
+        }
+        self.producer(bottom)
+    }
+
+    /// Removes a layer with one bottom and one top, connecting its input to its readers.
+    ///
+    /// The producer of the input takes over the top where it can, so that output and
+    /// fused blobs keep their names; inputs keep theirs.
+    fn bypass(&mut self, layer: usize) -> bool {
+        let (bottom, top) = match (
+            &self.graph.layers[layer].bottoms[..],
+            &self.graph.layers[layer].tops[..],
+        ) {
+            (&[bottom], &[top]) => (bottom, top),
+            _ => return false,
+        };
+        match self.sole_producer(layer) {
+            Some(producer) if self.graph.layers[producer].layer_type != "Input" => {
+                for blob in &mut self.graph.layers[producer].tops {
+                    if *blob == bottom {
+                        *blob = top;
+                    }
+                }
+            }
+            _ => {
+                let consumers = self.consumers(top);
+                if consumers.is_empty() {
+                    return false;
+                }
+                for consumer in consumers {
+                    for blob in &mut self.graph.layers[consumer].bottoms {
+                        if *blob == top {
+                            *blob = bottom;
+                        }
+                    }
+                }
+            }
+        }
+        self.removed[layer] = true;
+        true
+    }
+
+    fn remove(&mut self, layer: usize) {
+        if self.bypass(layer) {
+            let layer = &self.graph.layers[layer];
+            self.changes.push(Change::Removed {
+                layer_type: layer.layer_type.clone(),
+                name: layer.name.clone(),
+            });
+        }
+    }
+
+    fn fuse(&mut self, layer: usize, into: usize) {
+        if self.bypass(layer) {
+            let (layer, into) = (&self.graph.layers[layer], &self.graph.layers[into]);
+            self.changes.push(Change::Fused {
+                layer_type: layer.layer_type.clone(),
+                name: layer.name.clone(),
+                into: into.name.clone(),
+            });
+        }
+    }
+
+    /// Removes `Dropout` layers, which only scale if told to, and `Noop` layers.
+    fn remove_identities(&mut self) {
+        for i in 0..self.graph.layers.len() {
+            let layer = &self.graph.layers[i];
+            let identity = match layer.layer_type.as_str() {
+                "Dropout" => layer.params.get_float(0, 1.0) == 1.0,
+                "Noop" => true,
+                _ => false,
+            };
+            if identity {
+                self.remove(i);
+            }
+        }
+    }
+
+    /// Removes `Split` tops no layer reads, then `Split` layers left with a single top.
+    fn remove_dead_split_tops(&mut self) {
+        for i in 0..self.graph.layers.len() {
+            if self.removed[i] || self.graph.layers[i].layer_type != "Split" {
+                continue;
+            }
+            for top in self.graph.layers[i].tops.clone() {
+                if self.graph.layers[i].tops.len() > 1 && self.consumers(top).is_empty() {
+                    self.graph.layers[i].tops.retain(|&blob| blob != top);
+                    self.changes.push(Change::DeadBlob {
+                        blob: self.graph.blobs[top].name.clone(),
+                        layer: self.graph.layers[i].name.clone(),
+                    });
+                }
+            }
+            if self.graph.layers[i].tops.len() == 1 {
+                self.remove(i);
+            }
+        }
+    }
+
+    /// Whether `layer` can absorb a per-channel scale and shift of its `channels` outputs.
+    fn can_absorb(&self, layer: usize, channels: usize) -> bool {
+        let (layer, weights) = (&self.graph.layers[layer], &self.weights[layer]);
+        FUSE_TARGETS.contains(&layer.layer_type.as_str())
+            && layer.params.get_int(ACTIVATION_TYPE, 0) == 0
+            && layer.params.get_int(8, 0) == 0
+            && layer.params.get_int(0, 0) as usize == channels
+            && channels > 0
+            && weights
+                .first()
+                .is_some_and(|weight| weight.len() % channels == 0)
+    }
+
+    /// Folds `y = x * scale + shift` per output channel into the weights of `layer`.
+    fn fold(&mut self, layer: usize, scale: &[f32], shift: &[f32]) {
+        let bias_term = match self.graph.layers[layer].layer_type.as_str() {
+            "InnerProduct" => 1,
+            _ => 5,
+        };
+        let weights = &mut self.weights[layer];
+        if weights.len() < 2 {
+            weights.push(vec![0.0; scale.len()]);
+            self.graph.layers[layer]
+                .params
+                .set(bias_term, ParamValue::Int(1));
+        }
+        let per_channel = weights[0].len() / scale.len();
+        for (channel, weight) in weights[0].chunks_mut(per_channel).enumerate() {
+            for value in weight {
+                *value *= scale[channel];
+            }
+        }
+        for (channel, bias) in weights[1].iter_mut().enumerate() {
+            *bias = *bias * scale[channel] + shift[channel];
+        }
+    }
+
+    /// Folds `BatchNorm` and `Scale` layers into the convolution or inner product before.
+    fn fuse_batchnorm_scale(&mut self) {
+        for i in 0..self.graph.layers.len() {
+            let layer = &self.graph.layers[i];
+            let weights = &self.weights[i];
+            let (scale, shift): (Vec<f32>, Vec<f32>) = match layer.layer_type.as_str() {
+                "BatchNorm" => {
+                    let [slope, mean, var, bias] = &weights[..] else {
+                        continue;
+                    };
+                    let eps = layer.params.get_float(1, 0.0);
+                    let scale: Vec<f32> = slope
+                        .iter()
+                        .zip(var)
+                        .map(|(slope, var)| slope / (var + eps).sqrt())
+                        .collect();
+                    let shift = bias
+                        .iter()
+                        .zip(mean)
+                        .zip(&scale)
+                        .map(|((bias, mean), scale)| bias - mean * scale)
+                        .collect();
+                    (scale, shift)
+                }
+                // -233 takes the scale from a second bottom
+                "Scale" if layer.params.get_int(0, 0) != -233 => {
+                    let scale = weights[0].clone();
+                    let shift = match weights.get(1) {
+                        Some(bias) => bias.clone(),
+                        None => vec![0.0; scale.len()],
+                    };
+                    (scale, shift)
+                }
+                _ => continue,
+            };
+            let Some(target) = self.sole_producer(i) else {
+                continue;
+            };
+            if self.can_absorb(target, scale.len()) {
+                self.fold(target, &scale, &shift);
+                self.fuse(i, target);
+            }
+        }
+    }
+
+    /// Fuses activations into the convolution or inner product before, as its built-in
+    /// activation.
+    fn fuse_activations(&mut self) {
+        for i in 0..self.graph.layers.len() {
+            let params = &self.graph.layers[i].params;
+            let (activation, activation_params) = match self.graph.layers[i].layer_type.as_str() {
+                "ReLU" => match params.get_float(0, 0.0) {
+                    0.0 => (1, Vec::new()),
+                    slope => (2, vec![slope]),
+                },
+                "Clip" => (
+                    3,
+                    vec![
+                        params.get_float(0, -f32::MAX),
+                        params.get_float(1, f32::MAX),
+                    ],
+                ),
+                "Sigmoid" => (4, Vec::new()),
+                "Mish" => (5, Vec::new()),
+                "HardSwish" => (6, vec![params.get_float(0, 0.2), params.get_float(1, 0.5)]),
+                _ => continue,
+            };
+            let Some(target) = self.sole_producer(i) else {
+                continue;
+            };
+            let target_layer = &mut self.graph.layers[target];
+            if !FUSE_TARGETS.contains(&target_layer.layer_type.as_str())
+                || target_layer.params.get_int(ACTIVATION_TYPE, 0) != 0
+            {
+                continue;
+            }
+            let target_params = &mut target_layer.params;
+            target_params.set(ACTIVATION_TYPE, ParamValue::Int(activation));
+            if !activation_params.is_empty() {
+                target_params.set(ACTIVATION_PARAMS, ParamValue::FloatArray(activation_params));
+            }
+            self.fuse(i, target);
+        }
+    }
+
+    /// The graph without removed layers, and its `.bin`.
+    fn finish(self) -> Model {
+        let mut graph = Graph {
+            layers: Vec::with_capacity(self.graph.layers.len()),
+            blobs: self.graph.blobs,
+        };
+        let mut bin = Vec::new();
+        let layers = self.graph.layers.into_iter().zip(self.weights);
+        for ((layer, weights), removed) in layers.zip(self.removed) {
+            if removed {
+                continue;
+            }
+            for (spec, values) in modelbin::layout(&layer).iter().zip(&weights) {
+                modelbin::write_f32(&mut bin, values, spec.tagged);
+            }
+            graph.layers.push(layer);
+        }
+        graph.compact_blobs();
+        Model {
+            graph,
+            weights: bin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{Conv2d, GraphBuilder, ParamDict, Shape};
+
+    #[test]
+    fn optimize_conv_bn_relu() {
+        let mut builder = GraphBuilder::new();
+        builder.input("data", Shape::new_3d(1, 1, 1)).unwrap();
+        builder
+            .convolution("conv", "data", Conv2d::new(2, 1), vec![2.0, 3.0], None)
+            .unwrap();
+        let mut bn = ParamDict::new();
+        bn.set(0, ParamValue::Int(2));
+        let bn_weights = vec![
+            vec![1.0, 2.0],  // slope
+            vec![0.5, 1.0],  // mean
+            vec![4.0, 16.0], // var
+            vec![0.0, 1.0],  // bias
+        ];
+        builder
+            .layer("BatchNorm", "bn", &["conv"], bn, bn_weights)
+            .unwrap();
+        builder.unary("ReLU", "relu", "bn").unwrap();
+        builder
+            .layer("Dropout", "drop", &["relu"], ParamDict::new(), Vec::new())
+            .unwrap();
+        let model = builder.build().unwrap();
+
+        let (optimized, report) = model.optimize().unwrap();
+        assert_eq!(report.changes.len(), 3);
+        assert_eq!(report.layers, (5, 2));
+        assert_eq!(
+            report.to_string().lines().next(),
+            Some("removed Dropout drop")
+        );
+        let graph = &optimized.graph;
+        let conv = &graph.layers[1];
+        assert_eq!(graph.blobs[conv.tops[0]].name, "drop");
+        assert_eq!(conv.params.get_int(ACTIVATION_TYPE, 0), 1);
+        assert_eq!(conv.params.get_int(5, 0), 1);
+        let weights = modelbin::read_f32(graph, &optimized.weights).unwrap();
+        assert_eq!(weights[1], [vec![1.0, 1.5], vec![-0.25, 0.5]]);
+
+        // a split feeding a single layer goes, along with its unread top
+        let graph: Graph = "7767517
+4 5
+Input    data        0 1 data
+Split    splitncnn_0 1 2 data data_splitncnn_0 data_splitncnn_1
+Noop     noop        1 1 data_splitncnn_0 noop
+Softmax  prob        1 1 noop prob"
+            .parse()
+            .unwrap();
+        let model = Model {
+            graph,
+            weights: Vec::new(),
+        };
+        let (optimized, report) = model.optimize().unwrap();
+        assert_eq!(report.changes.len(), 3);
+        let expected: Graph = "7767517
+2 2
+Input    data 0 1 data
+Softmax  prob 1 1 data prob"
+            .parse()
+            .unwrap();
+        assert_eq!(optimized.graph, expected);
+    }
+}
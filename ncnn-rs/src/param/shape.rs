@@ -0,0 +1,694 @@
+//! Static shape inference over a [Graph], without running the model.
+use super::{Graph, Layer, ParamDict};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The shape of a blob, as the `dims`, `w`, `h`, `d` and `c` of an ncnn `Mat`.
+///
+/// Unused extents are 1, so a 3D shape has `d == 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Shape {
+    pub dims: usize,
+    pub w: usize,
+    pub h: usize,
+    pub d: usize,
+    pub c: usize,
+}
+
+impl Shape {
+    pub fn new_1d(w: usize) -> Shape {
+        Shape {
+            dims: 1,
+            w,
+            h: 1,
+            d: 1,
+            c: 1,
+        }
+    }
+
+    pub fn new_2d(w: usize, h: usize) -> Shape {
+        Shape {
+            dims: 2,
+            h,
+            ..Shape::new_1d(w)
+        }
+    }
+
+    pub fn new_3d(w: usize, h: usize, c: usize) -> Shape {
+        Shape {
+            dims: 3,
+            c,
+            ..Shape::new_2d(w, h)
+        }
+    }
+
+    pub fn new_4d(w: usize, h: usize, d: usize, c: usize) -> Shape {
+        Shape {
+            dims: 4,
+            d,
+            ..Shape::new_3d(w, h, c)
+        }
+    }
+
+    /// Number of elements.
+    pub fn elements(&self) -> usize {
+        self.w * self.h * self.d * self.c
+    }
+
+    /// Extents outermost first: `[w]`, `[h, w]`, `[c, h, w]` or `[c, d, h, w]`, the order
+    /// ncnn's `axis` parameters count in.
+    pub fn extents(&self) -> Vec<usize> {
+        match self.dims {
+            1 => vec![self.w],
+            2 => vec![self.h, self.w],
+            3 => vec![self.c, self.h, self.w],
+            _ => vec![self.c, self.d, self.h, self.w],
+        }
+    }
+
+    /// The inverse of [Shape::extents].
+    pub fn from_extents(extents: &[usize]) -> std::option::Option<Shape> {
+        match *extents {
+            [w] => Some(Shape::new_1d(w)),
+            [h, w] => Some(Shape::new_2d(w, h)),
+            [c, h, w] => Some(Shape::new_3d(w, h, c)),
+            [c, d, h, w] => Some(Shape::new_4d(w, h, d, c)),
+            _ => None,
+        }
+    }
+
+    /// A shape from `w`, `h`, `d` and `c` parameters where 0 means unused, as `Input` and
+    /// `MemoryData` take them.
+    fn from_params(w: i32, h: i32, d: i32, c: i32) -> std::option::Option<Shape> {
+        let [w, h, d, c] = [w, h, d, c].map(|v| v.max(0) as usize);
+        match (w, h, d, c) {
+            (0, ..) => None,
+            (w, 0, _, 0) => Some(Shape::new_1d(w)),
+            (w, h, _, 0) => Some(Shape::new_2d(w, h)),
+            (w, h, 0, c) => Some(Shape::new_3d(w, h, c)),
+            (w, h, d, c) => Some(Shape::new_4d(w, h, d, c)),
+        }
+    }
+}
+
+/// Written outermost first, like `3x224x224` for `c=3 h=224 w=224`.
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let extents: Vec<String> = self.extents().iter().map(|e| e.to_string()).collect();
+        write!(f, "{}", extents.join("x"))
+    }
+}
+
+/// Shapes from the `-23330` hints `ncnnoptimize` writes, `dims,w,h,c` per top.
+fn shape_hints(layer: &Layer) -> std::option::Option<Vec<Shape>> {
+    let hints = layer.params.get_ints(30)?;
+    let stride = if hints.len() == 5 * layer.tops.len() {
+        5
+    } else {
+        4
+    };
+    if hints.len() != stride * layer.tops.len() {
+        return None;
+    }
+    hints
+        .chunks(stride)
+        .map(|hint| {
+            let v = |i: usize| hint.get(i).map(|&v| v.max(0) as usize);
+            match hint[0] {
+                1 => Some(Shape::new_1d(v(1)?)),
+                2 => Some(Shape::new_2d(v(1)?, v(2)?)),
+                3 => Some(Shape::new_3d(v(1)?, v(2)?, v(3)?)),
+                4 => Some(Shape::new_4d(v(1)?, v(2)?, v(3)?, v(4)?)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Output extent of a sliding window, with ncnn's `-233`/`-234` meaning SAME padding.
+fn window_out(size: usize, kernel: i32, dilation: i32, stride: i32, pads: (i32, i32)) -> usize {
+    let size = size as i32;
+    let stride = stride.max(1);
+    if pads.0 == -233 || pads.0 == -234 {
+        return ((size + stride - 1) / stride).max(0) as usize;
+    }
+    let extent = dilation * (kernel - 1) + 1;
+    ((size + pads.0 + pads.1 - extent) / stride + 1).max(0) as usize
+}
+
+/// Kernel, dilation, stride and padding per axis of a `Convolution` style layer.
+struct Window {
+    kernel: (i32, i32),
+    dilation: (i32, i32),
+    stride: (i32, i32),
+    /// (left, right) and (top, bottom)
+    pad: ((i32, i32), (i32, i32)),
+}
+
+impl Window {
+    fn convolution(pd: &ParamDict) -> Window {
+        let kernel_w = pd.get_int(1, 0);
+        let dilation_w = pd.get_int(2, 1);
+        let stride_w = pd.get_int(3, 1);
+        let pad_left = pd.get_int(4, 0);
+        let pad_top = pd.get_int(14, pad_left);
+        Window {
+            kernel: (kernel_w, pd.get_int(11, kernel_w)),
+            dilation: (dilation_w, pd.get_int(12, dilation_w)),
+            stride: (stride_w, pd.get_int(13, stride_w)),
+            pad: (
+                (pad_left, pd.get_int(15, pad_left)),
+                (pad_top, pd.get_int(16, pad_top)),
+            ),
+        }
+    }
+}
+
+fn convolution(pd: &ParamDict, input: Shape) -> std::option::Option<Shape> {
+    if input.dims != 3 {
+        return None;
+    }
+    let win = Window::convolution(pd);
+    Some(Shape::new_3d(
+        window_out(
+            input.w,
+            win.kernel.0,
+            win.dilation.0,
+            win.stride.0,
+            win.pad.0,
+        ),
+        window_out(
+            input.h,
+            win.kernel.1,
+            win.dilation.1,
+            win.stride.1,
+            win.pad.1,
+        ),
+        pd.get_int(0, 0).max(0) as usize,
+    ))
+}
+
+fn deconvolution(pd: &ParamDict, input: Shape) -> std::option::Option<Shape> {
+    if input.dims != 3 {
+        return None;
+    }
+    let win = Window::convolution(pd);
+    let output_pad_right = pd.get_int(18, 0);
+    let output_pad_bottom = pd.get_int(19, output_pad_right);
+    let output_w = pd.get_int(20, 0);
+    let output_h = pd.get_int(21, output_w);
+    let out =
+        |size: usize, kernel: i32, dilation: i32, stride: i32, pad: (i32, i32), extra: i32| {
+            let full = (size as i32 - 1) * stride + dilation * (kernel - 1) + 1 + extra;
+            if pad.0 == -233 || pad.0 == -234 {
+                (size as i32 * stride).max(0) as usize
+            } else {
+                (full - pad.0 - pad.1).max(0) as usize
+            }
+        };
+    let mut w = out(
+        input.w,
+        win.kernel.0,
+        win.dilation.0,
+        win.stride.0,
+        win.pad.0,
+        output_pad_right,
+    );
+    let mut h = out(
+        input.h,
+        win.kernel.1,
+        win.dilation.1,
+        win.stride.1,
+        win.pad.1,
+        output_pad_bottom,
+    );
+    if output_w > 0 && output_h > 0 {
+        w = output_w as usize;
+        h = output_h as usize;
+    }
+    Some(Shape::new_3d(w, h, pd.get_int(0, 0).max(0) as usize))
+}
+
+fn pooling(pd: &ParamDict, input: Shape) -> std::option::Option<Shape> {
+    if input.dims != 3 {
+        return None;
+    }
+    if pd.get_int(4, 0) != 0 {
+        // global pooling
+        return Some(Shape::new_1d(input.c));
+    }
+    if pd.get_int(7, 0) != 0 {
+        // adaptive pooling
+        let out_w = pd.get_int(8, 0);
+        let out_h = pd.get_int(18, out_w);
+        let w = if out_w > 0 { out_w as usize } else { input.w };
+        let h = if out_h > 0 { out_h as usize } else { input.h };
+        return Some(Shape::new_3d(w, h, input.c));
+    }
+
+    let kernel_w = pd.get_int(1, 0);
+    let kernel_h = pd.get_int(11, kernel_w);
+    let stride_w = pd.get_int(2, 1).max(1);
+    let stride_h = pd.get_int(12, stride_w).max(1);
+    let pad_left = pd.get_int(3, 0);
+    let pad_right = pd.get_int(14, pad_left);
+    let pad_top = pd.get_int(13, pad_left);
+    let pad_bottom = pd.get_int(15, pad_top);
+    let pad_mode = pd.get_int(5, 0);
+
+    let padded = |size: usize, kernel: i32, stride: i32, pads: i32| {
+        let size = size as i32;
+        match pad_mode {
+            // full padding, rounding up like caffe
+            0 => {
+                let tail = (size + pads - kernel) % stride;
+                size + pads + if tail != 0 { stride - tail } else { 0 }
+            }
+            // SAME, upper or lower
+            2 | 3 => size + (kernel + (size - 1) / stride * stride - size).max(0),
+            // valid padding
+            _ => size + pads,
+        }
+    };
+    let w = padded(input.w, kernel_w, stride_w, pad_left + pad_right);
+    let h = padded(input.h, kernel_h, stride_h, pad_top + pad_bottom);
+    Some(Shape::new_3d(
+        ((w - kernel_w) / stride_w + 1).max(0) as usize,
+        ((h - kernel_h) / stride_h + 1).max(0) as usize,
+        input.c,
+    ))
+}
+
+fn inner_product(pd: &ParamDict, input: Shape) -> std::option::Option<Shape> {
+    let num_output = pd.get_int(0, 0).max(0) as usize;
+    let num_input = pd.get_int(2, 0).max(0) as usize / num_output.max(1);
+    // a 2D input whose rows match the weights is multiplied row by row
+    if input.dims == 2 && input.w == num_input {
+        Some(Shape::new_2d(num_output, input.h))
+    } else {
+        Some(Shape::new_1d(num_output))
+    }
+}
+
+/// Resolves a possibly negative `axis` against `dims`.
+fn resolve_axis(axis: i32, dims: usize) -> std::option::Option<usize> {
+    let axis = if axis < 0 { axis + dims as i32 } else { axis };
+    usize::try_from(axis).ok().filter(|&axis| axis < dims)
+}
+
+fn concat(pd: &ParamDict, inputs: &[Shape]) -> std::option::Option<Shape> {
+    let first = *inputs.first()?;
+    let axis = resolve_axis(pd.get_int(0, 0), first.dims)?;
+    let mut extents = first.extents();
+    for input in &inputs[1..] {
+        let other = input.extents();
+        if other.len() != extents.len() {
+            return None;
+        }
+        extents[axis] += other[axis];
+    }
+    Shape::from_extents(&extents)
+}
+
+fn slice(pd: &ParamDict, input: Shape, top_count: usize) -> std::option::Option<Vec<Shape>> {
+    let slices = pd.get_ints(0)?;
+    let axis = resolve_axis(pd.get_int(1, 0), input.dims)?;
+    let extents = input.extents();
+    let mut remaining = extents[axis];
+    let mut shapes = Vec::with_capacity(top_count);
+    for i in 0..top_count {
+        let slice = match slices.get(i) {
+            Some(&-233) | None => remaining / (top_count - i),
+            Some(&slice) => slice.max(0) as usize,
+        };
+        let slice = slice.min(remaining);
+        remaining -= slice;
+        let mut extents = extents.clone();
+        extents[axis] = slice;
+        shapes.push(Shape::from_extents(&extents)?);
+    }
+    Some(shapes)
+}
+
+fn crop(pd: &ParamDict, inputs: &[Shape]) -> std::option::Option<Shape> {
+    let input = *inputs.first()?;
+    let mut extents = input.extents();
+
+    if let Some(reference) = inputs.get(1) {
+        // crop to the size of the reference blob, innermost axes first
+        let reference = reference.extents();
+        let (n, m) = (extents.len(), reference.len());
+        for i in 0..n.min(m) {
+            extents[n - 1 - i] = reference[m - 1 - i];
+        }
+        return Shape::from_extents(&extents);
+    }
+
+    if let (Some(starts), Some(ends)) = (pd.get_ints(9), pd.get_ints(10)) {
+        // numpy style slicing
+        let axes = pd
+            .get_ints(11)
+            .unwrap_or_else(|| (0..starts.len() as i32).collect());
+        for ((&start, &end), &axis) in starts.iter().zip(&ends).zip(&axes) {
+            let axis = resolve_axis(axis, extents.len())?;
+            let size = extents[axis] as i64;
+            let resolve = |v: i32| {
+                let v = v as i64;
+                (if v < 0 { v + size } else { v }).clamp(0, size)
+            };
+            extents[axis] = (resolve(end) - resolve(start)).max(0) as usize;
+        }
+        return Shape::from_extents(&extents);
+    }
+
+    // offsets, output sizes and offsets from the far end, per w, h, d and c
+    let crop_axis = |size: usize, offset: i32, out: i32, offset2: i32| {
+        let size = size as i32;
+        let available = (size - offset - offset2).max(0);
+        let out = match out {
+            -233 => size - offset,
+            out if out > 0 => out.min(available),
+            _ => available,
+        };
+        out.max(0) as usize
+    };
+    let w = crop_axis(
+        input.w,
+        pd.get_int(0, 0),
+        pd.get_int(3, 0),
+        pd.get_int(6, 0),
+    );
+    let h = crop_axis(
+        input.h,
+        pd.get_int(1, 0),
+        pd.get_int(4, 0),
+        pd.get_int(7, 0),
+    );
+    let d = crop_axis(
+        input.d,
+        pd.get_int(13, 0),
+        pd.get_int(14, 0),
+        pd.get_int(15, 0),
+    );
+    let c = crop_axis(
+        input.c,
+        pd.get_int(2, 0),
+        pd.get_int(5, 0),
+        pd.get_int(8, 0),
+    );
+    Some(match input.dims {
+        1 => Shape::new_1d(w),
+        2 => Shape::new_2d(w, h),
+        3 => Shape::new_3d(w, h, c),
+        _ => Shape::new_4d(w, h, d, c),
+    })
+}
+
+fn interp(pd: &ParamDict, inputs: &[Shape]) -> std::option::Option<Shape> {
+    let input = *inputs.first()?;
+    let (mut out_w, mut out_h) = (pd.get_int(4, 0), pd.get_int(3, 0));
+    if let (Some(reference), true) = (inputs.get(1), pd.get_int(5, 0) != 0) {
+        out_w = reference.w as i32;
+        out_h = reference.h as i32;
+    }
+    let scaled = |size: usize, scale: f32| (size as f32 * scale) as usize;
+    let (w, h) = if out_w > 0 && out_h > 0 {
+        (out_w as usize, out_h as usize)
+    } else if out_w > 0 && input.dims == 2 {
+        (out_w as usize, input.h)
+    } else {
+        (
+            scaled(input.w, pd.get_float(2, 1.0)),
+            scaled(input.h, pd.get_float(1, 1.0)),
+        )
+    };
+    match input.dims {
+        // a vector is interpolated as c channels of 1x1
+        1 => Some(Shape::new_3d(w, h, input.w)),
+        2 => Some(Shape::new_2d(w, input.h)),
+        3 => Some(Shape::new_3d(w, h, input.c)),
+        _ => None,
+    }
+}
+
+fn reshape(pd: &ParamDict, input: Shape) -> std::option::Option<Shape> {
+    let w = pd.get_int(0, -233);
+    let h = pd.get_int(1, -233);
+    let d = pd.get_int(11, -233);
+    let c = pd.get_int(2, -233);
+    let dims = if w == -233 {
+        return None;
+    } else if h == -233 {
+        1
+    } else if c == -233 {
+        2
+    } else if d == -233 {
+        3
+    } else {
+        4
+    };
+
+    // 0 keeps the input extent, -1 takes whatever is left
+    let resolve = |v: i32, same: usize| if v == 0 { same as i64 } else { v as i64 };
+    let mut extents = vec![resolve(w, input.w)];
+    if dims >= 2 {
+        extents.push(resolve(h, input.h));
+    }
+    if dims >= 4 {
+        extents.push(resolve(d, input.d));
+    }
+    if dims >= 3 {
+        extents.push(resolve(c, input.c));
+    }
+    let known: i64 = extents.iter().filter(|&&e| e != -1).product();
+    if known <= 0 {
+        return None;
+    }
+    for extent in extents.iter_mut().filter(|e| **e == -1) {
+        *extent = input.elements() as i64 / known;
+    }
+    let e: Vec<usize> = extents.iter().map(|&e| e.max(0) as usize).collect();
+    Some(match dims {
+        1 => Shape::new_1d(e[0]),
+        2 => Shape::new_2d(e[0], e[1]),
+        3 => Shape::new_3d(e[0], e[1], e[2]),
+        _ => Shape::new_4d(e[0], e[1], e[2], e[3]),
+    })
+}
+
+fn permute(pd: &ParamDict, input: Shape) -> std::option::Option<Shape> {
+    // output extents as indices into the input extents, per order_type
+    const ORDERS_2D: [[usize; 2]; 2] = [[0, 1], [1, 0]];
+    const ORDERS_3D: [[usize; 3]; 6] = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+    let order_type = usize::try_from(pd.get_int(0, 0)).ok()?;
+    let extents = input.extents();
+    let order: &[usize] = match input.dims {
+        2 => ORDERS_2D.get(order_type)?,
+        3 => ORDERS_3D.get(order_type)?,
+        _ => return None,
+    };
+    Shape::from_extents(&order.iter().map(|&i| extents[i]).collect::<Vec<_>>())
+}
+
+fn padding(pd: &ParamDict, input: Shape) -> std::option::Option<Shape> {
+    let pad = |id| pd.get_int(id, 0).max(0) as usize;
+    let (top, bottom, left, right) = (pad(0), pad(1), pad(2), pad(3));
+    let (front, behind) = (pad(7), pad(8));
+    let w = input.w + left + right;
+    let h = input.h + top + bottom;
+    Some(match input.dims {
+        1 => Shape::new_1d(w),
+        2 => Shape::new_2d(w, h),
+        3 => Shape::new_3d(w, h, input.c + front + behind),
+        _ => Shape::new_4d(w, h, input.d + front + behind, input.c),
+    })
+}
+
+/// Element-wise layers, whose output has the shape of their first input.
+const SAME_SHAPE_LAYERS: [&str; 42] = [
+    "AbsVal",
+    "BatchNorm",
+    "Bias",
+    "BNLL",
+    "Cast",
+    "Clip",
+    "DeepCopy",
+    "Dequantize",
+    "Dropout",
+    "Eltwise",
+    "ELU",
+    "Exp",
+    "GELU",
+    "GroupNorm",
+    "HardSigmoid",
+    "HardSwish",
+    "InstanceNorm",
+    "LayerNorm",
+    "Log",
+    "LRN",
+    "Mish",
+    "MVN",
+    "Noop",
+    "Normalize",
+    "Packing",
+    "Power",
+    "PReLU",
+    "Quantize",
+    "ReLU",
+    "Requantize",
+    "Scale",
+    "SELU",
+    "ShuffleChannel",
+    "Sigmoid",
+    "Softmax",
+    "Softplus",
+    "Split",
+    "Swish",
+    "TanH",
+    "Threshold",
+    "UnaryOp",
+    "Erf",
+];
+
+/// Infers the shapes of a layer's tops from those of its bottoms, if the layer is known.
+fn infer_layer(layer: &Layer, inputs: &[Shape]) -> std::option::Option<Vec<Shape>> {
+    let pd = &layer.params;
+    let first = inputs.first().copied();
+    let one = |shape: std::option::Option<Shape>| shape.map(|shape| vec![shape]);
+    match layer.layer_type.as_str() {
+        "Input" => one(Shape::from_params(
+            pd.get_int(0, 0),
+            pd.get_int(1, 0),
+            pd.get_int(11, 0),
+            pd.get_int(2, 0),
+        )),
+        "MemoryData" => one(Shape::from_params(
+            pd.get_int(0, 0),
+            pd.get_int(1, 0),
+            pd.get_int(11, 0),
+            pd.get_int(2, 0),
+        )),
+        "Convolution" | "ConvolutionDepthWise" => one(convolution(pd, first?)),
+        "Deconvolution" | "DeconvolutionDepthWise" => one(deconvolution(pd, first?)),
+        "Pooling" => one(pooling(pd, first?)),
+        "InnerProduct" => one(inner_product(pd, first?)),
+        "Flatten" => one(Some(Shape::new_1d(first?.elements()))),
+        "Concat" => one(concat(pd, inputs)),
+        "Slice" => slice(pd, first?, layer.tops.len()),
+        "Crop" => one(crop(pd, inputs)),
+        "Interp" => one(interp(pd, inputs)),
+        "Reshape" => one(reshape(pd, first?)),
+        "Permute" => one(permute(pd, first?)),
+        "Padding" => one(padding(pd, first?)),
+        "BinaryOp" => {
+            // broadcasting: the output has the shape of the larger input
+            one(inputs
+                .iter()
+                .copied()
+                .max_by_key(|s| (s.dims, s.elements())))
+        }
+        t if SAME_SHAPE_LAYERS.contains(&t) => Some(vec![first?; layer.tops.len()]),
+        _ => None,
+    }
+}
+
+impl Graph {
+    /// Infers the shape of every blob, indexed like [Graph::blobs].
+    ///
+    /// Input shapes come from the `Input` layer parameters, or the `-23330` shape hints
+    /// where those are missing. Other layers are computed from their inputs where the layer
+    /// type is known, and fall back to their hints otherwise. Blobs whose shape cannot be
+    /// determined are `None`.
+    pub fn infer_shapes(&self) -> anyhow::Result<Vec<std::option::Option<Shape>>> {
+        self.infer_shapes_with(&HashMap::new())
+    }
+
+    /// Like [Graph::infer_shapes], with the shapes of some blobs, usually the inputs, given
+    /// by name. Given shapes take precedence over parameters and hints.
+    pub fn infer_shapes_with(
+        &self,
+        given: &HashMap<String, Shape>,
+    ) -> anyhow::Result<Vec<std::option::Option<Shape>>> {
+        let mut shapes = vec![None; self.blobs.len()];
+        for (name, &shape) in given {
+            let blob = self
+                .blob_index(name)
+                .ok_or_else(|| anyhow::anyhow!("No blob named `{}`", name))?;
+            shapes[blob] = Some(shape);
+        }
+
+        for index in self.topological_order()? {
+            let layer = &self.layers[index];
+            if layer.tops.iter().all(|&top| shapes[top].is_some()) {
+                continue;
+            }
+            let inputs: std::option::Option<Vec<Shape>> =
+                layer.bottoms.iter().map(|&bottom| shapes[bottom]).collect();
+            let tops = inputs
+                .and_then(|inputs| infer_layer(layer, &inputs))
+                .filter(|tops| tops.len() == layer.tops.len())
+                .or_else(|| shape_hints(layer));
+            if let Some(tops) = tops {
+                for (&top, shape) in layer.tops.iter().zip(tops) {
+                    shapes[top].get_or_insert(shape);
+                }
+            }
+        }
+        Ok(shapes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Checks inferred shapes against the hints `ncnnoptimize` wrote into shipped params.
+    #[test]
+    fn inferred_shapes_match_hints() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        for name in [
+            "squeezenet.param",
+            "mobilenet_v2.param",
+            "resnet50.param",
+            "shufflenet_v2.param",
+            "googlenet.param",
+            "mobilenet_ssd.param",
+            "mobilenetv2_yolov3.param",
+            "nanodet_m.param",
+        ] {
+            let graph = super::super::Graph::load(dir.join(name)).unwrap();
+            let mut stripped = graph.clone();
+            for layer in &mut stripped.layers {
+                if layer.layer_type != "Input" {
+                    layer.params.remove(30);
+                }
+            }
+            let shapes = stripped.infer_shapes().unwrap();
+            let mut checked = 0;
+            for layer in &graph.layers {
+                let hints = match shape_hints(layer) {
+                    Some(hints) => hints,
+                    None => continue,
+                };
+                for (&top, hint) in layer.tops.iter().zip(hints) {
+                    if let Some(shape) = shapes[top] {
+                        assert_eq!(shape, hint, "{} {}", name, layer.name);
+                        checked += 1;
+                    }
+                }
+            }
+            assert!(checked > 10, "{} checked only {} shapes", name, checked);
+        }
+
+        assert_eq!(Shape::new_3d(224, 224, 3).to_string(), "3x224x224");
+    }
+}
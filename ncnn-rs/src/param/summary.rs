@@ -0,0 +1,269 @@
+//! Per-layer cost estimates for picking a model for a device.
+use super::{Graph, Layer, Shape};
+use crate::modelbin;
+use std::fmt;
+use std::path::Path;
+
+/// Cost estimate of one layer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LayerSummary {
+    pub name: String,
+    pub layer_type: String,
+    /// Shapes of the layer's tops, `None` where they could not be inferred.
+    pub output_shapes: Vec<std::option::Option<Shape>>,
+    /// Multiply-accumulates of convolutions and matrix products.
+    pub macs: u64,
+    /// Floating point operations: two per MAC, plus one per output element for element-wise
+    /// layers and one per window element for pooling.
+    pub flops: u64,
+    /// Number of weights, biases and other per-channel constants stored in the `.bin`.
+    pub weights: u64,
+}
+
+/// Cost estimate of a whole model, see [Graph::summary].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    /// In `.param` order.
+    pub layers: Vec<LayerSummary>,
+    pub macs: u64,
+    pub flops: u64,
+    pub weights: u64,
+    /// Largest total size in bytes of fp32 blobs alive at once, running layers in
+    /// topological order and freeing each blob after its last consumer, like ncnn's
+    /// `lightmode`. `Split` tops share their bottom's memory, and blobs of unknown shape
+    /// count as empty.
+    pub peak_memory: u64,
+}
+
+/// Summarizes the model in a `.param` or `.param.bin` file, see [Graph::summary].
+pub fn model_summary<P: AsRef<Path>>(path: P) -> anyhow::Result<Summary> {
+    Graph::load(path)?.summary()
+}
+
+fn elements(shape: std::option::Option<Shape>) -> u64 {
+    shape.map_or(0, |shape| shape.elements() as u64)
+}
+
+/// Number of weights a layer loads from the `.bin`.
+pub(super) fn layer_weights(layer: &Layer) -> u64 {
+    modelbin::layout(layer)
+        .iter()
+        .map(|spec| spec.len as u64)
+        .sum()
+}
+
+/// Multiply-accumulates of a layer.
+fn layer_macs(
+    layer: &Layer,
+    input: std::option::Option<Shape>,
+    output: std::option::Option<Shape>,
+) -> u64 {
+    let pd = &layer.params;
+    let int = |id| pd.get_int(id, 0).max(0) as u64;
+    let spatial = |shape: std::option::Option<Shape>| shape.map_or(0, |s| (s.w * s.h) as u64);
+    match layer.layer_type.as_str() {
+        // every output position applies the whole kernel of its output channel
+        "Convolution" | "ConvolutionDepthWise" => spatial(output) * int(6),
+        // every input position scatters the whole kernel
+        "Deconvolution" | "DeconvolutionDepthWise" => spatial(input) * int(6),
+        "InnerProduct" => {
+            let rows = output.filter(|s| s.dims == 2).map_or(1, |s| s.h as u64);
+            rows * int(2)
+        }
+        _ => 0,
+    }
+}
+
+/// Non-MAC floating point operations of a layer.
+fn layer_flops(layer: &Layer, output: std::option::Option<Shape>) -> u64 {
+    let pd = &layer.params;
+    let out = elements(output);
+    match layer.layer_type.as_str() {
+        "Pooling" => {
+            let kernel_w = pd.get_int(1, 0).max(0) as u64;
+            let kernel_h = pd.get_int(11, kernel_w as i32).max(0) as u64;
+            out * (kernel_w * kernel_h).max(1)
+        }
+        "AbsVal" | "BatchNorm" | "Bias" | "BinaryOp" | "BNLL" | "Clip" | "Eltwise" | "ELU"
+        | "Exp" | "GELU" | "HardSigmoid" | "HardSwish" | "Log" | "Mish" | "Power" | "PReLU"
+        | "ReLU" | "Scale" | "SELU" | "Sigmoid" | "Softmax" | "Softplus" | "Swish" | "TanH"
+        | "UnaryOp" => out,
+        _ => 0,
+    }
+}
+
+/// Peak bytes of fp32 blobs alive at once, see [Summary::peak_memory].
+fn peak_memory(graph: &Graph, order: &[usize], shapes: &[std::option::Option<Shape>]) -> u64 {
+    let bytes = |blob: usize| elements(shapes[blob]) * 4;
+    // `Split` tops share the data of their bottom, so memory is counted per underlying blob
+    let mut storage: Vec<usize> = (0..graph.blobs.len()).collect();
+    for &index in order {
+        let layer = &graph.layers[index];
+        if let ("Split", [bottom]) = (layer.layer_type.as_str(), &layer.bottoms[..]) {
+            for &top in &layer.tops {
+                storage[top] = storage[*bottom];
+            }
+        }
+    }
+    // the step after which a blob can be freed, never for outputs
+    let mut last_use: Vec<std::option::Option<usize>> = vec![None; graph.blobs.len()];
+    let mut consumed = vec![false; graph.blobs.len()];
+    for (step, &index) in order.iter().enumerate() {
+        for &bottom in &graph.layers[index].bottoms {
+            last_use[storage[bottom]] = Some(step);
+            consumed[bottom] = true;
+        }
+    }
+    // an unconsumed alias is an output, which keeps the shared data alive
+    for blob in 0..graph.blobs.len() {
+        if storage[blob] != blob && !consumed[blob] {
+            last_use[storage[blob]] = None;
+        }
+    }
+
+    let (mut alive, mut peak) = (0, 0);
+    for (step, &index) in order.iter().enumerate() {
+        let layer = &graph.layers[index];
+        alive += layer
+            .tops
+            .iter()
+            .filter(|&&top| storage[top] == top)
+            .map(|&top| bytes(top))
+            .sum::<u64>();
+        peak = peak.max(alive);
+        for &bottom in &layer.bottoms {
+            // `take` so a blob used twice by the same layer, or through aliases, is freed once
+            let blob = storage[bottom];
+            if last_use[blob] == Some(step) {
+                last_use[blob].take();
+                alive -= bytes(blob);
+            }
+        }
+    }
+    peak
+}
+
+impl Graph {
+    /// Estimates per-layer output shapes, MACs, FLOPs and weight counts, and the peak
+    /// activation memory of a forward pass, from shapes given by [Graph::infer_shapes].
+    pub fn summary(&self) -> anyhow::Result<Summary> {
+        let shapes = self.infer_shapes()?;
+        let order = self.topological_order()?;
+
+        let layers: Vec<LayerSummary> = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let input = layer.bottoms.first().and_then(|&bottom| shapes[bottom]);
+                let output = layer.tops.first().and_then(|&top| shapes[top]);
+                let macs = layer_macs(layer, input, output);
+                LayerSummary {
+                    name: layer.name.clone(),
+                    layer_type: layer.layer_type.clone(),
+                    output_shapes: layer.tops.iter().map(|&top| shapes[top]).collect(),
+                    macs,
+                    flops: 2 * macs + layer_flops(layer, output),
+                    weights: layer_weights(layer),
+                }
+            })
+            .collect();
+
+        Ok(Summary {
+            macs: layers.iter().map(|layer| layer.macs).sum(),
+            flops: layers.iter().map(|layer| layer.flops).sum(),
+            weights: layers.iter().map(|layer| layer.weights).sum(),
+            peak_memory: peak_memory(self, &order, &shapes),
+            layers,
+        })
+    }
+}
+
+/// Formats a count with a metric suffix, like `1.23G`.
+struct Metric(u64);
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0 as f64;
+        match self.0 {
+            n if n >= 1_000_000_000 => write!(f, "{:.2}G", v / 1e9),
+            n if n >= 1_000_000 => write!(f, "{:.2}M", v / 1e6),
+            n if n >= 1_000 => write!(f, "{:.2}K", v / 1e3),
+            n => write!(f, "{}", n),
+        }
+    }
+}
+
+/// A table of layers followed by the totals.
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:<24} {:<20} {:>10} {:>10} {:>10}",
+            "type", "name", "output", "MACs", "FLOPs", "weights"
+        )?;
+        for layer in &self.layers {
+            let shapes: Vec<String> = layer
+                .output_shapes
+                .iter()
+                .map(|shape| shape.map_or_else(|| "?".to_string(), |shape| shape.to_string()))
+                .collect();
+            writeln!(
+                f,
+                "{:<24} {:<24} {:<20} {:>10} {:>10} {:>10}",
+                layer.layer_type,
+                layer.name,
+                shapes.join(","),
+                Metric(layer.macs).to_string(),
+                Metric(layer.flops).to_string(),
+                Metric(layer.weights).to_string()
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "MACs:        {}", Metric(self.macs))?;
+        writeln!(f, "FLOPs:       {}", Metric(self.flops))?;
+        writeln!(f, "weights:     {}", Metric(self.weights))?;
+        write!(f, "peak memory: {}B", Metric(self.peak_memory))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_alexnet() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        let summary = model_summary(dir.join("alexnet.param")).unwrap();
+
+        // the 8 weighted layers of the original two-group AlexNet
+        assert_eq!(summary.weights, 60_965_224);
+        let conv1 = &summary.layers[1];
+        assert_eq!(conv1.output_shapes, [Some(Shape::new_3d(55, 55, 96))]);
+        assert_eq!(conv1.macs, 55 * 55 * 34848);
+        assert_eq!(
+            summary.layers.iter().map(|l| l.macs).sum::<u64>(),
+            summary.macs
+        );
+        // data and conv1 are alive together while conv1 runs
+        assert!(summary.peak_memory >= (3 * 227 * 227 + 96 * 55 * 55) * 4);
+
+        // the Split tops are the input itself, alive alongside both ReLU outputs
+        let split = Graph::parse_text(
+            "7767517\n4 6\n\
+             Input data 0 1 data 0=10 1=10 2=1\n\
+             Split splitncnn_0 1 2 data data_0 data_1\n\
+             ReLU relu_a 1 1 data_0 a\n\
+             ReLU relu_b 1 1 data_1 b\n",
+        )
+        .unwrap();
+        assert_eq!(split.summary().unwrap().peak_memory, 3 * 100 * 4);
+
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let summary = model_summary(&path).unwrap();
+            assert!(summary.weights > 0, "{:?}", path);
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! The text `.param` format:
+//!
+//! ```text
+//! 7767517
+//! <layer count> <blob count>
+//! <type> <name> <bottom count> <top count> <bottoms...> <tops...> <id>=<value>...
+//! ```
+//!
+//! Array parameters are written `-233<id>=<len>,<v0>,<v1>...`. A value is a float if it
+//! contains `.` or `e`, an int otherwise, deciding each array element on its own.
+use super::{Blob, Graph, Layer, ParamDict, ParamValue, MAGIC, MAX_PARAM_COUNT};
+use anyhow::Context;
+use std::fmt;
+
+/// Array parameter ids are written as `-23300 - id`.
+const ARRAY_ID_BASE: i32 = -23300;
+
+fn is_float(value: &str) -> bool {
+    value.contains(['.', 'e', 'E'])
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, what: &str) -> anyhow::Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid {} `{}`", what, value))
+}
+
+pub(super) fn parse_param(token: &str) -> anyhow::Result<(i32, ParamValue)> {
+    let (id, value) = token
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected id=value, found `{}`", token))?;
+    let id: i32 = parse_number(id, "param id")?;
+
+    let (id, value) = if id <= ARRAY_ID_BASE {
+        let mut items = value.split(',');
+        let len: usize = parse_number(items.next().unwrap_or_default(), "array length")?;
+        let items: Vec<&str> = items.collect();
+        if items.len() != len {
+            anyhow::bail!(
+                "Array param {} declares {} values but has {}",
+                id,
+                len,
+                items.len()
+            );
+        }
+        let floats = items.iter().filter(|item| is_float(item)).count();
+        let value = if floats == 0 {
+            ParamValue::IntArray(
+                items
+                    .iter()
+                    .map(|item| parse_number(item, "int"))
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        } else if floats == items.len() {
+            ParamValue::FloatArray(
+                items
+                    .iter()
+                    .map(|item| parse_number(item, "float"))
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        } else {
+            // ncnn stores each element as it is written, keep the bits of both kinds
+            ParamValue::RawArray(
+                items
+                    .iter()
+                    .map(|item| {
+                        Ok(if is_float(item) {
+                            parse_number::<f32>(item, "float")?.to_bits()
+                        } else {
+                            parse_number::<i32>(item, "int")? as u32
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        };
+        (ARRAY_ID_BASE - id, value)
+    } else if is_float(value) {
+        (id, ParamValue::Float(parse_number(value, "float")?))
+    } else {
+        (id, ParamValue::Int(parse_number(value, "int")?))
+    };
+
+    if !(0..MAX_PARAM_COUNT).contains(&id) {
+        anyhow::bail!("Param id {} out of range 0..{}", id, MAX_PARAM_COUNT);
+    }
+    Ok((id, value))
+}
+
+/// Looks up a bottom blob, creating it if no layer has produced it yet, as ncnn does.
+fn bottom_blob(graph: &mut Graph, name: &str) -> usize {
+    graph.blob_index(name).unwrap_or_else(|| {
+        graph.blobs.push(Blob {
+            name: name.to_string(),
+        });
+        graph.blobs.len() - 1
+    })
+}
+
+fn parse_layer(graph: &mut Graph, line: &str) -> anyhow::Result<Layer> {
+    let mut tokens = line.split_whitespace();
+    let mut next = |what: &str| {
+        tokens
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Missing {}", what))
+    };
+    let layer_type = next("layer type")?.to_string();
+    let name = next("layer name")?.to_string();
+    let bottom_count: usize = parse_number(next("bottom count")?, "bottom count")?;
+    let top_count: usize = parse_number(next("top count")?, "top count")?;
+
+    let mut bottoms = Vec::with_capacity(bottom_count);
+    for _ in 0..bottom_count {
+        let blob = next("bottom blob")?;
+        bottoms.push(bottom_blob(graph, blob));
+    }
+    let mut tops = Vec::with_capacity(top_count);
+    for _ in 0..top_count {
+        let blob = next("top blob")?;
+        graph.blobs.push(Blob {
+            name: blob.to_string(),
+        });
+        tops.push(graph.blobs.len() - 1);
+    }
+
+    let mut params = ParamDict::new();
+    for token in tokens {
+        let (id, value) = parse_param(token)?;
+        if params.contains(id) {
+            anyhow::bail!("Param {} set twice", id);
+        }
+        params.set(id, value);
+    }
+
+    Ok(Layer {
+        layer_type,
+        name,
+        bottoms,
+        tops,
+        params,
+    })
+}
+
+pub(super) fn parse(param: &str) -> anyhow::Result<Graph> {
+    // (line number, line), skipping blank lines
+    let mut lines = param
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let (_, magic) = lines.next().context("Empty param")?;
+    if magic.trim_start_matches('\u{feff}') != MAGIC.to_string() {
+        anyhow::bail!("line 1: Expected magic {}, found `{}`", MAGIC, magic);
+    }
+    let (line_no, counts) = lines.next().context("Missing layer and blob counts")?;
+    let (layer_count, blob_count) = counts
+        .split_once(char::is_whitespace)
+        .and_then(|(layers, blobs)| Some((layers.parse().ok()?, blobs.trim().parse().ok()?)))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "line {}: Expected layer and blob counts, found `{}`",
+                line_no,
+                counts
+            )
+        })?;
+
+    let mut graph = Graph::default();
+    // like ncnn, read only the layers declared and ignore anything after them
+    for (line_no, line) in lines.take(layer_count) {
+        let layer = parse_layer(&mut graph, line).with_context(|| format!("line {}", line_no))?;
+        graph.layers.push(layer);
+        if graph.blobs.len() > blob_count {
+            anyhow::bail!(
+                "line {}: More blobs than the {} declared",
+                line_no,
+                blob_count
+            );
+        }
+    }
+    if graph.layers.len() < layer_count {
+        anyhow::bail!(
+            "{} layers declared, but only {} found",
+            layer_count,
+            graph.layers.len()
+        );
+    }
+    Ok(graph)
+}
+
+/// Formats a float so it reads back as the same float, and as a float.
+struct FloatParam(f32);
+
+impl fmt::Display for FloatParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `{:?}` is the shortest exact form and always has a `.` or `e`
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// Whether a raw `.param.bin` value is more likely an int than a float.
+///
+/// Small magnitudes are ints: as floats they would be denormal. Otherwise a value is a
+/// float if it is one of sensible magnitude.
+pub(super) fn raw_is_int(bits: u32) -> bool {
+    let f = f32::from_bits(bits);
+    let plausible_float = f.is_normal() && (1e-30..=1e30).contains(&f.abs());
+    (bits as i32).unsigned_abs() < 1 << 23 || !plausible_float
+}
+
+/// Whether a raw array mixes ints with floats, and is written element by element. Zero is
+/// the same either way, so a float array holding zeros is not mixed.
+pub(super) fn raw_is_mixed(values: &[u32]) -> bool {
+    values.iter().any(|&bits| raw_is_int(bits) && bits != 0)
+}
+
+pub(super) fn write_value(f: &mut fmt::Formatter<'_>, value: &ParamValue) -> fmt::Result {
+    let array = |f: &mut fmt::Formatter<'_>, items: Vec<String>| {
+        write!(f, "{}", items.len())?;
+        items.iter().try_for_each(|item| write!(f, ",{}", item))
+    };
+    match value {
+        ParamValue::Int(i) => write!(f, "{}", i),
+        ParamValue::Float(v) => write!(f, "{}", FloatParam(*v)),
+        ParamValue::Raw(bits) if raw_is_int(*bits) => write!(f, "{}", *bits as i32),
+        ParamValue::Raw(bits) => write!(f, "{}", FloatParam(f32::from_bits(*bits))),
+        ParamValue::IntArray(v) => array(f, v.iter().map(|i| i.to_string()).collect()),
+        ParamValue::FloatArray(v) => {
+            array(f, v.iter().map(|&v| FloatParam(v).to_string()).collect())
+        }
+        ParamValue::RawArray(v) if v.iter().all(|&bits| raw_is_int(bits)) => {
+            array(f, v.iter().map(|&bits| (bits as i32).to_string()).collect())
+        }
+        ParamValue::RawArray(v) if raw_is_mixed(v) => array(
+            f,
+            v.iter()
+                .map(|&bits| {
+                    if raw_is_int(bits) {
+                        (bits as i32).to_string()
+                    } else {
+                        FloatParam(f32::from_bits(bits)).to_string()
+                    }
+                })
+                .collect(),
+        ),
+        ParamValue::RawArray(v) => array(
+            f,
+            v.iter()
+                .map(|&bits| FloatParam(f32::from_bits(bits)).to_string())
+                .collect(),
+        ),
+    }
+}
+
+pub(super) fn write(graph: &Graph, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{}", MAGIC)?;
+    writeln!(f, "{} {}", graph.layers.len(), graph.blobs.len())?;
+    for layer in &graph.layers {
+        write!(
+            f,
+            "{:<24} {:<24} {} {}",
+            layer.layer_type,
+            layer.name,
+            layer.bottoms.len(),
+            layer.tops.len()
+        )?;
+        for &blob in layer.bottoms.iter().chain(&layer.tops) {
+            write!(f, " {}", graph.blobs[blob].name)?;
+        }
+        for (id, value) in layer.params.iter() {
+            let id = if value.is_array() {
+                ARRAY_ID_BASE - id
+            } else {
+                id
+            };
+            write!(f, " {}=", id)?;
+            write_value(f, value)?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
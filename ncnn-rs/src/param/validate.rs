@@ -0,0 +1,316 @@
+//! Checking a text `.param` for the mistakes ncnn rejects, or silently misreads, with the
+//! lines they are on.
+use super::{text, LAYER_TYPES, MAGIC};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Severity {
+    /// ncnn loads the file, but likely not as intended.
+    Warning,
+    /// ncnn fails to load the file, or the model fails to run.
+    Error,
+}
+
+/// A problem found by [validate].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostic {
+    /// 1-based line number, `None` for problems with the file as a whole.
+    pub line: std::option::Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Formatted like `line 3: error: Unknown layer type `Foo``.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+/// Checks a text `.param` against the built-in layer types in [LAYER_TYPES], see
+/// [validate_with].
+pub fn validate(param: &str) -> Vec<Diagnostic> {
+    validate_with(param, |layer_type| LAYER_TYPES.contains(&layer_type))
+}
+
+/// Checks a text `.param`, with `known_type` telling which layer types exist.
+///
+/// Unlike [super::Graph::parse_text], this does not stop at the first problem. It reports
+/// a wrong magic, layer and blob counts that do not match the file, malformed layer lines
+/// and parameters, unknown layer types, bottom blobs no earlier layer produces, and top
+/// blobs produced twice.
+pub fn validate_with<F: Fn(&str) -> bool>(param: &str, known_type: F) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut error = |line, message: String| {
+        diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Error,
+            message,
+        })
+    };
+
+    // (line number, line), skipping blank lines like the parser
+    let mut lines = param
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let Some((line_no, magic)) = lines.next() else {
+        error(None, "Empty param".to_string());
+        return diagnostics;
+    };
+    if magic.trim_start_matches('\u{feff}') != MAGIC.to_string() {
+        error(
+            Some(line_no),
+            format!(
+                "Expected magic {}, found `{}`; is this a .param.bin?",
+                MAGIC, magic
+            ),
+        );
+        return diagnostics;
+    }
+    let Some((line_no, counts)) = lines.next() else {
+        error(None, "Missing layer and blob counts".to_string());
+        return diagnostics;
+    };
+    let counts: Vec<&str> = counts.split_whitespace().collect();
+    let (layer_count, blob_count) = match counts[..] {
+        [layers, blobs] => match (layers.parse::<usize>(), blobs.parse::<usize>()) {
+            (Ok(layers), Ok(blobs)) => (layers, blobs),
+            _ => {
+                error(
+                    Some(line_no),
+                    format!("Invalid layer and blob counts `{}`", counts.join(" ")),
+                );
+                return diagnostics;
+            }
+        },
+        _ => {
+            error(
+                Some(line_no),
+                format!(
+                    "Expected a layer count and a blob count, found `{}`",
+                    counts.join(" ")
+                ),
+            );
+            return diagnostics;
+        }
+    };
+
+    let mut checker = Checker {
+        known_type: &known_type,
+        diagnostics,
+        layer_names: HashMap::new(),
+        producers: HashMap::new(),
+        consumed_early: HashSet::new(),
+        blob_count: 0,
+    };
+    let (mut layers, mut too_many_blobs) = (0, false);
+    for (line_no, line) in lines {
+        if layers == layer_count {
+            checker.warning(
+                Some(line_no),
+                format!(
+                    "Lines from here on are ignored, as only {} layers are declared",
+                    layer_count
+                ),
+            );
+            break;
+        }
+        layers += 1;
+        checker.check_layer(line_no, line);
+        if checker.blob_count > blob_count && !too_many_blobs {
+            too_many_blobs = true;
+            checker.error(
+                Some(line_no),
+                format!("More blobs than the {} declared", blob_count),
+            );
+        }
+    }
+
+    if layers < layer_count {
+        checker.error(
+            None,
+            format!("{} layers declared, but only {} found", layer_count, layers),
+        );
+    } else if checker.blob_count < blob_count {
+        checker.warning(
+            None,
+            format!(
+                "{} blobs declared, but only {} used",
+                blob_count, checker.blob_count
+            ),
+        );
+    }
+    checker.diagnostics
+}
+
+/// State carried across the layer lines.
+struct Checker<'a, F> {
+    known_type: &'a F,
+    diagnostics: Vec<Diagnostic>,
+    /// Layer name to line number.
+    layer_names: HashMap<String, usize>,
+    /// Blob name to the line of the layer producing it.
+    producers: HashMap<String, usize>,
+    /// Blobs consumed before any layer produced them.
+    consumed_early: HashSet<String>,
+    /// Blobs ncnn creates: one per top, and one per bottom it cannot find.
+    blob_count: usize,
+}
+
+impl<F: Fn(&str) -> bool> Checker<'_, F> {
+    fn error(&mut self, line: std::option::Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Error,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: std::option::Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Warning,
+            message,
+        });
+    }
+
+    fn check_layer(&mut self, line_no: usize, line: &str) {
+        let at = Some(line_no);
+        let mut tokens = line.split_whitespace();
+        let (Some(layer_type), Some(name)) = (tokens.next(), tokens.next()) else {
+            self.error(
+                at,
+                "Expected `type name bottom_count top_count`".to_string(),
+            );
+            return;
+        };
+        if !(self.known_type)(layer_type) {
+            self.error(at, format!("Unknown layer type `{}`", layer_type));
+        }
+        if let Some(&first) = self.layer_names.get(name) {
+            self.warning(
+                at,
+                format!(
+                    "Layer name `{}` already used on line {}, lookups by name find that one",
+                    name, first
+                ),
+            );
+        } else {
+            self.layer_names.insert(name.to_string(), line_no);
+        }
+
+        let counts = (tokens.next(), tokens.next());
+        let (Some(Ok(bottom_count)), Some(Ok(top_count))) = (
+            counts.0.map(str::parse::<usize>),
+            counts.1.map(str::parse::<usize>),
+        ) else {
+            self.error(at, "Missing or invalid bottom and top counts".to_string());
+            return;
+        };
+
+        for _ in 0..bottom_count {
+            let Some(bottom) = tokens.next() else {
+                self.error(at, format!("Expected {} bottom blobs", bottom_count));
+                return;
+            };
+            if !self.producers.contains_key(bottom)
+                && self.consumed_early.insert(bottom.to_string())
+            {
+                self.blob_count += 1;
+                self.error(
+                    at,
+                    format!(
+                        "Bottom blob `{}` is not produced by an earlier layer",
+                        bottom
+                    ),
+                );
+            }
+        }
+        for _ in 0..top_count {
+            let Some(top) = tokens.next() else {
+                self.error(at, format!("Expected {} top blobs", top_count));
+                return;
+            };
+            self.blob_count += 1;
+            if let Some(&first) = self.producers.get(top) {
+                self.error(
+                    at,
+                    format!("Top blob `{}` already produced on line {}", top, first),
+                );
+            } else {
+                self.producers.insert(top.to_string(), line_no);
+            }
+        }
+
+        let mut ids = HashSet::new();
+        for token in tokens {
+            match text::parse_param(token) {
+                Ok((id, _)) if !ids.insert(id) => {
+                    self.error(at, format!("Param {} set twice", id));
+                }
+                Ok(_) => {}
+                Err(e) => self.error(at, e.to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn validate_params() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let diagnostics = validate(&std::fs::read_to_string(&path).unwrap());
+            let errors: Vec<_> = diagnostics
+                .iter()
+                .filter(|d| d.severity == Severity::Error)
+                .collect();
+            assert!(errors.is_empty(), "{:?}: {:?}", path, errors);
+        }
+        // declares fewer layers than it has
+        let nanodet = std::fs::read_to_string(dir.join("nanodet_m.param")).unwrap();
+        let diagnostics = validate(&nanodet);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+
+        let param = "7767517
+5 5
+Input            data   0 1 data 0=4
+Convolution      conv   1 1 dat conv 0=8 1=x
+Foo              foo    1 1 conv conv
+ReLU             relu   1 1 conv out 2=1.0 -23300=3,1,2
+Softmax          prob   1 1 out prob
+Noop             extra  1 1 prob extra";
+        let messages: Vec<String> = validate(param).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 4: error: Bottom blob `dat` is not produced by an earlier layer",
+                "line 4: error: Invalid int `x`",
+                "line 5: error: Unknown layer type `Foo`",
+                "line 5: error: Top blob `conv` already produced on line 4",
+                "line 6: error: Array param -23300 declares 3 values but has 2",
+                "line 7: error: More blobs than the 5 declared",
+                "line 8: warning: Lines from here on are ignored, as only 5 layers are declared",
+            ]
+        );
+    }
+}
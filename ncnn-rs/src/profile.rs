@@ -0,0 +1,243 @@
+//! Per-layer timing of a loaded net, without an ncnn built with `NCNN_BENCHMARK`.
+//!
+//! An extractor computes only what a blob needs and keeps what it computed, so extracting
+//! the tops of each layer in topological order runs one more layer per extract, which
+//! [Net::profile] times. Each extract also converts its output for the caller, so layers
+//! look slower than they are within a whole run; compare layers with each other, and the
+//! total with `ncnn-bench`.
+use crate::param::{Graph, Shape};
+use crate::{Mat, Net};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Time and outputs of one layer, see [Net::profile].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LayerProfile {
+    pub name: String,
+    pub layer_type: String,
+    /// Mean time per run.
+    pub duration: Duration,
+    /// Shapes of the layer's tops, as extracted.
+    pub output_shapes: Vec<Shape>,
+}
+
+/// Time of all layers of a type.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TypeProfile {
+    pub layer_type: String,
+    /// Mean time per run of all layers of the type together.
+    pub duration: Duration,
+    pub layers: usize,
+}
+
+/// Per-layer times of a net, see [Net::profile].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Profile {
+    /// In the order the layers ran.
+    pub layers: Vec<LayerProfile>,
+    /// Mean time per run of all layers.
+    pub total: Duration,
+    /// Number of runs averaged over.
+    pub runs: usize,
+}
+
+impl Profile {
+    /// Layers from slowest to fastest.
+    pub fn slowest(&self) -> Vec<&LayerProfile> {
+        let mut layers: Vec<&LayerProfile> = self.layers.iter().collect();
+        layers.sort_by_key(|layer| std::cmp::Reverse(layer.duration));
+        layers
+    }
+
+    /// Times per layer type, from slowest to fastest.
+    pub fn by_type(&self) -> Vec<TypeProfile> {
+        let mut types: Vec<TypeProfile> = Vec::new();
+        for layer in &self.layers {
+            match types.iter_mut().find(|t| t.layer_type == layer.layer_type) {
+                Some(t) => {
+                    t.duration += layer.duration;
+                    t.layers += 1;
+                }
+                None => types.push(TypeProfile {
+                    layer_type: layer.layer_type.clone(),
+                    duration: layer.duration,
+                    layers: 1,
+                }),
+            }
+        }
+        types.sort_by_key(|t| std::cmp::Reverse(t.duration));
+        types
+    }
+
+    /// Share of the total time, in percent.
+    fn percent(&self, duration: Duration) -> f64 {
+        if self.total.is_zero() {
+            0.0
+        } else {
+            duration.as_secs_f64() / self.total.as_secs_f64() * 100.0
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Layers from slowest to fastest, then layer types from slowest to fastest.
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:<24} {:<20} {:>10} {:>7}",
+            "type", "name", "output", "ms", "%"
+        )?;
+        for layer in self.slowest() {
+            let shapes: Vec<String> = layer.output_shapes.iter().map(Shape::to_string).collect();
+            writeln!(
+                f,
+                "{:<24} {:<24} {:<20} {:>10.3} {:>6.1}%",
+                layer.layer_type,
+                layer.name,
+                shapes.join(","),
+                millis(layer.duration),
+                self.percent(layer.duration)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{:<24} {:>7} {:>10} {:>7}", "type", "layers", "ms", "%")?;
+        for t in self.by_type() {
+            writeln!(
+                f,
+                "{:<24} {:>7} {:>10.3} {:>6.1}%",
+                t.layer_type,
+                t.layers,
+                millis(t.duration),
+                self.percent(t.duration)
+            )?;
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            "total: {:.3} ms, mean of {} runs",
+            millis(self.total),
+            self.runs
+        )
+    }
+}
+
+impl Net {
+    /// Times every layer of the net, which was loaded from `graph`, over `runs` runs on
+    /// `inputs`, after a warm-up run.
+    ///
+    /// Layers run one at a time, extracting the tops of each in topological order, so every
+    /// extract runs just that layer. The times include converting each output to the
+    /// layout extracts return, so they add up to more than running the net in one go.
+    /// An ncnn built with `NCNN_BENCHMARK` also prints its own per-layer times to stderr,
+    /// which this does not need or read.
+    pub fn profile(
+        &mut self,
+        graph: &Graph,
+        inputs: &[(&str, &Mat)],
+        runs: usize,
+    ) -> anyhow::Result<Profile> {
+        if runs == 0 {
+            anyhow::bail!("Profiling needs at least one run");
+        }
+        let order = graph.topological_order()?;
+        let fed: HashSet<&str> = inputs.iter().map(|&(name, _)| name).collect();
+        let timed: Vec<usize> = order
+            .into_iter()
+            .filter(|&i| {
+                let layer = &graph.layers[i];
+                layer.layer_type != "Input"
+                    && !layer
+                        .tops
+                        .iter()
+                        .all(|&top| fed.contains(graph.blobs[top].name.as_str()))
+            })
+            .collect();
+
+        let mut durations = vec![Duration::ZERO; timed.len()];
+        let mut shapes = vec![Vec::new(); timed.len()];
+        for run in 0..=runs {
+            let mut ex = self.create_extractor();
+            for &(name, mat) in inputs {
+                ex.input(name, mat)?;
+            }
+            for (n, &i) in timed.iter().enumerate() {
+                let layer = &graph.layers[i];
+                let mut outputs = Vec::with_capacity(layer.tops.len());
+                let start = Instant::now();
+                for &top in &layer.tops {
+                    let mut mat = Mat::new();
+                    ex.extract(&graph.blobs[top].name, &mut mat)?;
+                    outputs.push(mat);
+                }
+                let elapsed = start.elapsed();
+                if run == 0 {
+                    shapes[n] = outputs.iter().map(Mat::shape).collect();
+                } else {
+                    durations[n] += elapsed;
+                }
+            }
+        }
+
+        let layers: Vec<LayerProfile> = timed
+            .iter()
+            .zip(durations)
+            .zip(shapes)
+            .map(|((&i, duration), output_shapes)| LayerProfile {
+                name: graph.layers[i].name.clone(),
+                layer_type: graph.layers[i].layer_type.clone(),
+                duration: duration / runs as u32,
+                output_shapes,
+            })
+            .collect();
+        Ok(Profile {
+            total: layers.iter().map(|layer| layer.duration).sum(),
+            layers,
+            runs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_table() {
+        let layer = |name: &str, layer_type: &str, micros| LayerProfile {
+            name: name.to_string(),
+            layer_type: layer_type.to_string(),
+            duration: Duration::from_micros(micros),
+            output_shapes: vec![Shape::new_3d(8, 8, 16)],
+        };
+        let profile = Profile {
+            layers: vec![
+                layer("conv1", "Convolution", 300),
+                layer("relu1", "ReLU", 100),
+                layer("conv2", "Convolution", 500),
+                layer("pool", "Pooling", 100),
+            ],
+            total: Duration::from_micros(1000),
+            runs: 10,
+        };
+        let slowest: Vec<&str> = profile.slowest().iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(slowest[..2], ["conv2", "conv1"]);
+        let by_type = profile.by_type();
+        assert_eq!(by_type[0].layer_type, "Convolution");
+        assert_eq!(by_type[0].duration, Duration::from_micros(800));
+        assert_eq!(by_type[0].layers, 2);
+
+        let table = profile.to_string();
+        let first = table.lines().nth(1).unwrap();
+        assert!(first.starts_with("Convolution") && first.contains("conv2"));
+        assert!(first.ends_with("0.500   50.0%"), "{}", first);
+        assert!(table.ends_with("total: 1.000 ms, mean of 10 runs"));
+    }
+}
@@ -0,0 +1,468 @@
+//! Post-training int8 quantization, like ncnn's `ncnn2table` and `ncnn2int8`.
+//!
+//! [calibrate] runs a float model over calibration inputs and picks a scale for the input
+//! of every convolution and inner product, giving a [CalibrationTable]. [quantize] then
+//! stores the weights of those layers as int8, for ncnn to run them in int8:
+//!
+//! ```no_run
+//! use ncnn_rs::param::Model;
+//! use ncnn_rs::quantize::{self, Method};
+//! use ncnn_rs::{Mat, Net};
+//!
+//! let model = Model::load("squeezenet.param", "squeezenet.bin")?;
+//! let mut net = Net::new();
+//! model.load_into(&mut net)?;
+//! let table = quantize::calibrate(&mut net, &model, Method::Kl, 100, |_| {
+//!     let mut data = Mat::new_3d(227, 227, 3, None);
+//!     data.fill(0.5);
+//!     Ok(vec![("data".to_string(), data)])
+//! })?;
+//! table.save("squeezenet.table")?;
+//! quantize::quantize(&model, &table)?.save("squeezenet_int8.param", "squeezenet_int8.bin")?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use crate::modelbin::{self, WeightBlob, Weights};
+use crate::param::{Graph, Layer, Model, ParamValue};
+use crate::{Mat, Net};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Layers ncnn runs in int8 given scales.
+const QUANTIZABLE: [&str; 3] = ["Convolution", "ConvolutionDepthWise", "InnerProduct"];
+
+/// Suffix of the weight scale entries of a [CalibrationTable], after the layer name.
+const WEIGHT_SUFFIX: &str = "_param_0";
+
+/// Histogram resolution of [Method::Kl], and the number of levels it quantizes to.
+const HISTOGRAM_BINS: usize = 2048;
+const TARGET_BINS: usize = 128;
+
+/// How [calibrate] picks the range of activations to map to int8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// The range whose quantized histogram is closest to the original by KL divergence,
+    /// which needs a second pass over the inputs.
+    Kl,
+    /// The range minimizing quantization error if activations were Gaussian.
+    Aciq,
+    /// The largest absolute value seen.
+    MinMax,
+}
+
+impl FromStr for Method {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Method> {
+        match s {
+            "kl" => Ok(Method::Kl),
+            "aciq" => Ok(Method::Aciq),
+            "minmax" => Ok(Method::MinMax),
+            _ => anyhow::bail!("Unknown method `{}`, expected kl, aciq or minmax", s),
+        }
+    }
+}
+
+/// Scales of the layers to run in int8, in the `.table` format of `ncnn2table`.
+///
+/// Values are multiplied by the scales to quantize them, so a scale is 127 over the largest
+/// value to keep.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalibrationTable {
+    /// Layer name to the scales of its weights, per output channel or group.
+    pub weight_scales: Vec<(String, Vec<f32>)>,
+    /// Layer name to the scale of its input.
+    pub bottom_scales: Vec<(String, f32)>,
+}
+
+impl CalibrationTable {
+    pub fn weight_scales(&self, layer: &str) -> std::option::Option<&[f32]> {
+        self.weight_scales
+            .iter()
+            .find(|(name, _)| name == layer)
+            .map(|(_, scales)| scales.as_slice())
+    }
+
+    pub fn bottom_scale(&self, layer: &str) -> std::option::Option<f32> {
+        self.bottom_scales
+            .iter()
+            .find(|(name, _)| name == layer)
+            .map(|&(_, scale)| scale)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<CalibrationTable> {
+        let path = path.as_ref();
+        let table = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path.display(), e))?;
+        table
+            .parse()
+            .map_err(|e: anyhow::Error| e.context(format!("Error parsing {}", path.display())))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string())
+            .map_err(|e| anyhow::anyhow!("Error writing {}: {}", path.display(), e))
+    }
+}
+
+/// Parses lines of a layer name and its scales, weight scales named `{layer}_param_0`.
+impl FromStr for CalibrationTable {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<CalibrationTable> {
+        let mut table = CalibrationTable::default();
+        for (i, line) in s.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let Some(name) = tokens.next() else {
+                continue;
+            };
+            let scales = tokens
+                .map(str::parse::<f32>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e))?;
+            match (name.strip_suffix(WEIGHT_SUFFIX), &scales[..]) {
+                (_, []) => anyhow::bail!("line {}: No scales for `{}`", i + 1, name),
+                (Some(layer), _) => table.weight_scales.push((layer.to_string(), scales)),
+                (None, &[scale]) => table.bottom_scales.push((name.to_string(), scale)),
+                (None, _) => anyhow::bail!("line {}: More than one scale for `{}`", i + 1, name),
+            }
+        }
+        Ok(table)
+    }
+}
+
+impl fmt::Display for CalibrationTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (layer, scales) in &self.weight_scales {
+            write!(f, "{}{}", layer, WEIGHT_SUFFIX)?;
+            for scale in scales {
+                write!(f, " {}", scale)?;
+            }
+            writeln!(f)?;
+        }
+        for (layer, scale) in &self.bottom_scales {
+            writeln!(f, "{} {}", layer, scale)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether ncnn can run `layer` in int8, and it is not already.
+fn quantizable(layer: &Layer) -> bool {
+    QUANTIZABLE.contains(&layer.layer_type.as_str())
+        && layer.bottoms.len() == 1
+        && layer.params.get_int(8, 0) == 0
+}
+
+/// Number of weight scales of a quantizable layer: one per group for depthwise convolutions,
+/// one per output channel otherwise.
+fn scale_count(layer: &Layer) -> usize {
+    match layer.layer_type.as_str() {
+        "ConvolutionDepthWise" => layer.params.get_int(7, 1),
+        _ => layer.params.get_int(0, 0),
+    }
+    .max(1) as usize
+}
+
+/// 127 over the largest absolute weight of every output channel or group.
+fn weight_scales(layer: &Layer, weight: &[f32]) -> Vec<f32> {
+    let count = scale_count(layer);
+    weight
+        .chunks(weight.len().div_ceil(count).max(1))
+        .map(|chunk| {
+            let absmax = chunk.iter().fold(0.0f32, |max, value| max.max(value.abs()));
+            if absmax == 0.0 {
+                1.0
+            } else {
+                127.0 / absmax
+            }
+        })
+        .collect()
+}
+
+/// Runs the net on one set of inputs, passing the values of every blob in `blobs` to `f`.
+fn run(
+    net: &mut Net,
+    inputs: &[(String, Mat)],
+    blobs: &[String],
+    mut f: impl FnMut(usize, &[f32]),
+) -> anyhow::Result<()> {
+    let mut ex = net.create_extractor();
+    for (name, mat) in inputs {
+        ex.input(name, mat)?;
+    }
+    for (i, blob) in blobs.iter().enumerate() {
+        let mut mat = Mat::new();
+        ex.extract(blob, &mut mat)?;
+        let values = mat
+            .to_vec()
+            .map_err(|e| e.context(format!("Error reading blob `{}`", blob)))?;
+        f(i, &values);
+    }
+    Ok(())
+}
+
+/// Activation statistics of a blob.
+#[derive(Debug, Clone, Default)]
+struct BlobStats {
+    absmax: f32,
+    count: usize,
+    /// Counts of absolute values in [HISTOGRAM_BINS] bins over `0..absmax`, zeros left out.
+    histogram: Vec<u64>,
+}
+
+/// Calibrates `model`, loaded in `net`, over `samples` sets of inputs, `inputs(i)` giving the
+/// `(input name, data)` pairs of the `i`th.
+///
+/// [Method::Kl] asks for every sample twice.
+pub fn calibrate<F>(
+    net: &mut Net,
+    model: &Model,
+    method: Method,
+    samples: usize,
+    mut inputs: F,
+) -> anyhow::Result<CalibrationTable>
+where
+    F: FnMut(usize) -> anyhow::Result<Vec<(String, Mat)>>,
+{
+    let graph = &model.graph;
+    let weights = modelbin::read_f32(graph, &model.weights)?;
+    let layers: Vec<usize> = (0..graph.layers.len())
+        .filter(|&i| quantizable(&graph.layers[i]))
+        .collect();
+    let blobs: Vec<String> = layers
+        .iter()
+        .map(|&i| graph.blobs[graph.layers[i].bottoms[0]].name.clone())
+        .collect();
+    if samples == 0 {
+        anyhow::bail!("No calibration samples");
+    }
+
+    let mut stats = vec![BlobStats::default(); blobs.len()];
+    for sample in 0..samples {
+        run(net, &inputs(sample)?, &blobs, |i, values| {
+            for &value in values {
+                stats[i].absmax = stats[i].absmax.max(value.abs());
+                stats[i].count += 1;
+            }
+        })?;
+    }
+    if method == Method::Kl {
+        for stats in &mut stats {
+            stats.histogram = vec![0; HISTOGRAM_BINS];
+        }
+        for sample in 0..samples {
+            run(net, &inputs(sample)?, &blobs, |i, values| {
+                let stats = &mut stats[i];
+                let interval = stats.absmax / HISTOGRAM_BINS as f32;
+                for &value in values.iter().filter(|&&value| value != 0.0) {
+                    let bin = ((value.abs() / interval) as usize).min(HISTOGRAM_BINS - 1);
+                    stats.histogram[bin] += 1;
+                }
+            })?;
+        }
+    }
+
+    let mut table = CalibrationTable::default();
+    for (&i, stats) in layers.iter().zip(&stats) {
+        let layer = &graph.layers[i];
+        table
+            .weight_scales
+            .push((layer.name.clone(), weight_scales(layer, &weights[i][0])));
+        let threshold = match method {
+            Method::Kl => kl_threshold(&stats.histogram, stats.absmax),
+            Method::Aciq => aciq_threshold(stats.absmax, stats.count),
+            Method::MinMax => stats.absmax,
+        };
+        let scale = if threshold == 0.0 {
+            1.0
+        } else {
+            127.0 / threshold
+        };
+        table.bottom_scales.push((layer.name.clone(), scale));
+    }
+    Ok(table)
+}
+
+/// The threshold over `0..absmax` whose quantization to [TARGET_BINS] levels loses the least
+/// information from `histogram`, measured by KL divergence, as TensorRT calibrates.
+fn kl_threshold(histogram: &[u64], absmax: f32) -> f32 {
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return absmax;
+    }
+    let p: Vec<f64> = histogram
+        .iter()
+        .map(|&count| count as f64 / total as f64)
+        .collect();
+
+    let (mut best, mut best_divergence) = (HISTOGRAM_BINS, f64::MAX);
+    for threshold in TARGET_BINS..HISTOGRAM_BINS {
+        // everything beyond clips to the last bin
+        let mut clipped = p[..threshold].to_vec();
+        clipped[threshold - 1] += p[threshold..].iter().sum::<f64>();
+
+        // merge into the target bins, then spread each back evenly over the bins it
+        // covers that had values
+        let mut expanded = vec![0.0; threshold];
+        let per_bin = threshold as f64 / TARGET_BINS as f64;
+        for j in 0..TARGET_BINS {
+            let (start, end) = (j as f64 * per_bin, (j + 1) as f64 * per_bin);
+            let bins = start.floor() as usize..(end.ceil() as usize).min(threshold);
+            let coverage = |k: usize| end.min(k as f64 + 1.0) - start.max(k as f64);
+            let (mut mass, mut used) = (0.0, 0.0);
+            for k in bins.clone() {
+                mass += clipped[k] * coverage(k);
+                if clipped[k] != 0.0 {
+                    used += coverage(k);
+                }
+            }
+            if used == 0.0 {
+                continue;
+            }
+            for k in bins.filter(|&k| clipped[k] != 0.0) {
+                expanded[k] += mass / used * coverage(k);
+            }
+        }
+
+        let divergence: f64 = clipped
+            .iter()
+            .zip(&expanded)
+            .filter(|(&p, _)| p != 0.0)
+            .map(|(&p, &q)| if q == 0.0 { 1e-4 } else { p * (p / q).ln() })
+            .sum();
+        if divergence < best_divergence {
+            (best, best_divergence) = (threshold, divergence);
+        }
+    }
+    (best as f32 + 0.5) * absmax / HISTOGRAM_BINS as f32
+}
+
+/// The clipping threshold minimizing the mean squared error of 8-bit quantization, for a
+/// Gaussian whose `count` samples span `absmax`, after Banner et al., "ACIQ".
+fn aciq_threshold(absmax: f32, count: usize) -> f32 {
+    const ALPHA_8BIT: f64 = 3.924_037_14;
+    if count < 2 {
+        return absmax;
+    }
+    let gaussian_const = 0.5 * 0.35 * (1.0 + (std::f64::consts::PI * 4f64.ln()).sqrt());
+    let std = absmax as f64 * 2.0 * gaussian_const / (2.0 * (count as f64).ln()).sqrt();
+    (ALPHA_8BIT * std).min(absmax as f64) as f32
+}
+
+/// Stores the weights of the layers in `table` as int8, with the scales ncnn needs to run
+/// them in int8. Other layers are left as they are.
+pub fn quantize(model: &Model, table: &CalibrationTable) -> anyhow::Result<Model> {
+    let mut graph: Graph = model.graph.clone();
+    let mut weights = modelbin::read(&graph, &model.weights)?;
+    for (layer, blobs) in graph.layers.iter_mut().zip(&mut weights) {
+        if !quantizable(layer) {
+            continue;
+        }
+        let (Some(scales), Some(bottom_scale)) = (
+            table.weight_scales(&layer.name),
+            table.bottom_scale(&layer.name),
+        ) else {
+            continue;
+        };
+        if scales.len() != scale_count(layer) {
+            anyhow::bail!(
+                "Layer `{}` needs {} weight scales, the table has {}",
+                layer.name,
+                scale_count(layer),
+                scales.len()
+            );
+        }
+
+        let weight = blobs[0].weights.to_f32();
+        let per_scale = weight.len().div_ceil(scales.len()).max(1);
+        let quantized = weight
+            .chunks(per_scale)
+            .zip(scales)
+            .flat_map(|(chunk, &scale)| {
+                chunk
+                    .iter()
+                    .map(move |&value| (value * scale).round().clamp(-127.0, 127.0) as i8)
+            })
+            .collect();
+        // per group for depthwise convolutions, per output channel otherwise
+        let int8_scale_term = match layer.layer_type.as_str() {
+            "ConvolutionDepthWise" => 1,
+            _ => 2,
+        };
+        layer.params.set(8, ParamValue::Int(int8_scale_term));
+
+        let mut values = vec![Weights::Int8(quantized)];
+        values.extend(blobs.drain(1..).map(|blob| blob.weights));
+        values.push(Weights::F32(scales.to_vec()));
+        values.push(Weights::F32(vec![bottom_scale]));
+        *blobs = modelbin::layout(layer)
+            .into_iter()
+            .zip(values)
+            .map(|(spec, weights)| WeightBlob { spec, weights })
+            .collect();
+    }
+    let weights = modelbin::write(&graph, &weights)?;
+    Ok(Model { graph, weights })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{Conv2d, GraphBuilder, Shape};
+
+    #[test]
+    fn calibrate_and_quantize() {
+        // mostly small values and a rare outlier, which KL and ACIQ clip
+        let mut histogram = vec![0; HISTOGRAM_BINS];
+        for (bin, count) in histogram.iter_mut().take(200).enumerate() {
+            *count = 1000 - 4 * bin as u64;
+        }
+        histogram[HISTOGRAM_BINS - 1] = 1;
+        let kl = kl_threshold(&histogram, 10.0);
+        assert!(kl > 0.5 && kl < 2.0, "{}", kl);
+        let aciq = aciq_threshold(10.0, 1_000_000);
+        assert!(aciq > 1.0 && aciq < 10.0, "{}", aciq);
+        assert_eq!(aciq_threshold(10.0, 1), 10.0);
+
+        let mut builder = GraphBuilder::new();
+        builder.input("data", Shape::new_3d(4, 4, 2)).unwrap();
+        let weight = vec![1.0, -2.0, 0.5, 0.25];
+        builder
+            .convolution(
+                "conv",
+                "data",
+                Conv2d::new(2, 1),
+                weight.clone(),
+                Some(vec![0.1, 0.2]),
+            )
+            .unwrap();
+        let model = builder.build().unwrap();
+
+        let conv = &model.graph.layers[1];
+        let scales = weight_scales(conv, &weight);
+        assert_eq!(scales, [63.5, 254.0]);
+        let table = CalibrationTable {
+            weight_scales: vec![("conv".to_string(), scales)],
+            bottom_scales: vec![("conv".to_string(), 12.7)],
+        };
+        assert_eq!(table.to_string(), "conv_param_0 63.5 254\nconv 12.7\n");
+        assert_eq!(
+            table.to_string().parse::<CalibrationTable>().unwrap(),
+            table
+        );
+
+        let int8 = quantize(&model, &table).unwrap();
+        let conv = &int8.graph.layers[1];
+        assert_eq!(conv.params.get_int(8, 0), 2);
+        let blobs = modelbin::read(&int8.graph, &int8.weights).unwrap();
+        let names: Vec<&str> = blobs[1].iter().map(|blob| blob.spec.name).collect();
+        assert_eq!(
+            names,
+            ["weight", "bias", "weight_int8_scales", "bottom_int8_scales"]
+        );
+        assert_eq!(blobs[1][0].weights, Weights::Int8(vec![64, -127, 127, 64]));
+        assert_eq!(blobs[1][3].weights, Weights::F32(vec![12.7]));
+    }
+}
@@ -0,0 +1,214 @@
+use ncnn_bind::*;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::str::FromStr;
+
+pub fn version() -> &'static str {
+    let c_buf = unsafe { ncnn_version() };
+    let c_str = unsafe { CStr::from_ptr(c_buf) };
+    let str_slice: &str = c_str.to_str().unwrap();
+    str_slice
+}
+
+/// A parsed ncnn version, such as `1.0.20220729`.
+///
+/// ncnn numbers its releases by date, so `patch` is the release date and versions order
+/// by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Version of the linked ncnn library.
+    ///
+    /// Fails if ncnn reports a version that is not `major.minor.patch`.
+    pub fn current() -> anyhow::Result<Version> {
+        version()
+            .parse()
+            .map_err(|e: anyhow::Error| e.context("ncnn reports an unexpected version"))
+    }
+}
+
+impl FromStr for Version {
+    type Err = anyhow::Error;
+
+    /// Parses `major.minor.patch`, ignoring a `-` or `+` suffix such as a git revision.
+    fn from_str(s: &str) -> anyhow::Result<Version> {
+        let core = s.trim().split(['-', '+']).next().unwrap_or_default();
+        let parts = core
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow::anyhow!("Invalid version `{}`: {}", s, e))?;
+        match parts[..] {
+            [major, minor, patch] => Ok(Version::new(major, minor, patch)),
+            _ => anyhow::bail!("Invalid version `{}`, expected major.minor.patch", s),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// x86 instruction set extensions ncnn will use: those it was built with kernels for,
+/// and the CPU supports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct X86Extensions {
+    pub avx: bool,
+    pub fma: bool,
+    pub xop: bool,
+    pub f16c: bool,
+    pub avx2: bool,
+    pub avx_vnni: bool,
+    pub avx512: bool,
+    pub avx512_vnni: bool,
+}
+
+impl X86Extensions {
+    fn names(&self) -> impl Iterator<Item = &'static str> {
+        [
+            (self.avx, "avx"),
+            (self.fma, "fma"),
+            (self.xop, "xop"),
+            (self.f16c, "f16c"),
+            (self.avx2, "avx2"),
+            (self.avx_vnni, "avx-vnni"),
+            (self.avx512, "avx512"),
+            (self.avx512_vnni, "avx512-vnni"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+    }
+}
+
+/// How the linked ncnn was built and what it will use on this machine.
+///
+/// ```no_run
+/// let caps = ncnn_rs::capabilities()?;
+/// println!("{}", caps);
+/// if caps.version < ncnn_rs::Version::new(1, 0, 20220729) || !caps.int8 {
+///     panic!("incompatible ncnn: {}", caps);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub version: Version,
+    /// Built with the Vulkan GPU backend.
+    pub vulkan: bool,
+    /// Built with OpenMP, or `None` if the build script could not tell.
+    pub openmp: std::option::Option<bool>,
+    /// Built with int8 inference.
+    pub int8: bool,
+    /// Built with bfloat16 storage.
+    pub bf16: bool,
+    /// fp16 storage will be used: the CPU converts fp16 (F16C on x86, NEON on ARM).
+    pub fp16_storage: bool,
+    /// fp16 arithmetic will be used: built with ARMv8.2 kernels and the CPU supports them.
+    pub fp16_arithmetic: bool,
+    /// Built to pick kernels at runtime from the CPU's features.
+    pub runtime_cpu: bool,
+    pub x86: X86Extensions,
+}
+
+/// Reports how the linked ncnn was built and what it will use on this machine.
+///
+/// Fails if the version of ncnn cannot be parsed, see [Version::current].
+pub fn capabilities() -> anyhow::Result<Capabilities> {
+    let flags = unsafe { ncnn_rs_build_flags() };
+    let built = |flag| flags & flag != 0;
+    let cpu = |support: unsafe extern "C" fn() -> std::os::raw::c_int| unsafe { support() != 0 };
+
+    let x86 = X86Extensions {
+        avx: built(NCNN_RS_BUILD_AVX) && cpu(ncnn_rs_cpu_support_x86_avx),
+        fma: built(NCNN_RS_BUILD_FMA) && cpu(ncnn_rs_cpu_support_x86_fma),
+        xop: built(NCNN_RS_BUILD_XOP) && cpu(ncnn_rs_cpu_support_x86_xop),
+        f16c: built(NCNN_RS_BUILD_F16C) && cpu(ncnn_rs_cpu_support_x86_f16c),
+        avx2: built(NCNN_RS_BUILD_AVX2) && cpu(ncnn_rs_cpu_support_x86_avx2),
+        avx_vnni: built(NCNN_RS_BUILD_AVXVNNI) && cpu(ncnn_rs_cpu_support_x86_avx_vnni),
+        avx512: built(NCNN_RS_BUILD_AVX512) && cpu(ncnn_rs_cpu_support_x86_avx512),
+        avx512_vnni: built(NCNN_RS_BUILD_AVX512VNNI) && cpu(ncnn_rs_cpu_support_x86_avx512_vnni),
+    };
+    let neon = cpu(ncnn_rs_cpu_support_arm_neon);
+
+    Ok(Capabilities {
+        version: Version::current()?,
+        vulkan: built(NCNN_RS_BUILD_VULKAN),
+        openmp: match NCNN_OPENMP {
+            Some(openmp) => Some(openmp == "ON"),
+            // ncnn's own OpenMP runtime
+            None if built(NCNN_RS_BUILD_SIMPLEOMP) => Some(true),
+            None => None,
+        },
+        int8: built(NCNN_RS_BUILD_INT8),
+        bf16: built(NCNN_RS_BUILD_BF16),
+        fp16_storage: x86.f16c || neon,
+        fp16_arithmetic: built(NCNN_RS_BUILD_ARM82) && cpu(ncnn_rs_cpu_support_arm_asimdhp),
+        runtime_cpu: built(NCNN_RS_BUILD_RUNTIME_CPU),
+        x86,
+    })
+}
+
+/// Whether the linked ncnn was built with a layer type, such as `Convolution`, by creating
+/// one.
+///
+/// Layers left out with `WITH_LAYER_<name>=OFF` keep their type name but cannot be created,
+/// so they are reported missing, as is everything when ncnn was built without `NCNN_STRING`.
+pub fn has_layer(layer_type: &str) -> bool {
+    match CString::new(layer_type) {
+        Ok(c_str) => unsafe { ncnn_rs_layer_available(c_str.as_ptr()) != 0 },
+        Err(_) => false,
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let features: Vec<&str> = [
+            (self.vulkan, "vulkan"),
+            (self.openmp == Some(true), "openmp"),
+            (self.int8, "int8"),
+            (self.bf16, "bf16"),
+            (self.fp16_storage, "fp16-storage"),
+            (self.fp16_arithmetic, "fp16-arithmetic"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .chain(self.x86.names())
+        .collect();
+        write!(f, "ncnn {} ({})", self.version, features.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_parse_and_order() {
+        let v: Version = "1.0.20220729".parse().unwrap();
+        assert_eq!(v, Version::new(1, 0, 20220729));
+        assert_eq!(v.to_string(), "1.0.20220729");
+        assert_eq!(
+            "1.0.20230223-gabc".parse::<Version>().unwrap(),
+            Version::new(1, 0, 20230223)
+        );
+        assert!(v < Version::new(1, 0, 20230223));
+        assert!(v > Version::new(0, 9, 20230223));
+        assert!("1.0".parse::<Version>().is_err());
+        assert!("1.x.3".parse::<Version>().is_err());
+    }
+}
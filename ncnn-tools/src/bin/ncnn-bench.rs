@@ -0,0 +1,723 @@
+//! Benchmark ncnn models over a matrix of runtime options, like ncnn's `benchncnn`.
+//!
+//! ```text
+//! ncnn-bench [options] <model dir | param>...
+//! ncnn-bench --save-baseline baseline.json <model dir | param>...
+//! ncnn-bench --baseline baseline.json [--tolerance <percent>] <model dir | param>...
+//! ```
+use ncnn_rs::cpu::{self, PowerSave};
+use ncnn_rs::param::{Graph, Shape};
+use ncnn_rs::{Allocator, DataReader, Mat, Net, TrackingAllocator};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const USAGE: &str = "\
+Usage: ncnn-bench [options] <model dir | param>...
+
+Runs every .param given, or found in a directory given, with each combination of the
+options below and reports its latency and the memory ncnn allocated. Weights are read from
+the .bin next to the .param, or left zero when there is none.
+
+Options:
+  --threads <n,...>                Thread counts [default: physical cores]
+  --fp16 <on|off,...>              fp16 storage and arithmetic [default: on]
+  --int8 <on|off,...>              int8 inference of int8 layers [default: on]
+  --lightmode <on|off,...>         Free blobs once consumed [default: on]
+  --powersave <all|little|big,...> Cores to bind threads to [default: all]
+  --shape <blob=w[,h[,c]]>         Input shape the param does not give, repeatable
+  --loops <n>                      Timed runs of every combination [default: 10]
+  --warmup <n>                     Untimed runs before those [default: 1]
+  --format <table|json|csv>        Output format [default: table]
+  --save-baseline <file>           Also write the results to a baseline file, as JSON
+  --baseline <file>                Compare with a baseline file, failing if any benchmark
+                                   slowed down beyond tolerance
+  --tolerance <percent>            Slowdown of the median allowed [default: 5], raised
+                                   for benchmarks whose times spread more than that";
+
+/// Parsed command line: the option matrix, run counts and the models.
+struct Args {
+    threads: Vec<u32>,
+    fp16: Vec<bool>,
+    int8: Vec<bool>,
+    lightmode: Vec<bool>,
+    powersave: Vec<PowerSave>,
+    shapes: HashMap<String, Shape>,
+    loops: usize,
+    warmup: usize,
+    format: String,
+    save_baseline: Option<String>,
+    baseline: Option<String>,
+    tolerance: f64,
+    paths: Vec<String>,
+}
+
+/// One combination of runtime options.
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    threads: u32,
+    fp16: bool,
+    int8: bool,
+    lightmode: bool,
+    powersave: PowerSave,
+}
+
+/// Latency and memory of a model under one [Config].
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    model: String,
+    threads: u32,
+    fp16: bool,
+    int8: bool,
+    lightmode: bool,
+    powersave: String,
+    loops: usize,
+    min_ms: f64,
+    median_ms: f64,
+    p95_ms: f64,
+    max_ms: f64,
+    mean_ms: f64,
+    /// Peak bytes of blobs, the inputs and outputs of layers.
+    blob_peak_bytes: usize,
+    /// Peak bytes of memory layers use while running.
+    workspace_peak_bytes: usize,
+}
+
+fn powersave_name(powersave: PowerSave) -> &'static str {
+    match powersave {
+        PowerSave::All => "all",
+        PowerSave::LittleCores => "little",
+        PowerSave::BigCores => "big",
+    }
+}
+
+/// Comma separated values of an option.
+fn parse_list<T>(
+    option: &str,
+    value: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> anyhow::Result<Vec<T>> {
+    value
+        .split(',')
+        .map(|item| parse(item.trim()))
+        .collect::<Option<_>>()
+        .ok_or_else(|| anyhow::anyhow!("Invalid `{}` value `{}`", option, value))
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_powersave(value: &str) -> Option<PowerSave> {
+    match value {
+        "all" => Some(PowerSave::All),
+        "little" => Some(PowerSave::LittleCores),
+        "big" => Some(PowerSave::BigCores),
+        _ => None,
+    }
+}
+
+/// A `blob=w,h,c` input shape.
+fn parse_shape(value: &str) -> anyhow::Result<(String, Shape)> {
+    let invalid = || anyhow::anyhow!("Invalid `--shape` value `{}`", value);
+    let (blob, extents) = value.split_once('=').ok_or_else(invalid)?;
+    let extents: Vec<usize> = parse_list("--shape", extents, |v| v.parse().ok())?;
+    let shape = match extents[..] {
+        [w] => Shape::new_1d(w),
+        [w, h] => Shape::new_2d(w, h),
+        [w, h, c] => Shape::new_3d(w, h, c),
+        [w, h, d, c] => Shape::new_4d(w, h, d, c),
+        _ => return Err(invalid()),
+    };
+    Ok((blob.to_string(), shape))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let mut parsed = Args {
+        threads: vec![cpu::physical_cpu_count() as u32],
+        fp16: vec![true],
+        int8: vec![true],
+        lightmode: vec![true],
+        powersave: vec![PowerSave::All],
+        shapes: HashMap::new(),
+        loops: 10,
+        warmup: 1,
+        format: "table".to_string(),
+        save_baseline: None,
+        baseline: None,
+        tolerance: 5.0,
+        paths: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            parsed.paths.push(arg);
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Missing value of `{}`", arg))?;
+        let count = |value: &str| {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid `{}` value `{}`", arg, value))
+        };
+        match arg.as_str() {
+            "--threads" => {
+                parsed.threads = parse_list(&arg, &value, |v| v.parse().ok().filter(|&n| n > 0))?
+            }
+            "--fp16" => parsed.fp16 = parse_list(&arg, &value, parse_switch)?,
+            "--int8" => parsed.int8 = parse_list(&arg, &value, parse_switch)?,
+            "--lightmode" => parsed.lightmode = parse_list(&arg, &value, parse_switch)?,
+            "--powersave" => parsed.powersave = parse_list(&arg, &value, parse_powersave)?,
+            "--shape" => {
+                let (blob, shape) = parse_shape(&value)?;
+                parsed.shapes.insert(blob, shape);
+            }
+            "--loops" => parsed.loops = count(&value)?,
+            "--warmup" => parsed.warmup = count(&value)?,
+            "--format" => match value.as_str() {
+                "table" | "json" | "csv" => parsed.format = value,
+                _ => anyhow::bail!("`--format` takes table, json or csv"),
+            },
+            "--save-baseline" => parsed.save_baseline = Some(value),
+            "--baseline" => parsed.baseline = Some(value),
+            "--tolerance" => {
+                parsed.tolerance = value
+                    .parse()
+                    .ok()
+                    .filter(|&tolerance: &f64| tolerance >= 0.0)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid `--tolerance` value `{}`", value))?
+            }
+            _ => anyhow::bail!("Unknown option `{}`", arg),
+        }
+    }
+    if parsed.paths.is_empty() {
+        anyhow::bail!("Missing model directory or param");
+    }
+    if parsed.loops == 0 {
+        anyhow::bail!("`--loops` must be at least 1");
+    }
+    Ok(parsed)
+}
+
+impl Args {
+    /// Every combination of the option lists.
+    fn configs(&self) -> Vec<Config> {
+        let mut configs = Vec::new();
+        for &threads in &self.threads {
+            for &fp16 in &self.fp16 {
+                for &int8 in &self.int8 {
+                    for &lightmode in &self.lightmode {
+                        for &powersave in &self.powersave {
+                            configs.push(Config {
+                                threads,
+                                fp16,
+                                int8,
+                                lightmode,
+                                powersave,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        configs
+    }
+}
+
+/// The `.param` files given, with those of directories by file name.
+fn find_models(paths: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut models = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if !path.is_dir() {
+            models.push(path.to_path_buf());
+            continue;
+        }
+        let entries = std::fs::read_dir(path)
+            .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path.display(), e))?;
+        let mut found = Vec::new();
+        for entry in entries {
+            let model = entry?.path();
+            if model.extension().is_some_and(|ext| ext == "param") {
+                found.push(model);
+            }
+        }
+        if found.is_empty() {
+            anyhow::bail!("No .param files in {}", path.display());
+        }
+        found.sort();
+        models.extend(found);
+    }
+    Ok(models)
+}
+
+/// The value at fraction `p` of sorted `values`, interpolating between neighbours.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+/// An input of the given shape, filled with ones.
+fn input_mat(shape: &Shape) -> Mat {
+    let [w, h, d, c] = [shape.w, shape.h, shape.d, shape.c].map(|v| v as i32);
+    let mut mat = match shape.dims {
+        1 => Mat::new_1d(w, None),
+        2 => Mat::new_2d(w, h, None),
+        3 => Mat::new_3d(w, h, c, None),
+        _ => Mat::new_4d(w, h, d, c, None),
+    };
+    mat.fill(1.0);
+    mat
+}
+
+/// A model's graph, inputs with their shapes and outputs.
+struct Model {
+    name: String,
+    param: PathBuf,
+    bin: Option<PathBuf>,
+    inputs: Vec<(String, Mat)>,
+    outputs: Vec<String>,
+}
+
+fn load_model(param: &Path, given: &HashMap<String, Shape>) -> anyhow::Result<Model> {
+    let graph = Graph::load(param)?;
+    let given: HashMap<String, Shape> = given
+        .iter()
+        .filter(|(blob, _)| graph.blob_index(blob).is_some())
+        .map(|(blob, &shape)| (blob.clone(), shape))
+        .collect();
+    let shapes = graph.infer_shapes_with(&given)?;
+    let mut inputs = Vec::new();
+    for blob in graph.inputs() {
+        let name = &graph.blobs[blob].name;
+        let shape = shapes[blob].ok_or_else(|| {
+            anyhow::anyhow!(
+                "The shape of input `{}` is unknown, give it with `--shape {}=w,h,c`",
+                name,
+                name
+            )
+        })?;
+        inputs.push((name.clone(), input_mat(&shape)));
+    }
+    let outputs = graph
+        .outputs()
+        .into_iter()
+        .map(|blob| graph.blobs[blob].name.clone())
+        .collect();
+    let bin = param.with_extension("bin");
+    Ok(Model {
+        name: param.file_stem().map_or_else(
+            || param.display().to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        ),
+        param: param.to_path_buf(),
+        bin: bin.exists().then_some(bin),
+        inputs,
+        outputs,
+    })
+}
+
+fn bench(model: &Model, config: Config, args: &Args) -> anyhow::Result<Record> {
+    if cpu::powersave() != config.powersave {
+        cpu::set_powersave(config.powersave)?;
+    }
+    let blobs = TrackingAllocator::new(Allocator::new());
+    let workspace = TrackingAllocator::new(Allocator::new());
+    let mut opt = ncnn_rs::Option::new();
+    opt.set_num_threads(config.threads);
+    opt.set_fp16(config.fp16);
+    opt.set_int8_inference(config.int8);
+    opt.set_lightmode(config.lightmode);
+    opt.set_blob_allocator(&Allocator::new_custom(blobs.clone()));
+    opt.set_workspace_allocator(&Allocator::new_custom(workspace.clone()));
+
+    let mut net = Net::new();
+    net.set_option(&opt);
+    net.load_param(&model.param.to_string_lossy())?;
+    match &model.bin {
+        Some(bin) => net.load_model(&bin.to_string_lossy())?,
+        None => net.load_model_datareader(&DataReader::empty())?,
+    }
+
+    let mut run = || -> anyhow::Result<()> {
+        let mut ex = net.create_extractor();
+        for (name, mat) in &model.inputs {
+            ex.input(name, mat)?;
+        }
+        for name in &model.outputs {
+            let mut out = Mat::new();
+            ex.extract(name, &mut out)?;
+        }
+        Ok(())
+    };
+    for _ in 0..args.warmup {
+        run()?;
+    }
+    blobs.reset_stats();
+    workspace.reset_stats();
+    let mut times = Vec::with_capacity(args.loops);
+    for _ in 0..args.loops {
+        let start = Instant::now();
+        run()?;
+        times.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    times.sort_by(f64::total_cmp);
+
+    Ok(Record {
+        model: model.name.clone(),
+        threads: config.threads,
+        fp16: config.fp16,
+        int8: config.int8,
+        lightmode: config.lightmode,
+        powersave: powersave_name(config.powersave).to_string(),
+        loops: args.loops,
+        min_ms: times[0],
+        median_ms: percentile(&times, 0.5),
+        p95_ms: percentile(&times, 0.95),
+        max_ms: times[times.len() - 1],
+        mean_ms: times.iter().sum::<f64>() / times.len() as f64,
+        blob_peak_bytes: blobs.stats().peak_bytes,
+        workspace_peak_bytes: workspace.stats().peak_bytes,
+    })
+}
+
+fn switch(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn print_table(records: &[Record]) {
+    println!(
+        "{:<28} {:>7} {:>4} {:>4} {:>5} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "model",
+        "threads",
+        "fp16",
+        "int8",
+        "light",
+        "cores",
+        "min ms",
+        "median ms",
+        "p95 ms",
+        "max ms",
+        "peak MiB"
+    );
+    for r in records {
+        let peak = (r.blob_peak_bytes + r.workspace_peak_bytes) as f64 / (1 << 20) as f64;
+        println!(
+            "{:<28} {:>7} {:>4} {:>4} {:>5} {:>6} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.1}",
+            r.model,
+            r.threads,
+            switch(r.fp16),
+            switch(r.int8),
+            switch(r.lightmode),
+            r.powersave,
+            r.min_ms,
+            r.median_ms,
+            r.p95_ms,
+            r.max_ms,
+            peak
+        );
+    }
+}
+
+/// A CSV field, quoted when it has to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn print_csv(records: &[Record]) {
+    println!("model,threads,fp16,int8,lightmode,powersave,loops,min_ms,median_ms,p95_ms,max_ms,mean_ms,blob_peak_bytes,workspace_peak_bytes");
+    for r in records {
+        println!(
+            "{},{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.4},{:.4},{},{}",
+            csv_field(&r.model),
+            r.threads,
+            r.fp16,
+            r.int8,
+            r.lightmode,
+            r.powersave,
+            r.loops,
+            r.min_ms,
+            r.median_ms,
+            r.p95_ms,
+            r.max_ms,
+            r.mean_ms,
+            r.blob_peak_bytes,
+            r.workspace_peak_bytes
+        );
+    }
+}
+
+/// Whether two records are of the same model run with the same options.
+fn same_benchmark(a: &Record, b: &Record) -> bool {
+    (
+        &a.model,
+        a.threads,
+        a.fp16,
+        a.int8,
+        a.lightmode,
+        &a.powersave,
+    ) == (
+        &b.model,
+        b.threads,
+        b.fp16,
+        b.int8,
+        b.lightmode,
+        &b.powersave,
+    )
+}
+
+/// How far, in ms, the median may rise over the baseline's: `tolerance` percent of it, or
+/// the spread from median to p95 of both runs where that is larger, so noisy benchmarks
+/// have to slow down by more than their noise.
+fn allowed_slowdown(baseline: &Record, record: &Record, tolerance: f64) -> f64 {
+    let noise = (baseline.p95_ms - baseline.median_ms) + (record.p95_ms - record.median_ms);
+    (baseline.median_ms * tolerance / 100.0).max(noise)
+}
+
+/// Prints every record next to its baseline, then the baseline entries no record matched,
+/// returning how many records slowed down beyond tolerance.
+fn compare(
+    records: &[Record],
+    baseline: &[Record],
+    tolerance: f64,
+    out: &mut dyn Write,
+) -> std::io::Result<usize> {
+    writeln!(
+        out,
+        "{:<28} {:>7} {:>4} {:>4} {:>5} {:>6} {:>9} {:>9} {:>8} {:>8}  {:<6}",
+        "model",
+        "threads",
+        "fp16",
+        "int8",
+        "light",
+        "cores",
+        "base ms",
+        "median ms",
+        "change",
+        "allowed",
+        "status"
+    )?;
+    // the baseline and new median, the change and the allowed slowdown
+    let mut row = |r: &Record, cells: [String; 4], status: &str| {
+        let [base_ms, median_ms, change, allowed] = cells;
+        writeln!(
+            out,
+            "{:<28} {:>7} {:>4} {:>4} {:>5} {:>6} {:>9} {:>9} {:>8} {:>8}  {}",
+            r.model,
+            r.threads,
+            switch(r.fp16),
+            switch(r.int8),
+            switch(r.lightmode),
+            r.powersave,
+            base_ms,
+            median_ms,
+            change,
+            allowed,
+            status
+        )
+    };
+    let none = || "-".to_string();
+    let mut slower = 0;
+    for r in records {
+        let median_ms = format!("{:.2}", r.median_ms);
+        let base = match baseline.iter().find(|base| same_benchmark(base, r)) {
+            Some(base) => base,
+            None => {
+                row(r, [none(), median_ms, none(), none()], "new")?;
+                continue;
+            }
+        };
+        let allowed = allowed_slowdown(base, r, tolerance);
+        let change = r.median_ms - base.median_ms;
+        let status = if change > allowed {
+            slower += 1;
+            "SLOWER"
+        } else if -change > allowed {
+            "faster"
+        } else {
+            "ok"
+        };
+        let percent = |ms: f64| format!("{:+.1}%", ms / base.median_ms * 100.0);
+        let base_ms = format!("{:.2}", base.median_ms);
+        row(
+            r,
+            [base_ms, median_ms, percent(change), percent(allowed)],
+            status,
+        )?;
+    }
+    for base in baseline {
+        if !records.iter().any(|r| same_benchmark(base, r)) {
+            let base_ms = format!("{:.2}", base.median_ms);
+            row(base, [base_ms, none(), none(), none()], "missing")?;
+        }
+    }
+    Ok(slower)
+}
+
+/// Benchmarks every model, reporting failures on stderr; fails if any model failed or
+/// slowed down beyond tolerance of the baseline.
+fn run(args: Args) -> anyhow::Result<()> {
+    let baseline: Option<Vec<Record>> = match &args.baseline {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path, e))?;
+            let baseline = serde_json::from_str(&json)
+                .map_err(|e| anyhow::anyhow!("Error reading baseline {}: {}", path, e))?;
+            Some(baseline)
+        }
+        None => None,
+    };
+    let configs = args.configs();
+    let mut records = Vec::new();
+    let mut failures = 0;
+    for param in find_models(&args.paths)? {
+        let model = match load_model(&param, &args.shapes) {
+            Ok(model) => model,
+            Err(e) => {
+                eprintln!("{}: {:#}", param.display(), e);
+                failures += 1;
+                continue;
+            }
+        };
+        for &config in &configs {
+            match bench(&model, config, &args) {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    eprintln!("{} {:?}: {:#}", param.display(), config, e);
+                    failures += 1;
+                }
+            }
+        }
+    }
+    match args.format.as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&records)?),
+        "csv" => print_csv(&records),
+        _ => print_table(&records),
+    }
+    if let Some(path) = &args.save_baseline {
+        std::fs::write(path, serde_json::to_string_pretty(&records)?)
+            .map_err(|e| anyhow::anyhow!("Error writing {}: {}", path, e))?;
+    }
+    // compare what did run before reporting failures, which show up as missing
+    let slower = match baseline {
+        // keep JSON and CSV output on stdout parseable
+        Some(baseline) if args.format == "table" => {
+            println!();
+            compare(&records, &baseline, args.tolerance, &mut std::io::stdout())?
+        }
+        Some(baseline) => compare(&records, &baseline, args.tolerance, &mut std::io::stderr())?,
+        None => 0,
+    };
+    if failures > 0 {
+        anyhow::bail!("{} benchmarks failed, see above", failures);
+    }
+    if slower > 0 {
+        anyhow::bail!(
+            "{} benchmarks slowed down beyond tolerance of the baseline",
+            slower
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some("help" | "--help" | "-h") = args.first().map(String::as_str) {
+        println!("{}", USAGE);
+        return;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(model: &str, median_ms: f64, p95_ms: f64) -> Record {
+        Record {
+            model: model.to_string(),
+            threads: 4,
+            fp16: true,
+            int8: true,
+            lightmode: true,
+            powersave: "all".to_string(),
+            loops: 10,
+            min_ms: median_ms,
+            median_ms,
+            p95_ms,
+            max_ms: p95_ms,
+            mean_ms: median_ms,
+            blob_peak_bytes: 0,
+            workspace_peak_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn compare_with_baseline() {
+        // steady runs get the tolerance, noisy ones their spread
+        let steady = record("a", 10.0, 10.1);
+        assert!((allowed_slowdown(&steady, &record("a", 10.0, 10.1), 5.0) - 0.5).abs() < 1e-9);
+        let noisy = record("a", 10.0, 12.0);
+        assert!((allowed_slowdown(&noisy, &record("a", 11.0, 12.0), 5.0) - 3.0).abs() < 1e-9);
+
+        let baseline = [
+            record("slower", 10.0, 10.1),
+            record("ok", 10.0, 10.1),
+            record("faster", 10.0, 10.1),
+            record("missing", 10.0, 10.1),
+        ];
+        let records = [
+            record("slower", 11.0, 11.1),
+            record("ok", 10.3, 10.4),
+            record("faster", 9.0, 9.1),
+            record("new", 10.0, 10.1),
+        ];
+        let mut out = Vec::new();
+        assert_eq!(compare(&records, &baseline, 5.0, &mut out).unwrap(), 1);
+        let out = String::from_utf8(out).unwrap();
+        let statuses: Vec<(&str, &str)> = out
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                (fields[0], fields[fields.len() - 1])
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("slower", "SLOWER"),
+                ("ok", "ok"),
+                ("faster", "faster"),
+                ("new", "new"),
+                ("missing", "missing"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Quantize ncnn models to int8, like ncnn's `ncnn2table` and `ncnn2int8`.
+//!
+//! ```text
+//! ncnn-int8 table [options] <param> <bin> <image dir> <table>
+//! ncnn-int8 quantize <param> <bin> <table> <int8 param> <int8 bin>
+//! ```
+use ncnn_rs::param::Model;
+use ncnn_rs::quantize::{self, CalibrationTable, Method};
+use ncnn_rs::Net;
+use ncnn_tools::{Preprocess, PREPROCESS_OPTIONS};
+use std::path::PathBuf;
+
+/// The usage text, listing the preprocessing options the tools share.
+fn usage() -> String {
+    format!(
+        "\
+Usage: ncnn-int8 <command> [options] <path>...
+
+Commands:
+  table      Run the model over a directory of images and write a calibration table
+             ncnn-int8 table [options] <param> <bin> <image dir> <table>
+  quantize   Store the layers in a calibration table as int8
+             ncnn-int8 quantize <param> <bin> <table> <int8 param> <int8 bin>
+
+Options of `table`:
+  --method <kl|aciq|minmax>   How to pick activation ranges [default: kl]
+  --input <name>              Input blob [default: data]
+{}
+  --threads <n>               Threads to run the model with",
+        PREPROCESS_OPTIONS
+    )
+}
+
+/// Parsed command line: the command, its options and its positional arguments.
+struct Args {
+    command: String,
+    method: Method,
+    input: String,
+    preprocess: Preprocess,
+    threads: Option<u32>,
+    paths: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let command = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Missing command"))?;
+    let mut parsed = Args {
+        command,
+        method: Method::Kl,
+        input: "data".to_string(),
+        preprocess: Preprocess::default(),
+        threads: None,
+        paths: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            parsed.paths.push(arg);
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Missing value of `{}`", arg))?;
+        if parsed.preprocess.parse_option(&arg, &value)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--method" => parsed.method = value.parse()?,
+            "--input" => parsed.input = value,
+            "--threads" => {
+                let threads = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid `--threads` value `{}`", value))?;
+                parsed.threads = Some(threads);
+            }
+            _ => anyhow::bail!("Unknown option `{}`", arg),
+        }
+    }
+    Ok(parsed)
+}
+
+/// The images of a directory, by file name.
+fn list_images(dir: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| anyhow::anyhow!("Error reading {}: {}", dir, e))?;
+    let mut images = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        if let Some("jpg" | "jpeg" | "png" | "bmp") = extension.as_deref() {
+            images.push(path);
+        }
+    }
+    images.sort();
+    if images.is_empty() {
+        anyhow::bail!("No jpg, png or bmp images in {}", dir);
+    }
+    Ok(images)
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    match args.command.as_str() {
+        "table" => {
+            let (model, images, table_path) = match args.paths.as_slice() {
+                [param, bin, dir, table] => (Model::load(param, bin)?, list_images(dir)?, table),
+                _ => anyhow::bail!("`table` takes a param, a bin, an image directory and a table"),
+            };
+            let mut net = Net::new();
+            if let Some(threads) = args.threads {
+                let mut opt = ncnn_rs::Option::new();
+                opt.set_num_threads(threads);
+                net.set_option(&opt);
+            }
+            model.load_into(&mut net)?;
+            let table = quantize::calibrate(&mut net, &model, args.method, images.len(), |i| {
+                Ok(vec![(
+                    args.input.clone(),
+                    args.preprocess.load_image(&images[i])?,
+                )])
+            })?;
+            table.save(table_path)?;
+            println!(
+                "{} layers calibrated over {} images",
+                table.bottom_scales.len(),
+                images.len()
+            );
+        }
+        "quantize" => {
+            let (model, table, out_param, out_bin) = match args.paths.as_slice() {
+                [param, bin, table, out_param, out_bin] => (
+                    Model::load(param, bin)?,
+                    CalibrationTable::load(table)?,
+                    out_param,
+                    out_bin,
+                ),
+                _ => anyhow::bail!(
+                    "`quantize` takes a param, a bin, a table and the param and bin to write"
+                ),
+            };
+            let int8 = quantize::quantize(&model, &table)?;
+            int8.save(out_param, out_bin)?;
+            println!(
+                "{} -> {} bytes of weights",
+                model.weights.len(),
+                int8.weights.len()
+            );
+        }
+        "help" | "--help" | "-h" => println!("{}", usage()),
+        command => anyhow::bail!("Unknown command `{}`, see `ncnn-int8 help`", command),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, usage());
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
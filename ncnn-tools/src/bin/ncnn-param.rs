@@ -0,0 +1,186 @@
+//! Inspect and optimize ncnn `.param` files without loading them into ncnn.
+//!
+//! ```text
+//! ncnn-param summary [--json] <param>
+//! ncnn-param dot <param> > model.dot
+//! ncnn-param json <param>
+//! ncnn-param diff [--json] <old param> <new param>
+//! ncnn-param lint [--json] <param>
+//! ncnn-param optimize [--json] <param> <bin> <out param> <out bin>
+//! ncnn-param weights <param> <bin>
+//! ncnn-param fp16 <param> <bin> <out bin>
+//! ```
+use ncnn_rs::{modelbin, param};
+
+const USAGE: &str = "\
+Usage: ncnn-param <command> [options] <param>...
+
+Commands:
+  summary    Per-layer output shapes, MACs, FLOPs, weights and peak memory
+  dot        Graphviz DOT of the graph, for `dot -Tsvg`
+  json       The graph as JSON, layers referring to blobs by name
+  diff       Added, removed and changed layers and renamed blobs between two params
+  lint       Problems the linked ncnn would reject or misread, by line; fails on errors
+  optimize   Fuse and remove layers like ncnnoptimize, writing a new param and bin
+  weights    Storage, size and value range of every weight blob in a bin
+  fp16       Store the weights of a bin as fp16, halving its size
+
+Options:
+  --json     Print JSON instead of text";
+
+/// Parsed command line: the command, its flags and its positional arguments.
+struct Args {
+    command: String,
+    json: bool,
+    paths: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let command = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Missing command"))?;
+    let mut parsed = Args {
+        command,
+        json: false,
+        paths: Vec::new(),
+    };
+    for arg in args {
+        match arg.as_str() {
+            "--json" => parsed.json = true,
+            flag if flag.starts_with("--") => anyhow::bail!("Unknown option `{}`", flag),
+            _ => parsed.paths.push(arg),
+        }
+    }
+    Ok(parsed)
+}
+
+/// The single path argument of a command.
+fn one_path(args: &Args) -> anyhow::Result<&str> {
+    match args.paths.as_slice() {
+        [path] => Ok(path),
+        _ => anyhow::bail!("`{}` takes one param file", args.command),
+    }
+}
+
+fn read(path: &str) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| anyhow::anyhow!("Error reading {}: {}", path, e))
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    match args.command.as_str() {
+        "summary" => {
+            let summary = param::model_summary(one_path(&args)?)?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                println!("{}", summary);
+            }
+        }
+        "dot" => print!("{}", param::Graph::load(one_path(&args)?)?.to_dot()),
+        "json" => println!("{}", param::Graph::load(one_path(&args)?)?.to_json()),
+        "diff" => {
+            let (old, new) = match args.paths.as_slice() {
+                [old, new] => (param::Graph::load(old)?, param::Graph::load(new)?),
+                _ => anyhow::bail!("`diff` takes two param files"),
+            };
+            let diff = param::diff(&old, &new);
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else {
+                println!("{}", diff);
+            }
+        }
+        "lint" => {
+            let path = one_path(&args)?;
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path, e))?;
+            let diagnostics = param::validate_with(&text, ncnn_rs::has_layer);
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+            } else {
+                for diagnostic in &diagnostics {
+                    println!("{}: {}", path, diagnostic);
+                }
+            }
+            let errors = diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.severity == param::Severity::Error)
+                .count();
+            if errors > 0 {
+                anyhow::bail!("{} errors in {}", errors, path);
+            }
+        }
+        "optimize" => {
+            let (model, out_param, out_bin) = match args.paths.as_slice() {
+                [param, bin, out_param, out_bin] => {
+                    (param::Model::load(param, bin)?, out_param, out_bin)
+                }
+                _ => anyhow::bail!("`optimize` takes a param, a bin and the two to write"),
+            };
+            let (optimized, report) = model.optimize()?;
+            optimized.save(out_param, out_bin)?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", report);
+            }
+        }
+        "weights" => {
+            let (graph, bin) = match args.paths.as_slice() {
+                [param, bin] => (param::Graph::load(param)?, read(bin)?),
+                _ => anyhow::bail!("`weights` takes a param and its bin"),
+            };
+            let weights = modelbin::read(&graph, &bin)?;
+            for (layer, blobs) in graph.layers.iter().zip(&weights) {
+                for blob in blobs {
+                    let values = blob.weights.to_f32();
+                    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+                    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                    println!(
+                        "{:<24} {:<20} {:<5} {:>10} {:>12} {:>12}",
+                        layer.name,
+                        blob.spec.name,
+                        blob.weights.storage(),
+                        blob.weights.len(),
+                        min,
+                        max
+                    );
+                }
+            }
+        }
+        "fp16" => {
+            let (graph, bin, out_bin) = match args.paths.as_slice() {
+                [param, bin, out_bin] => (param::Graph::load(param)?, read(bin)?, out_bin),
+                _ => anyhow::bail!("`fp16` takes a param, its bin and the bin to write"),
+            };
+            let mut weights = modelbin::read(&graph, &bin)?;
+            let converted = modelbin::convert_to_f16(&mut weights);
+            let half = modelbin::write(&graph, &weights)?;
+            std::fs::write(out_bin, &half)
+                .map_err(|e| anyhow::anyhow!("Error writing {}: {}", out_bin, e))?;
+            println!(
+                "{} blobs converted, {} -> {} bytes",
+                converted,
+                bin.len(),
+                half.len()
+            );
+        }
+        "help" | "--help" | "-h" => println!("{}", USAGE),
+        command => anyhow::bail!("Unknown command `{}`, see `ncnn-param help`", command),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
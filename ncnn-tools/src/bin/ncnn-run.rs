@@ -0,0 +1,262 @@
+//! Run an ncnn model on an image and print its top classes or write its outputs.
+//!
+//! ```text
+//! ncnn-run --param <param> --bin <bin> [options] <image>
+//! ```
+use ncnn_rs::param::Graph;
+use ncnn_rs::{npy, Mat, Net};
+use ncnn_tools::{Preprocess, PREPROCESS_OPTIONS};
+use std::path::PathBuf;
+
+/// The usage text, listing the preprocessing options the tools share.
+fn usage() -> String {
+    format!(
+        "\
+Usage: ncnn-run --param <param> --bin <bin> [options] <image>
+
+Runs a model on one image and prints the top classes of every output.
+
+Options:
+  --param <file>              Model .param
+  --bin <file>                Model .bin
+  --input <name>              Input blob [default: the model's only input]
+  --output <name,...>         Output blobs [default: the blobs no layer consumes]
+{}
+  --threads <n>               Threads to run the model with
+  --top <k>                   Classes to print per output [default: 5]
+  --labels <file>             Class names, one per line
+  --json                      Print the outputs and their top classes as JSON
+  --npy <dir>                 Write every output to <dir>/<blob>.npy
+  --dump <dir>                Write every blob of the model to <dir> as .npy, with a manifest",
+        PREPROCESS_OPTIONS
+    )
+}
+
+/// Parsed command line.
+struct Args {
+    param: Option<String>,
+    bin: Option<String>,
+    input: Option<String>,
+    outputs: Vec<String>,
+    preprocess: Preprocess,
+    threads: Option<u32>,
+    top: usize,
+    labels: Option<String>,
+    json: bool,
+    npy: Option<PathBuf>,
+    dump: Option<PathBuf>,
+    image: Option<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let mut parsed = Args {
+        param: None,
+        bin: None,
+        input: None,
+        outputs: Vec::new(),
+        preprocess: Preprocess::default(),
+        threads: None,
+        top: 5,
+        labels: None,
+        json: false,
+        npy: None,
+        dump: None,
+        image: None,
+    };
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if parsed.image.replace(PathBuf::from(&arg)).is_some() {
+                anyhow::bail!("Only one image can be given, not also `{}`", arg);
+            }
+            continue;
+        }
+        if arg == "--json" {
+            parsed.json = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Missing value of `{}`", arg))?;
+        if parsed.preprocess.parse_option(&arg, &value)? {
+            continue;
+        }
+        let count = |value: &str| {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid `{}` value `{}`", arg, value))
+        };
+        match arg.as_str() {
+            "--param" => parsed.param = Some(value),
+            "--bin" => parsed.bin = Some(value),
+            "--input" => parsed.input = Some(value),
+            "--output" => parsed.outputs = value.split(',').map(str::to_string).collect(),
+            "--threads" => parsed.threads = Some(count(&value)?),
+            "--top" => parsed.top = count(&value)? as usize,
+            "--labels" => parsed.labels = Some(value),
+            "--npy" => parsed.npy = Some(PathBuf::from(value)),
+            "--dump" => parsed.dump = Some(PathBuf::from(value)),
+            _ => anyhow::bail!("Unknown option `{}`", arg),
+        }
+    }
+    if parsed.param.is_none() || parsed.bin.is_none() {
+        anyhow::bail!("`--param` and `--bin` are required");
+    }
+    if parsed.image.is_none() {
+        anyhow::bail!("Missing image");
+    }
+    Ok(parsed)
+}
+
+/// Indices of the `k` largest values, largest first.
+fn top_k(values: &[f32], k: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    indices.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+    indices.truncate(k);
+    indices
+}
+
+fn read_labels(path: &str) -> anyhow::Result<Vec<String>> {
+    let labels = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path, e))?;
+    Ok(labels.lines().map(|line| line.trim().to_string()).collect())
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    let (param, bin) = (args.param.as_deref().unwrap(), args.bin.as_deref().unwrap());
+    let graph = Graph::load(param)?;
+    let input = match args.input {
+        Some(input) => input,
+        None => match graph.inputs()[..] {
+            [blob] => graph.blobs[blob].name.clone(),
+            _ => anyhow::bail!("The model has several inputs or none, pick one with `--input`"),
+        },
+    };
+    let outputs = if args.outputs.is_empty() {
+        graph
+            .outputs()
+            .into_iter()
+            .map(|blob| graph.blobs[blob].name.clone())
+            .collect()
+    } else {
+        args.outputs.clone()
+    };
+    let labels = args.labels.as_deref().map(read_labels).transpose()?;
+    let image = args.preprocess.load_image(args.image.as_deref().unwrap())?;
+
+    let mut net = Net::new();
+    if let Some(threads) = args.threads {
+        let mut opt = ncnn_rs::Option::new();
+        opt.set_num_threads(threads);
+        net.set_option(&opt);
+    }
+    net.load_param(param)?;
+    net.load_model(bin)?;
+    let mut ex = net.create_extractor();
+    ex.input(&input, &image)?;
+    if let Some(dir) = &args.dump {
+        let dumped = npy::dump(&ex, dir)?;
+        eprintln!("{} blobs written to {}", dumped.len(), dir.display());
+    }
+
+    let mut results = Vec::new();
+    for name in &outputs {
+        let mut mat = Mat::new();
+        ex.extract(name, &mut mat)?;
+        if let Some(dir) = &args.npy {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| anyhow::anyhow!("Error creating {}: {}", dir.display(), e))?;
+            npy::write(&mat, dir.join(format!("{}.npy", npy::file_stem(name))))?;
+        }
+        let values = mat
+            .to_vec()
+            .map_err(|e| e.context(format!("Error reading output `{}`", name)))?;
+        let top = top_k(&values, args.top);
+        results.push((name, mat.shape(), values, top));
+    }
+
+    let label = |index: usize| -> Option<&str> {
+        labels
+            .as_ref()
+            .and_then(|labels| labels.get(index))
+            .map(String::as_str)
+    };
+    if args.json {
+        let outputs: Vec<serde_json::Value> = results
+            .iter()
+            .map(|(name, shape, values, top)| {
+                let top: Vec<serde_json::Value> = top
+                    .iter()
+                    .map(
+                        |&i| serde_json::json!({"index": i, "label": label(i), "score": values[i]}),
+                    )
+                    .collect();
+                serde_json::json!({
+                    "name": name,
+                    "shape": shape.extents(),
+                    "top": top,
+                    "values": values,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&outputs)?);
+        return Ok(());
+    }
+    for (name, shape, values, top) in &results {
+        println!("{} {}", name, shape);
+        for (rank, &i) in top.iter().enumerate() {
+            match label(i) {
+                Some(label) => println!("  {}. {:>6} {:>12.6}  {}", rank + 1, i, values[i], label),
+                None => println!("  {}. {:>6} {:>12.6}", rank + 1, i, values[i]),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some("help" | "--help" | "-h") = args.first().map(String::as_str) {
+        println!("{}", usage());
+        return;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, usage());
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> anyhow::Result<Args> {
+        parse_args(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn args_and_top_k() {
+        let args = parse("--param a.param --bin a.bin --top 3 --json cat.jpg").unwrap();
+        assert_eq!(args.param.as_deref(), Some("a.param"));
+        assert_eq!(args.bin.as_deref(), Some("a.bin"));
+        assert_eq!((args.top, args.json), (3, true));
+        assert_eq!(args.image, Some(PathBuf::from("cat.jpg")));
+
+        let error = |args: &str| parse(args).err().unwrap().to_string();
+        assert!(error("--param a.param --bin a.bin cat.jpg dog.jpg").contains("`dog.jpg`"));
+        assert!(error("--param a.param cat.jpg").contains("are required"));
+        assert!(error("--bin a.bin cat.jpg").contains("are required"));
+        assert!(error("--param a.param --bin a.bin").contains("Missing image"));
+
+        let values = [0.1, 0.7, -1.0, 0.2];
+        assert_eq!(top_k(&values, 2), vec![1, 3]);
+        assert_eq!(top_k(&values, 10), vec![1, 3, 0, 2]);
+        assert!(top_k(&[], 5).is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+//! Parts shared by the ncnn command line tools.
+use ncnn_rs::{Mat, MatPixelType};
+use std::path::Path;
+
+/// Help of the options [Preprocess::parse_option] takes.
+pub const PREPROCESS_OPTIONS: &str =
+    "  --size <w,h>                Resize images to this size [default: keep]
+  --mean <a,b,c>              Subtracted from every channel
+  --norm <a,b,c>              Multiplied with every channel after the mean
+  --pixel <rgb|bgr|gray>      Channel order the model takes [default: bgr]";
+
+/// Comma separated numbers.
+pub fn parse_list<T: std::str::FromStr>(option: &str, value: &str) -> anyhow::Result<Vec<T>> {
+    value
+        .split(',')
+        .map(|item| item.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| anyhow::anyhow!("Invalid `{}` value `{}`", option, value))
+}
+
+/// How to turn an image into the input of a model.
+#[derive(Debug, Clone)]
+pub struct Preprocess {
+    pub size: Option<(i32, i32)>,
+    pub mean: Option<Vec<f32>>,
+    pub norm: Option<Vec<f32>>,
+    pub pixel: String,
+}
+
+impl Default for Preprocess {
+    fn default() -> Self {
+        Preprocess {
+            size: None,
+            mean: None,
+            norm: None,
+            pixel: "bgr".to_string(),
+        }
+    }
+}
+
+impl Preprocess {
+    /// Takes one of [PREPROCESS_OPTIONS], returning whether `option` was one.
+    pub fn parse_option(&mut self, option: &str, value: &str) -> anyhow::Result<bool> {
+        match option {
+            "--size" => match parse_list(option, value)?[..] {
+                [w, h] => self.size = Some((w, h)),
+                _ => anyhow::bail!("`--size` takes a width and a height"),
+            },
+            "--mean" => self.mean = Some(parse_list(option, value)?),
+            "--norm" => self.norm = Some(parse_list(option, value)?),
+            "--pixel" => match value {
+                "rgb" | "bgr" | "gray" => self.pixel = value.to_string(),
+                _ => anyhow::bail!("`--pixel` takes rgb, bgr or gray"),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// An image as the model takes it, resized, reordered and normalized.
+    pub fn load_image(&self, path: &Path) -> anyhow::Result<Mat> {
+        let image = image::open(path)
+            .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path.display(), e))?;
+        let (w, h) = (image.width() as i32, image.height() as i32);
+        let (pixels, pixel_type, channels) = match self.pixel.as_str() {
+            "gray" => (image.to_luma8().into_raw(), MatPixelType::GRAY.to_int(), 1),
+            "rgb" => (image.to_rgb8().into_raw(), MatPixelType::RGB.to_int(), 3),
+            _ => (
+                image.to_rgb8().into_raw(),
+                MatPixelType::RGB.convert(&MatPixelType::BGR),
+                3,
+            ),
+        };
+        let size = self.size.unwrap_or((w, h));
+        let mut mat =
+            Mat::from_pixels_resize(&pixels, pixel_type, (w, h), w * channels, size, None)?;
+        if self.mean.is_some() || self.norm.is_some() {
+            let mean = self.mean.clone().unwrap_or(vec![0.0; channels as usize]);
+            let norm = self.norm.clone().unwrap_or(vec![1.0; channels as usize]);
+            if mean.len() != channels as usize || norm.len() != channels as usize {
+                anyhow::bail!("`--mean` and `--norm` take {} values", channels);
+            }
+            mat.substract_mean_normalize(&mean, &norm);
+        }
+        Ok(mat)
+    }
+}